@@ -1,3 +1,4 @@
+#![no_std]
 /// This is a wrapper type for a CP437 (OEM437) string
 /// https://en.wikipedia.org/wiki/Code_page_437
 /// Note any u8 is a valid character in this encoding
@@ -30,6 +31,13 @@ where
     }
 }
 
+impl<'a> OEM437Str<'a> {
+    /// The underlying bytes, for the full lifetime of the borrow
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
 /// Allows the string to be treated as a slice of u8
 impl<'a> AsRef<[u8]> for OEM437Str<'a> {
     fn as_ref(&self) -> &'a [u8] {
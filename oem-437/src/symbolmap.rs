@@ -11,7 +11,7 @@ pub fn char_to_oem437(character: char) -> Option<u8> {
     } else {
         let lower = 0..0x20u8;
         let upper = 0x7F..0xFFu8;
-        let all = lower.into_iter().chain(upper.into_iter());
+        let all = lower.chain(upper);
         for input in all.into_iter() {
             let x = oem437_lookup_unicode_char(input) as u32;
             if as_num == x {
@@ -2,9 +2,9 @@ use crate::{
     symbolmap::{char_to_oem437, oem437_lookup_unicode_char},
     OEM437Str,
 };
+use core::convert::TryFrom;
 use core::fmt::Write;
 use core::str::from_utf8;
-use std::convert::TryFrom;
 #[derive(PartialEq, Copy, Clone)]
 
 /// New type in which we are happy to treat all characters as symbols
@@ -112,7 +112,7 @@ where
 /// Display is only implemented when we are happy with it
 /// being a symbol
 impl<'a> core::fmt::Display for OEM437Symbols<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for c in self.to_utf8() {
             f.write_char(c)?;
         }
@@ -1,5 +1,5 @@
 //! https://linux.die.net/man/1/unzip
-use super::zipr::Opt as ZiprOpt;
+use super::zipr::{CheckOptions, ExtractOptions, Opt as ZiprOpt};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -28,8 +28,23 @@ struct Opt {
     #[structopt(short, help = "list archive files (short format)")]
     list: bool,
 
+    #[structopt(short = "t", help = "test archive files")]
+    test: bool,
+
     #[structopt(short = "z", help = "display archive comment")]
     comment: bool,
+
+    #[structopt(short = "o", help = "overwrite existing files without prompting")]
+    overwrite: bool,
+
+    #[structopt(short = "D", help = "skip restoration of timestamps")]
+    no_times: bool,
+
+    #[structopt(
+        short = "X",
+        help = "restore unix permissions (the default, kept for compatibility)"
+    )]
+    _permissions: bool,
 }
 
 /// Parses the unzip style args into the zipr opt.
@@ -38,7 +53,15 @@ struct Opt {
 pub fn parse_args() -> ZiprOpt {
     let opt = Opt::from_args();
     if opt.list {
-        ZiprOpt::List { file: opt.zipfile }
+        ZiprOpt::List {
+            file: opt.zipfile,
+            check: CheckOptions::default(),
+        }
+    } else if opt.test {
+        ZiprOpt::Test {
+            file: opt.zipfile,
+            check: CheckOptions::default(),
+        }
     } else if opt.comment {
         ZiprOpt::ShowComment { file: opt.zipfile }
     } else {
@@ -46,6 +69,12 @@ pub fn parse_args() -> ZiprOpt {
             file: opt.zipfile,
             files: opt.files,
             output: opt.exdir,
+            options: ExtractOptions {
+                overwrite: opt.overwrite,
+                no_times: opt.no_times,
+                ..Default::default()
+            },
+            check: CheckOptions::default(),
         }
     }
 }
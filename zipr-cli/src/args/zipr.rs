@@ -1,6 +1,10 @@
-use std::{fmt::Display, num::ParseIntError, path::PathBuf, str::FromStr};
+use std::{convert::TryFrom, fmt::Display, num::ParseIntError, path::PathBuf, str::FromStr};
 use structopt::StructOpt;
-use zipr::data::CompressionMethod;
+use zipr::{
+    data::CompressionMethod,
+    domain::{ConsistencyPolicy, StructurePolicy},
+    std::Symlinks,
+};
 
 #[derive(Debug)]
 pub enum InspectKind {
@@ -25,6 +29,46 @@ impl FromStr for Hex {
     }
 }
 
+/// The size of each file of a split zip, eg 2g, 100m or 64k
+pub struct SegmentSize(pub u32);
+
+/// Split zip files can't be smaller than this
+const MIN_SEGMENT_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug)]
+pub struct SegmentSizeParseError(String);
+
+impl Display for SegmentSizeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "segment size must be between 64k and 4g less a byte: {}",
+            self.0
+        )
+    }
+}
+
+impl FromStr for SegmentSize {
+    type Err = SegmentSizeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || SegmentSizeParseError(s.to_owned());
+        let lower = s.to_lowercase();
+        let (number, unit) = match lower.char_indices().last() {
+            Some((i, 'k')) => (&lower[..i], 1 << 10),
+            Some((i, 'm')) => (&lower[..i], 1 << 20),
+            Some((i, 'g')) => (&lower[..i], 1 << 30),
+            _ => (&lower[..], 1),
+        };
+        let size = number.parse::<u64>().map_err(|_| error())?;
+        let size = size.checked_mul(unit).ok_or_else(error)?;
+        match u32::try_from(size) {
+            Ok(size) if size as u64 >= MIN_SEGMENT_SIZE => Ok(SegmentSize(size)),
+            _ => Err(error()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct InspectKindParseError(String);
 
@@ -53,17 +97,30 @@ pub enum Opt {
     List {
         #[structopt(help = "The file to open")]
         file: PathBuf,
+        #[structopt(flatten)]
+        check: CheckOptions,
+    },
+    #[structopt(about = "Test the integrity of every entry in a zip file")]
+    Test {
+        #[structopt(help = "The file to open")]
+        file: PathBuf,
+        #[structopt(flatten)]
+        check: CheckOptions,
     },
     #[structopt(about = "Show zip file comment")]
     ShowComment { file: PathBuf },
     #[structopt(about = "Extract from a zip file")]
     Extract {
-        #[structopt(help = "The file to open")]
+        #[structopt(help = "The file to open, - reads the archive from stdin")]
         file: PathBuf,
         #[structopt(help = "Files to extract (optional)")]
         files: Vec<PathBuf>,
         #[structopt(short, long, help = "The output folder", default_value = ".")]
         output: PathBuf,
+        #[structopt(flatten)]
+        options: ExtractOptions,
+        #[structopt(flatten)]
+        check: CheckOptions,
     },
     #[structopt(about = "Add files to a zip file")]
     Add {
@@ -74,6 +131,30 @@ pub enum Opt {
 
         #[structopt(long, help = "Compression Method", default_value = "Deflate")]
         compress: CompressionMethod,
+        #[structopt(long, help = "Only add files matching the glob")]
+        include: Vec<String>,
+        #[structopt(long, help = "Don't add anything matching the glob")]
+        exclude: Vec<String>,
+        #[structopt(
+            long,
+            help = "How to handle symlinks: follow, store or skip",
+            default_value = "follow"
+        )]
+        symlinks: Symlinks,
+        #[structopt(
+            long,
+            help = "Split the zip into files of at most this size, eg 2g, 100m or 64k"
+        )]
+        split: Option<SegmentSize>,
+    },
+    #[structopt(
+        about = "Rebuild a zip file from its local headers, when the central directory is damaged"
+    )]
+    Recover {
+        #[structopt(help = "The damaged file")]
+        file: PathBuf,
+        #[structopt(help = "Where to write the rebuilt zip file")]
+        output: PathBuf,
     },
 
     #[structopt(
@@ -97,3 +178,44 @@ pub enum Opt {
 pub fn parse_args() -> Opt {
     Opt::from_args()
 }
+
+/// How strictly entries are checked when read
+#[derive(StructOpt, Debug, Default)]
+pub struct CheckOptions {
+    #[structopt(
+        long,
+        help = "Reject any difference between local headers and the central directory, and any unused bytes"
+    )]
+    pub strict: bool,
+}
+
+impl CheckOptions {
+    pub fn policy(&self) -> ConsistencyPolicy {
+        if self.strict {
+            ConsistencyPolicy::Strict
+        } else {
+            ConsistencyPolicy::Lenient
+        }
+    }
+
+    pub fn structure_policy(&self) -> StructurePolicy {
+        if self.strict {
+            StructurePolicy::Strict
+        } else {
+            StructurePolicy::Lenient
+        }
+    }
+}
+
+/// How extracted files are written
+#[derive(StructOpt, Debug, Default)]
+pub struct ExtractOptions {
+    #[structopt(long, help = "Overwrite existing files")]
+    pub overwrite: bool,
+    #[structopt(long, help = "Don't restore modification and access times")]
+    pub no_times: bool,
+    #[structopt(long, help = "Don't restore unix permissions")]
+    pub no_permissions: bool,
+    #[structopt(long, help = "Write symlinks as regular files containing the target")]
+    pub no_symlinks: bool,
+}
@@ -1,106 +1,171 @@
 use crate::{
+    display::ToString,
     error::{AppError, AppResult},
     sequence::Sequence,
 };
 use std::{
-    convert::{TryFrom, TryInto},
-    path::Path,
+    ffi::OsString,
+    fs::File,
+    path::{Component, Path, PathBuf},
 };
 use zipr::{
-    data::{
-        borrowed::{file::CompressedData, OEM437Str, OEM437Symbols, ZipEntry},
-        CompressionMethod, HostCompatibility, Version, ZipSpecification,
-    },
-    std::ToPath,
+    data::{borrowed::ZipEntry, owned::ZipEntryBuf, CompressionMethod},
+    std::{segment_path, Symlinks, WalkKind, Walker, ZipArchive},
 };
 
+/// How the inputs are walked, and the archive written
+pub struct AddOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub symlinks: Symlinks,
+    /// The most bytes in each file of a split archive
+    pub split: Option<u32>,
+}
+
+/// Relative paths keep their name as given, others are named from their folder
+fn walker(path: &Path, options: &AddOptions) -> AppResult<Walker> {
+    let mut walker = Walker::new(path).symlinks(options.symlinks);
+    let escapes = path.components().any(|x| x == Component::ParentDir);
+    if path.is_relative() && !escapes {
+        walker = walker.base(PathBuf::new());
+    }
+    for glob in options.include.iter() {
+        walker = walker.include(glob)?;
+    }
+    for glob in options.exclude.iter() {
+        walker = walker.exclude(glob)?;
+    }
+    Ok(walker)
+}
+
 /// Adds files to an existing archive
 pub fn add_files<P: AsRef<Path>>(
     file: P,
     files: Vec<P>,
     compression: CompressionMethod,
+    options: AddOptions,
 ) -> AppResult<()> {
-    fn to_zip<'a>(path: &'a Path, compressed_data: CompressedData<'a>) -> AppResult<ZipEntry<'a>> {
-        let comment = Default::default();
-        let extra_field = zipr::data::borrowed::extra_field::ExtraField::Unknown(&[]);
-        let file_modification_time = zipr::data::DosTime::from_u16_unchecked(0);
-        let file_modification_date = zipr::data::DosDate::from_u16_unchecked(0);
-        let symbols = OEM437Symbols::try_from(path.to_str().unwrap())?;
-        let str: OEM437Str = *symbols.as_ref();
-        let file_name = zipr::data::borrowed::ZipPath::try_from(str)?;
-
-        let version = Version {
-            host: HostCompatibility::MSDOS,
-            spec: ZipSpecification {
-                major: 2u8.try_into().unwrap(),
-                minor: 0u8.try_into().unwrap(),
-            },
-        };
-
-        let entry = ZipEntry {
-            version_made_by: version,
-            version_needed: version,
-            general_purpose: 0,
-            file_modification_time,
-            file_modification_date,
-            internal_file_attributes: 0,
-            external_file_attributes: 0,
-            file_name,
-            extra_field,
-            comment,
-            compressed_data,
-        };
-        Ok(entry)
-    }
-
     let path = file.as_ref();
-    let files: Vec<&Path> = files.iter().map(|x| x.as_ref()).collect();
+    let mut inputs = Vec::new();
+    for f in files.iter() {
+        inputs.append(&mut walker(f.as_ref(), &options)?.walk()?);
+    }
     println!("{}", path.to_string_lossy());
 
-    // Get the input bytes
-    let bytes = if path.exists() {
-        std::fs::read(path)?
-    } else {
-        Vec::new()
+    // At its core we need existing entries to determine what entries to store.
+    // A split archive is read along with its other segments
+    let archive = match path.exists() && path.metadata()?.len() > 0 {
+        true => Some(ZipArchive::from_path(path)?),
+        false => None,
     };
-
-    // At its core we need existing entries to determine what entries to store
-    let entries = if !bytes.is_empty() {
-        let entries = zipr::nom::iter::zip_entry_iter(&bytes)
+    let entries = match &archive {
+        Some(archive) => archive
+            .entries()
             .sequence()
-            .map_err(Into::<AppError>::into)?;
-        entries
-    } else {
-        Vec::new()
+            .map_err(Into::<AppError>::into)?,
+        None => Vec::new(),
     };
 
     // Filter out the entries that we already have
-    let mut existing: Vec<_> = entries
-        .into_iter()
-        .filter(|x| !files.contains(&x.file_name.to_path().as_path()))
+    let mut existing: Vec<ZipEntryBuf> = entries
+        .iter()
+        .filter(|x| !inputs.iter().any(|i| i.name == x.file_name.to_string()))
+        .map(ZipEntry::to_owned)
         .collect();
+    // The archive may be memory mapped, so must be closed before it is rewritten
+    drop(entries);
+    drop(archive);
+
+    for input in inputs.iter() {
+        let data = input.read()?;
+        let mut buf = Vec::new();
+        let compressed = match input.kind {
+            WalkKind::File => zipr::compression::compress_with(compression, &mut buf, &data),
+            _ => zipr::compression::store(&mut buf, &data),
+        };
+        existing.push(input.to_zip_entry(compressed)?.to_owned());
+    }
+
+    match options.split {
+        None => replace_with(path, |temporary| {
+            let mut zip = File::create(temporary)?;
+            let serializer = zipr::cookie::file_buf(existing.iter());
+            cookie_factory::gen(serializer, &mut zip)?;
+            Ok(())
+        })
+        .and_then(|()| remove_segments(path, 0))?,
+        Some(segment_size) => write_split(path, &existing, segment_size)?,
+    }
 
-    let mut pool: Vec<Vec<u8>> = Vec::new();
+    Ok(())
+}
 
-    let mut new_entries = {
-        let mut new_entries: Vec<ZipEntry> = Vec::new();
-        for _ in files.iter() {
-            pool.push(Vec::new())
+/// A hidden sibling of path, on the same filesystem so it can be renamed over path
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Writes to a temporary file, and only replaces path once that succeeds.
+/// The existing archive is left as it was if writing fails
+fn replace_with<F>(path: &Path, write: F) -> AppResult<()>
+where
+    F: FnOnce(&Path) -> AppResult<()>,
+{
+    let temporary = temporary_path(path);
+    match write(&temporary) {
+        Ok(()) => Ok(std::fs::rename(&temporary, path)?),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temporary);
+            Err(e)
         }
+    }
+}
 
-        for (i, buf) in pool.iter_mut().enumerate() {
-            let f = std::fs::read(files[i]).unwrap();
-            let compress = zipr::compression::compress_with(compression, buf, &f);
-            let zip = to_zip(files[i], compress).unwrap();
-            new_entries.push(zip)
+/// Writes the entries as path.z01, path.z02 and so on, with the
+/// last segment, holding the central directory, as path.
+/// Like replace_with, nothing is replaced until every segment is written,
+/// then segments left over from a longer archive are removed
+fn write_split(path: &Path, entries: &[ZipEntryBuf], segment_size: u32) -> AppResult<()> {
+    let mut written = Vec::new();
+    let segment = |disk| {
+        let temporary = temporary_path(&segment_path(path, disk));
+        let file = File::create(&temporary)?;
+        written.push(temporary);
+        Ok(file)
+    };
+    let last = match zipr::cookie::split_file_buf(entries.iter(), segment_size, segment) {
+        Ok(last) => last,
+        Err(e) => {
+            for temporary in written {
+                let _ = std::fs::remove_file(temporary);
+            }
+            return Err(e.into());
         }
-        new_entries
     };
-    existing.append(&mut new_entries);
 
-    let mut zip = std::fs::File::create(path)?;
-    let serializer = zipr::cookie::file(existing.iter());
-    let _ = cookie_factory::gen(serializer, &mut zip);
+    for (disk, temporary) in (0..).zip(written) {
+        let target = match disk == last {
+            true => path.to_path_buf(),
+            false => segment_path(path, disk),
+        };
+        std::fs::rename(temporary, target)?;
+    }
+    remove_segments(path, last)?;
+
+    if last > 0 {
+        println!("  split into {} files", last as u32 + 1);
+    }
+    Ok(())
+}
 
+/// Removes segments left over from a longer split archive, from the disk on
+fn remove_segments(path: &Path, from: u16) -> AppResult<()> {
+    let stale = (from..).map(|disk| segment_path(path, disk));
+    for segment in stale.take_while(|x| x.exists()) {
+        std::fs::remove_file(segment)?;
+    }
     Ok(())
 }
@@ -1,41 +1,107 @@
-use std::path::Path;
-use zipr::{compression::DecompressToVec, data::borrowed::ZipEntry, std::ToPath};
+use std::{
+    io::{self, Read},
+    path::Path,
+};
+use zipr::{
+    data::borrowed::ZipEntry,
+    std::{Extractor, Overwrite, ToPath, ZipArchive, ZipStream},
+};
 
 use crate::{
+    args::zipr::{CheckOptions, ExtractOptions},
     display::ToString,
     error::{AppError, AppResult},
-    sequence::Sequence,
 };
 
-fn extract_bytes(file: &ZipEntry<'_>) -> AppResult<Vec<u8>> {
-    let bytes = file
-        .compressed_data
-        .decompress_to_vec()
-        .map_err(Into::<AppError>::into)?;
-    Ok(bytes)
-}
+/// Reads the archive from stdin rather than a file
+const STDIN: &str = "-";
 
 /// Extract files to the supplied path
+/// Entries with unsafe paths, or that conflict with existing files
+/// are reported and skipped, and the command fails once the rest are extracted
 pub fn extract_files<P: AsRef<Path> + PartialEq>(
     file: P,
     files: Vec<P>,
     output: P,
+    options: ExtractOptions,
+    check: CheckOptions,
 ) -> AppResult<()> {
-    let bytes = std::fs::read(file)?;
-    let entries = zipr::nom::iter::zip_entry_iter(&bytes)
-        .sequence()
-        .map_err(Into::<AppError>::into)?;
-
+    let overwrite = if options.overwrite {
+        Overwrite::Always
+    } else {
+        Overwrite::Never
+    };
+    let mut extractor = Extractor::new(output.as_ref())
+        .overwrite(overwrite)
+        .restore_times(!options.no_times)
+        .restore_permissions(!options.no_permissions)
+        .restore_symlinks(!options.no_symlinks);
     let files: Vec<&Path> = files.iter().map(|x| x.as_ref()).collect();
-    for entry in entries.iter() {
-        if !files.is_empty() && !files.contains(&entry.file_name.to_path().as_path()) {
-            println!("Skipping: {}", entry.file_name.to_string());
-        } else {
-            let bytes = extract_bytes(entry)?;
-            let path = output.as_ref().join(entry.file_name.to_path());
-            std::fs::write(path.clone(), bytes)?;
-            println!("Extracted: {} ", path.to_string_lossy());
+    let mut failed = 0;
+
+    if file.as_ref() == Path::new(STDIN) {
+        // Only the local headers can be read, so there is nothing to check
+        let mut stream = ZipStream::new(io::stdin().lock());
+        while let Some(entry) = stream.next_streamed() {
+            let entry = entry.map_err(Into::<AppError>::into)?;
+            if !extract_entry(
+                &mut extractor,
+                &files,
+                &entry.entry.as_borrowed(),
+                entry.reader,
+            ) {
+                failed += 1;
+            }
+        }
+        extractor.finish().map_err(Into::<AppError>::into)?;
+        return finished(failed);
+    }
+
+    let archive = ZipArchive::from_path(file)?
+        .with_policy(check.policy())
+        .with_structure_policy(check.structure_policy());
+    for warning in archive.check_structure()? {
+        eprintln!("Warning: {}", warning);
+    }
+    for entry in archive.entries() {
+        let entry = entry.map_err(Into::<AppError>::into)?;
+        if !extract_entry(&mut extractor, &files, &entry, archive.open(&entry)) {
+            failed += 1;
+        }
+    }
+    extractor.finish().map_err(Into::<AppError>::into)?;
+    finished(failed)
+}
+
+/// Fails if any entry that was asked for wasn't extracted
+fn finished(failed: usize) -> AppResult<()> {
+    match failed {
+        0 => Ok(()),
+        x => Err(AppError::ExtractFailed(x)),
+    }
+}
+
+/// Extracts the entry if it was asked for, reporting what happened
+/// Returns false if the entry was wanted but couldn't be extracted
+fn extract_entry<R: Read>(
+    extractor: &mut Extractor,
+    files: &[&Path],
+    entry: &ZipEntry<'_>,
+    reader: R,
+) -> bool {
+    if !files.is_empty() && !files.contains(&entry.file_name.to_path().as_path()) {
+        println!("Skipping: {}", entry.file_name.to_string());
+        true
+    } else {
+        match extractor.extract(entry, reader) {
+            Ok(path) => {
+                println!("Extracted: {} ", path.to_string_lossy());
+                true
+            }
+            Err(e) => {
+                eprintln!("Skipping: {}: {}", entry.file_name.to_string(), e);
+                false
+            }
         }
     }
-    Ok(())
 }
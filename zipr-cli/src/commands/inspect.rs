@@ -1,41 +1,47 @@
 use std::path::Path;
 
-use nom::Finish;
-use zipr::nom::data::{parse_directory_header, parse_end_of_central_directory, parse_local_file};
-
-use crate::{
-    args::zipr::InspectKind,
-    error::{AppError, AppResult},
+use nom::{error::VerboseError, Finish};
+use zipr::{
+    nom::{
+        data::{parse_directory_header, parse_end_of_central_directory, parse_local_file},
+        error::{Record, ZipError, CENTRAL_DIRECTORY_HEADER, LOCAL_FILE_HEADER},
+    },
+    std::MmapArchive,
 };
 
+use crate::{args::zipr::InspectKind, error::AppResult};
+
 pub fn inspect<P: AsRef<Path> + PartialEq>(
     file: P,
     kind: InspectKind,
     offset: usize,
     upto: Option<usize>,
 ) -> AppResult<()> {
-    let bytes = std::fs::read(file)?;
-    let slice = match upto {
-        None => &bytes[offset..],
-        Some(x) => &bytes[offset..offset + x],
+    let bytes = MmapArchive::open(file)?;
+    let record = match kind {
+        InspectKind::Local => Record::Unknown(LOCAL_FILE_HEADER),
+        InspectKind::Dir => Record::Unknown(CENTRAL_DIRECTORY_HEADER),
+        InspectKind::Eocd => Record::EndOfCentralDirectory,
     };
+    let slice = match upto {
+        None => bytes.get(offset..),
+        Some(x) => offset.checked_add(x).and_then(|end| bytes.get(offset..end)),
+    }
+    .ok_or_else(|| ZipError::out_of_range(offset as u64, record))?;
+    let report = |e: VerboseError<&[u8]>| ZipError::from_verbose(&bytes, record, e);
     match kind {
         InspectKind::Local => {
-            let (_, local) = parse_local_file(slice)
-                .finish()
-                .map_err(Into::<AppError>::into)?;
+            let (_, local) = parse_local_file(slice).finish().map_err(report)?;
             println!("{:x?}", local);
         }
         InspectKind::Dir => {
-            let (_, local) = parse_directory_header(slice)
-                .finish()
-                .map_err(Into::<AppError>::into)?;
+            let (_, local) = parse_directory_header(slice).finish().map_err(report)?;
             println!("{:x?}", local);
         }
         InspectKind::Eocd => {
             let (_, local) = parse_end_of_central_directory(slice)
                 .finish()
-                .map_err(Into::<AppError>::into)?;
+                .map_err(report)?;
             println!("{:x?}", local);
         }
     }
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use zipr::{domain::ConsistencyPolicy, std::ZipArchive};
+
 use crate::{
     display::display_entries,
     error::{AppError, AppResult},
@@ -7,12 +9,13 @@ use crate::{
 };
 
 /// List all the files to console
-pub fn list_files<P>(path: P) -> AppResult<()>
+pub fn list_files<P>(path: P, policy: ConsistencyPolicy) -> AppResult<()>
 where
     P: AsRef<Path>,
 {
-    let bytes = std::fs::read(path)?;
-    let entries = zipr::nom::iter::zip_entry_iter(&bytes)
+    let archive = ZipArchive::from_path(path)?.with_policy(policy);
+    let entries = archive
+        .entries()
         .sequence()
         .map_err(Into::<AppError>::into)?;
 
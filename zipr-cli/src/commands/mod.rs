@@ -2,10 +2,14 @@ mod add_files;
 mod extract_files;
 mod inspect;
 mod list_files;
+mod recover;
 mod show_comment;
+mod test_files;
 
 pub use add_files::*;
 pub use extract_files::*;
 pub use inspect::*;
 pub use list_files::*;
+pub use recover::*;
 pub use show_comment::*;
+pub use test_files::*;
@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use zipr::{
+    data::{constants::GENERAL_PURPOSE_DATA_DESCRIPTOR, owned::ZipEntryBuf},
+    domain::local_zip_entry,
+    nom::recover::recovery_scan,
+};
+
+use crate::{display::ToString, error::AppResult};
+
+/// Rebuilds an archive from the entries whose local headers can still be read,
+/// eg when the central directory is truncated or overwritten.
+/// Anything that couldn't be recovered is printed
+pub fn recover<P: AsRef<Path>>(file: P, output: P) -> AppResult<()> {
+    let bytes = std::fs::read(file)?;
+
+    let mut recovered: Vec<ZipEntryBuf> = Vec::new();
+    for result in recovery_scan(&bytes) {
+        match result {
+            Ok(found) => {
+                let mut entry = local_zip_entry(&found.entry);
+                // The sizes are known now, so go in the rebuilt headers
+                entry.general_purpose &= !GENERAL_PURPOSE_DATA_DESCRIPTOR;
+                println!("  recovered: {}", entry.file_name.to_string());
+                recovered.push(entry.to_owned());
+            }
+            Err(damage) => println!("    damaged: {}", damage),
+        }
+    }
+
+    let path = output.as_ref();
+    let mut zip = std::fs::File::create(path)?;
+    let serializer = zipr::cookie::file_buf(recovered.iter());
+    cookie_factory::gen(serializer, &mut zip)?;
+
+    println!(
+        "Recovered {} entries into {}.",
+        recovered.len(),
+        path.to_string_lossy()
+    );
+    Ok(())
+}
@@ -1,9 +1,15 @@
 use std::path::Path;
 
 use nom::Finish;
-use zipr::{data::borrowed::OEM437Symbols, nom::find_end_of_central_directory};
+use zipr::{
+    data::borrowed::OEM437Symbols,
+    nom::{
+        error::{ParseFailure, Record, ZipError},
+        find_end_of_central_directory,
+    },
+};
 
-use crate::error::{AppError, AppResult};
+use crate::error::AppResult;
 
 /// Shows the comment of the zip archive
 pub fn show_comment<P>(path: P) -> AppResult<()>
@@ -11,9 +17,9 @@ where
     P: AsRef<Path>,
 {
     let bytes = std::fs::read(path)?;
-    let (_, file) = find_end_of_central_directory(&bytes)
+    let (_, file) = find_end_of_central_directory::<ParseFailure>(&bytes)
         .finish()
-        .map_err(Into::<AppError>::into)?;
+        .map_err(|e| ZipError::new(&bytes, Record::EndOfCentralDirectory, e))?;
     let comment = OEM437Symbols::from(file.comment);
     println!("{}", comment);
     Ok(())
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use zipr::std::ZipArchive;
+
+use crate::{
+    args::zipr::CheckOptions,
+    error::{AppError, AppResult},
+};
+
+/// Tests the archive structure and every entry, printing the result of each.
+/// Fails if any entry does, or the structure is rejected
+pub fn test_files<P>(path: P, check: CheckOptions) -> AppResult<()>
+where
+    P: AsRef<Path>,
+{
+    let archive = ZipArchive::from_path(&path)?.with_policy(check.policy());
+    let mut failed = 0;
+
+    // A structure that can't be analysed is a failure, but the entries are still tested
+    let structure = check.structure_policy();
+    match archive.structural_issues() {
+        Ok(issues) => {
+            for issue in issues {
+                if structure.rejects(&issue) {
+                    failed += 1;
+                    println!("  structure: {}   FAILED", issue);
+                } else {
+                    println!("  structure: {}   warning", issue);
+                }
+            }
+        }
+        Err(e) => {
+            failed += 1;
+            println!("  structure: {}   FAILED", e);
+        }
+    }
+
+    for result in archive.test() {
+        if result.passed() {
+            println!("    testing: {}   OK", result.name);
+        } else {
+            failed += 1;
+            println!("    testing: {}   FAILED", result.name);
+            for failure in result.failures {
+                println!("        {}", failure);
+            }
+        }
+    }
+
+    let path = path.as_ref().to_string_lossy();
+    if failed == 0 {
+        println!("No errors detected in compressed data of {}.", path);
+        Ok(())
+    } else {
+        println!("{} problems found in {}.", failed, path);
+        Err(AppError::TestFailed(failed))
+    }
+}
@@ -22,8 +22,12 @@ pub fn display_entries(entries: Vec<ZipEntry<'_>>) -> Table {
     for e in entries.iter() {
         let row = vec![
             format!("{}", e.compressed_data.uncompressed_size()),
-            format!("{}", e.file_modification_date.to_date()),
-            format!("{}", e.file_modification_time.to_time()),
+            e.file_modification_date
+                .to_date()
+                .map_or_else(|_| "invalid".into(), |x| x.to_string()),
+            e.file_modification_time
+                .to_time()
+                .map_or_else(|_| "invalid".into(), |x| x.to_string()),
             e.file_name.to_string(),
         ];
         total += e.compressed_data.uncompressed_size();
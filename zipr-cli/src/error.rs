@@ -1,19 +1,27 @@
+use cookie_factory::GenError;
 use std::fmt::Display;
 use zipr::{
     compression::DecompressError,
     data::borrowed::{NotValidOEM437, ZipPathError},
-    nom::iter::ZipEntryIteratorError,
+    nom::{error::ZipError, iter::ZipEntryIteratorError},
+    std::{ArchiveError, ExtractError, WalkError},
 };
 
 pub type AppResult<T> = Result<T, AppError>;
 #[derive(Debug)]
 pub enum AppError {
     Decompression(DecompressError),
-    NomError(nom::error::Error<Vec<u8>>),
+    Parse(ZipError),
     ZipIteratorError(ZipEntryIteratorError),
     IOError(std::io::Error),
     OEM437Error(NotValidOEM437),
     ZipPathError(ZipPathError),
+    Archive(ArchiveError),
+    Extract(ExtractError),
+    Walk(WalkError),
+    Write(GenError),
+    TestFailed(usize),
+    ExtractFailed(usize),
 }
 
 impl From<std::io::Error> for AppError {
@@ -45,17 +53,59 @@ impl From<ZipPathError> for AppError {
     }
 }
 
-impl From<nom::error::Error<&'_ [u8]>> for AppError {
-    fn from(e: nom::error::Error<&'_ [u8]>) -> Self {
-        let data = e.input.to_vec();
-        let error = nom::error::Error::new(data, e.code);
-        AppError::NomError(error)
+impl From<ArchiveError> for AppError {
+    fn from(e: ArchiveError) -> Self {
+        AppError::Archive(e)
+    }
+}
+
+impl From<ExtractError> for AppError {
+    fn from(e: ExtractError) -> Self {
+        AppError::Extract(e)
+    }
+}
+
+impl From<WalkError> for AppError {
+    fn from(e: WalkError) -> Self {
+        AppError::Walk(e)
+    }
+}
+
+impl From<GenError> for AppError {
+    fn from(e: GenError) -> Self {
+        match e {
+            GenError::IoError(e) => AppError::IOError(e),
+            e => AppError::Write(e),
+        }
+    }
+}
+
+impl From<ZipError> for AppError {
+    fn from(e: ZipError) -> Self {
+        AppError::Parse(e)
     }
 }
 
 impl Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{:x?}", self))
+        match self {
+            AppError::Decompression(e) => f.write_fmt(format_args!("{:x?}", e)),
+            AppError::Parse(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::ZipIteratorError(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::IOError(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::OEM437Error(e) => f.write_fmt(format_args!("{:x?}", e)),
+            AppError::ZipPathError(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::Archive(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::Extract(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::Walk(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::Write(e) => f.write_fmt(format_args!("{}", e)),
+            AppError::TestFailed(count) => {
+                f.write_fmt(format_args!("{} problems found when testing", count))
+            }
+            AppError::ExtractFailed(count) => {
+                f.write_fmt(format_args!("{} entries could not be extracted", count))
+            }
+        }
     }
 }
 
@@ -5,11 +5,18 @@ mod error;
 mod sequence;
 use args::zipr::Opt;
 use error::AppResult;
-use std::env;
+use std::{env, process};
 
 const UNZIP: &str = "unzip";
 
-fn main() -> AppResult<()> {
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> AppResult<()> {
     let args: Vec<String> = env::args().collect();
     let head = args.first().map(String::as_str);
 
@@ -22,18 +29,34 @@ fn main() -> AppResult<()> {
 
     // Run logic;
     match opt {
-        Opt::List { file } => commands::list_files(file),
+        Opt::List { file, check } => commands::list_files(file, check.policy()),
+        Opt::Test { file, check } => commands::test_files(file, check),
         Opt::ShowComment { file } => commands::show_comment(file),
         Opt::Extract {
             file,
             files,
             output,
-        } => commands::extract_files(file, files, output),
+            options,
+            check,
+        } => commands::extract_files(file, files, output, options, check),
         Opt::Add {
             file,
             files,
             compress,
-        } => commands::add_files(file, files, compress),
+            include,
+            exclude,
+            symlinks,
+            split,
+        } => {
+            let options = commands::AddOptions {
+                include,
+                exclude,
+                symlinks,
+                split: split.map(|x| x.0),
+            };
+            commands::add_files(file, files, compress, options)
+        }
+        Opt::Recover { file, output } => commands::recover(file, output),
         Opt::Inspect {
             file,
             offset,
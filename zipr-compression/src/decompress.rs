@@ -1,17 +1,20 @@
 use alloc::vec::Vec;
 use crc::Crc;
 use crc::CRC_32_ISO_HDLC;
-use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::{inflate::TINFLStatus, MZError};
 use zipr_data::{borrowed::file::CompressedData, CompressionMethod};
 
 const CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
 
 /// Enum for ways in which decompression can fail
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum DecompressError {
     InvalidCrc(u32, u32),
+    InvalidSize(u32, u64),
     UnhandledCompressionMethod,
     Miniz(TINFLStatus),
+    Stream(MZError),
+    Truncated,
 }
 
 /// Trait for expanded the compressed data into a vector
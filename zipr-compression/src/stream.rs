@@ -0,0 +1,356 @@
+use alloc::boxed::Box;
+use core::cmp::min;
+use crc::{Crc, Digest, CRC_32_ISO_HDLC};
+use miniz_oxide::{
+    inflate::stream::{inflate, InflateState},
+    DataFormat, MZError, MZFlush, MZStatus,
+};
+use zipr_data::{borrowed::file::CompressedData, CompressionMethod};
+
+use crate::DecompressError;
+
+static CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// Incrementally expands compressed data into caller supplied buffers
+///
+/// Unlike decompress_to_vec this never holds the whole file in memory.
+/// The crc32 and size are checked once the end of the data is reached,
+/// so the final read will fail if the data was corrupt. Producing more
+/// than the uncompressed size fails straight away. Once a read fails,
+/// every later read gives the same error
+pub struct DecompressStream<'a> {
+    data: CompressedData<'a>,
+    consumed: usize,
+    decompressor: Decompressor,
+}
+
+impl<'a> DecompressStream<'a> {
+    pub fn new(data: CompressedData<'a>) -> Self {
+        let decompressor = Decompressor::new(
+            data.compression_method(),
+            data.bytes().len() as u64,
+            data.uncompressed_size(),
+            data.crc32(),
+        );
+        DecompressStream {
+            data,
+            consumed: 0,
+            decompressor,
+        }
+    }
+
+    /// The number of uncompressed bytes produced so far
+    pub fn written(&self) -> u64 {
+        self.decompressor.written()
+    }
+
+    /// The crc32 of the uncompressed data, once the end has been reached.
+    /// Available even if it didn't match the expected value
+    pub fn crc32(&self) -> Option<u32> {
+        self.decompressor.crc32()
+    }
+
+    /// Fills output with the next chunk of uncompressed data
+    /// Returns 0 once all the data has been read and verified
+    pub fn read(&mut self, output: &mut [u8]) -> Result<usize, DecompressError> {
+        let input = &self.data.bytes()[self.consumed..];
+        let (consumed, written) = self.decompressor.decompress(input, output, true)?;
+        self.consumed += consumed;
+        Ok(written)
+    }
+}
+
+/// Expands compressed data that arrives in pieces, eg from a pipe
+///
+/// This is the state behind DecompressStream, without the data. Each call
+/// continues from the input the last one didn't consume, and the same
+/// checks are made once the end is reached
+pub struct Decompressor {
+    compressed_size: u64,
+    uncompressed_size: u32,
+    expected_crc32: u32,
+    consumed: u64,
+    inflate: Option<Box<InflateState>>,
+    digest: Digest<'static, u32>,
+    written: u64,
+    crc32: Option<u32>,
+    done: bool,
+    failed: Option<DecompressError>,
+}
+
+impl Decompressor {
+    pub fn new(
+        compression_method: CompressionMethod,
+        compressed_size: u64,
+        uncompressed_size: u32,
+        crc32: u32,
+    ) -> Self {
+        let inflate = match compression_method {
+            CompressionMethod::Stored => None,
+            CompressionMethod::Deflate => Some(InflateState::new_boxed(DataFormat::Raw)),
+        };
+        Decompressor {
+            compressed_size,
+            uncompressed_size,
+            expected_crc32: crc32,
+            consumed: 0,
+            inflate,
+            digest: CRC32.digest(),
+            written: 0,
+            crc32: None,
+            done: false,
+            failed: None,
+        }
+    }
+
+    /// The number of uncompressed bytes produced so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// The crc32 of the uncompressed data, once the end has been reached.
+    /// Available even if it didn't match the expected value
+    pub fn crc32(&self) -> Option<u32> {
+        self.crc32
+    }
+
+    /// Whether the end has been reached and verified
+    pub fn is_done(&self) -> bool {
+        self.done && self.failed.is_none()
+    }
+
+    /// Expands the start of input into output, giving how many bytes
+    /// were consumed and written. Writes nothing when more input is needed,
+    /// unless last is set, as then the data stops short and fails as Truncated
+    pub fn decompress(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+        last: bool,
+    ) -> Result<(usize, usize), DecompressError> {
+        if let Some(error) = self.failed {
+            return Err(error);
+        }
+        let result = self.expand(input, output, last);
+        if let Err(error) = result {
+            self.failed = Some(error);
+        }
+        result
+    }
+
+    fn expand(
+        &mut self,
+        mut input: &[u8],
+        output: &mut [u8],
+        last: bool,
+    ) -> Result<(usize, usize), DecompressError> {
+        if self.done || output.is_empty() {
+            return Ok((0, 0));
+        }
+        let mut total = 0;
+        loop {
+            let (consumed, written, end) = match &mut self.inflate {
+                None => {
+                    let remaining = self.compressed_size - self.consumed;
+                    let length = min(input.len() as u64, remaining).min(output.len() as u64);
+                    let length = length as usize;
+                    output[..length].copy_from_slice(&input[..length]);
+                    (length, length, length as u64 == remaining)
+                }
+                Some(state) => {
+                    let result = inflate(state, input, output, MZFlush::None);
+                    let end = match result.status {
+                        Ok(status) => status == MZStatus::StreamEnd,
+                        // Nothing could be done with the input so far
+                        Err(MZError::Buf) => false,
+                        Err(e) => return Err(DecompressError::Stream(e)),
+                    };
+                    (result.bytes_consumed, result.bytes_written, end)
+                }
+            };
+            input = &input[consumed..];
+            total += consumed;
+            self.consumed += consumed as u64;
+            self.written += written as u64;
+            self.digest.update(&output[..written]);
+
+            if end {
+                self.done = true;
+                self.verify()?;
+                return Ok((total, written));
+            }
+            let expected_size = self.uncompressed_size;
+            if self.written > expected_size as u64 {
+                return Err(DecompressError::InvalidSize(expected_size, self.written));
+            }
+            if written > 0 {
+                return Ok((total, written));
+            }
+            if consumed == 0 {
+                // No progress can be made, the data stops mid stream
+                return match last {
+                    true => Err(DecompressError::Truncated),
+                    false => Ok((total, 0)),
+                };
+            }
+        }
+    }
+
+    fn verify(&mut self) -> Result<(), DecompressError> {
+        let digest = core::mem::replace(&mut self.digest, CRC32.digest());
+        let crc = digest.finalize();
+        self.crc32 = Some(crc);
+        let expected_size = self.uncompressed_size;
+        if self.written != expected_size as u64 {
+            return Err(DecompressError::InvalidSize(expected_size, self.written));
+        }
+        if crc != self.expected_crc32 {
+            return Err(DecompressError::InvalidCrc(self.expected_crc32, crc));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn drain(mut stream: DecompressStream<'_>) -> Result<Vec<u8>, DecompressError> {
+        let mut result = Vec::new();
+        let mut buffer = [0u8; 7];
+        loop {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(result);
+            }
+            result.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    #[test]
+    fn hello_world_deflate_small_buffer() {
+        let hello = include_bytes!("../../assets/hello_world_deflate.zip");
+        let data = &hello[39..39 + 22];
+        let compressed =
+            CompressedData::create_unchecked(215, CompressionMethod::Deflate, 810231625, data);
+
+        let result = drain(DecompressStream::new(compressed)).unwrap();
+
+        assert_eq!("world".repeat(43).as_bytes(), result.as_slice());
+    }
+
+    #[test]
+    fn stored_invalid_crc() {
+        let compressed =
+            CompressedData::create_unchecked(5, CompressionMethod::Stored, 1, b"world");
+
+        let result = drain(DecompressStream::new(compressed));
+
+        assert!(matches!(result, Err(DecompressError::InvalidCrc(1, _))));
+    }
+
+    #[test]
+    fn crc32_after_mismatch() {
+        let compressed =
+            CompressedData::create_unchecked(4, CompressionMethod::Stored, 1, b"world");
+        let mut stream = DecompressStream::new(compressed);
+        let mut buffer = [0u8; 8];
+
+        assert!(matches!(
+            stream.read(&mut buffer),
+            Err(DecompressError::InvalidSize(4, 5))
+        ));
+        assert_eq!(Some(0x3A771143), stream.crc32());
+    }
+
+    #[test]
+    fn stops_past_uncompressed_size() {
+        let hello = include_bytes!("../../assets/hello_world_deflate.zip");
+        let data = &hello[39..39 + 22];
+        // Declares 5 bytes, but inflates to 215
+        let compressed =
+            CompressedData::create_unchecked(5, CompressionMethod::Deflate, 810231625, data);
+        let mut stream = DecompressStream::new(compressed);
+        let mut buffer = [0u8; 7];
+
+        assert!(matches!(
+            stream.read(&mut buffer),
+            Err(DecompressError::InvalidSize(5, 7))
+        ));
+        assert_eq!(7, stream.written());
+        assert!(matches!(
+            stream.read(&mut buffer),
+            Err(DecompressError::InvalidSize(5, 7))
+        ));
+    }
+
+    #[test]
+    fn errors_are_sticky() {
+        let compressed =
+            CompressedData::create_unchecked(5, CompressionMethod::Stored, 1, b"world");
+        let mut stream = DecompressStream::new(compressed);
+        let mut buffer = [0u8; 8];
+
+        assert!(matches!(
+            stream.read(&mut buffer),
+            Err(DecompressError::InvalidCrc(1, _))
+        ));
+        assert!(matches!(
+            stream.read(&mut buffer),
+            Err(DecompressError::InvalidCrc(1, _))
+        ));
+    }
+
+    #[test]
+    fn decompressor_in_pieces() {
+        let hello = include_bytes!("../../assets/hello_world_deflate.zip");
+        let data = &hello[39..39 + 22];
+        let mut decompressor = Decompressor::new(CompressionMethod::Deflate, 22, 215, 810231625);
+        let mut result = Vec::new();
+        let mut buffer = [0u8; 16];
+        // A byte of input at a time, which isn't always enough to write anything
+        for position in 0..data.len() {
+            let mut input = &data[position..=position];
+            loop {
+                let (consumed, written) =
+                    decompressor.decompress(input, &mut buffer, false).unwrap();
+                input = &input[consumed..];
+                result.extend_from_slice(&buffer[..written]);
+                if written == 0 {
+                    break;
+                }
+            }
+            assert!(input.is_empty());
+        }
+
+        assert!(decompressor.is_done());
+        assert_eq!("world".repeat(43).as_bytes(), result.as_slice());
+    }
+
+    #[test]
+    fn decompressor_truncated_when_last() {
+        let mut decompressor = Decompressor::new(CompressionMethod::Stored, 5, 5, 0x3A771143);
+        let mut buffer = [0u8; 8];
+
+        let part = decompressor.decompress(b"wor", &mut buffer, false);
+        assert!(matches!(part, Ok((3, 3))));
+        let waiting = decompressor.decompress(&[], &mut buffer, false);
+        assert!(matches!(waiting, Ok((0, 0))));
+        let last = decompressor.decompress(&[], &mut buffer, true);
+        assert!(matches!(last, Err(DecompressError::Truncated)));
+    }
+
+    #[test]
+    fn deflate_truncated() {
+        let hello = include_bytes!("../../assets/hello_world_deflate.zip");
+        let data = &hello[39..39 + 10];
+        let compressed =
+            CompressedData::create_unchecked(215, CompressionMethod::Deflate, 810231625, data);
+
+        let result = drain(DecompressStream::new(compressed));
+
+        assert!(result.is_err());
+    }
+}
@@ -29,7 +29,7 @@ pub fn central_directory_entry<'a, W: Write + 'a>(
         le_u16(input.file_name.as_ref().len() as u16),
         extra_field_len(&input.extra_field),
         le_u16(input.comment.len() as u16),
-        le_u16(0),
+        le_u16(input.disk_number_start),
         le_u16(input.internal_file_attributes),
         le_u32(input.external_file_attributes),
         le_u32(input.relative_offset),
@@ -88,6 +88,7 @@ mod tests {
             }),
             compression_method: CompressionMethod::Stored,
             general_purpose: 0,
+            disk_number_start: 0,
             relative_offset: 0,
         };
 
@@ -132,6 +133,7 @@ mod tests {
             }),
             compression_method: CompressionMethod::Deflate,
             general_purpose: 0,
+            disk_number_start: 0,
             relative_offset: 0,
         };
 
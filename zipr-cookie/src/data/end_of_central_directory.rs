@@ -16,9 +16,9 @@ pub fn end_of_central_directory<'a, W: Write + 'a>(
 ) -> impl SerializeFn<W> + 'a {
     tuple((
         slice(END_OF_CENTRAL_DIRECTORY_HEADER),
-        le_u16(0),
-        le_u16(0),
-        le_u16(input.total_number_records),
+        le_u16(input.number_of_this_disk),
+        le_u16(input.directory_start_disk),
+        le_u16(input.records_on_this_disk),
         le_u16(input.total_number_records),
         le_u32(input.size_of_directory),
         le_u32(input.offset_start_directory),
@@ -44,6 +44,9 @@ mod tests {
     #[test]
     fn minimal() {
         let input = EndOfCentralDirectory {
+            number_of_this_disk: 0,
+            directory_start_disk: 0,
+            records_on_this_disk: 0,
             total_number_records: 0,
             size_of_directory: 0,
             offset_start_directory: 0,
@@ -67,6 +70,7 @@ mod tests {
             &hello[len - END_OF_CENTRAL_DIRECTORY_MIN_SIZE..len]
         };
         let input = EndOfCentralDirectory {
+            records_on_this_disk: 1,
             total_number_records: 1,
             size_of_directory: 91,
             offset_start_directory: 0x2C,
@@ -91,6 +95,7 @@ mod tests {
             &hello[len - END_OF_CENTRAL_DIRECTORY_MIN_SIZE..len]
         };
         let input = EndOfCentralDirectory {
+            records_on_this_disk: 2,
             total_number_records: 2,
             size_of_directory: 185,
             offset_start_directory: 0x59,
@@ -1,29 +1,26 @@
 use cookie_factory::{
-    bytes::{le_u16, le_u32, le_u64},
+    bytes::{le_i32, le_u16, le_u32, le_u64, le_u8},
     combinator::slice,
     lib::std::io::Write,
     sequence::tuple,
     SerializeFn, WriteContext,
 };
 use zipr_data::{
-    borrowed::extra_field::{ntfs::NTFS, ExtraField},
-    constants::{self, EXTRA_FIELD_NTFS_HEADER},
+    borrowed::extra_field::{extended_timestamp::ExtendedTimestamp, ntfs::NTFS, ExtraField},
+    constants::{EXTRA_FIELD_EXTENDED_TIMESTAMP_HEADER, EXTRA_FIELD_NTFS_HEADER},
     WinTimestamp,
 };
 
 pub fn extra_field<'a, W: Write + 'a>(input: ExtraField<'a>) -> impl SerializeFn<W> + 'a {
     move |out: WriteContext<W>| match input {
         ExtraField::NTFS(n) => ntfs(&n)(out),
+        ExtraField::ExtendedTimestamp(x) => extended_timestamp(&x)(out),
         ExtraField::Unknown(b) => unknown(b)(out),
     }
 }
 
 pub fn extra_field_len<'a, W: Write + 'a>(input: &ExtraField<'a>) -> impl SerializeFn<W> + 'a {
-    let len = match *input {
-        ExtraField::NTFS(_) => constants::EXTRA_FIELD_NTFS_LENGTH as u16,
-        ExtraField::Unknown(b) => b.len() as u16,
-    };
-    le_u16(len)
+    le_u16(input.serialized_len() as u16)
 }
 
 pub fn unknown<'a, W: Write + 'a>(input: &'a [u8]) -> impl SerializeFn<W> + 'a {
@@ -45,3 +42,59 @@ pub fn ntfs<'a, W: Write + 'a>(input: &NTFS) -> impl SerializeFn<W> + 'a {
 pub fn wintimestamp<W: Write>(input: &WinTimestamp) -> impl SerializeFn<W> {
     le_u64(input.into())
 }
+
+pub fn extended_timestamp<'a, W: Write + 'a>(
+    input: &ExtendedTimestamp,
+) -> impl SerializeFn<W> + 'a {
+    let flags = input.flags;
+    let times = [input.modify, input.access, input.create];
+    let data_len = (input.serialized_len() - 4) as u16;
+    move |out: WriteContext<W>| {
+        let out = tuple((
+            slice(EXTRA_FIELD_EXTENDED_TIMESTAMP_HEADER),
+            le_u16(data_len),
+            le_u8(flags),
+        ))(out)?;
+        times
+            .iter()
+            .flatten()
+            .try_fold(out, |out, x| le_i32(*x)(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cookie_factory::gen;
+
+    use super::*;
+
+    #[test]
+    fn extended_timestamp_central() {
+        let expected: &[u8] = &[0x55, 0x54, 0x05, 0x00, 0x03, 0x10, 0x00, 0x00, 0x00];
+        let input = ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+            flags: 3,
+            modify: Some(16),
+            access: None,
+            create: None,
+        });
+        let mut buf = [0u8; 9];
+        let (_, pos) = gen(extra_field(input), &mut buf[..]).unwrap();
+        assert_eq!(9, pos);
+        assert_eq!(expected, buf);
+    }
+
+    #[test]
+    fn parsed_subfields_round_trip() {
+        // Info-ZIP's extended timestamp, then its unix uid/gid
+        let input: &[u8] = &[
+            0x55, 0x54, 0x05, 0x00, 0x03, 0x47, 0xcc, 0xd5, 0x6a, 0x75, 0x78, 0x0b, 0x00, 0x01,
+            0x04, 0xe8, 0x03, 0x00, 0x00, 0x04, 0xe8, 0x03, 0x00, 0x00,
+        ];
+        let (_, parsed) =
+            zipr_nom::data::parse_extra_field::<zipr_nom::error::ParseFailure>(input).unwrap();
+        let mut buf = [0u8; 24];
+        let (_, pos) = gen(extra_field(parsed), &mut buf[..]).unwrap();
+        assert_eq!(24, pos);
+        assert_eq!(input, buf);
+    }
+}
@@ -10,6 +10,16 @@ use zipr_data::{borrowed::file::LocalFileEntry, constants::LOCAL_FILE_HEADER_SIG
 
 pub fn local_file_entry<'a, W: Write + 'a>(
     input: &'a LocalFileEntry<'a>,
+) -> impl SerializeFn<W> + 'a {
+    tuple((
+        local_file_header(input),
+        slice(input.compressed_data.bytes()),
+    ))
+}
+
+/// The local file without its data, eg to write the data in parts
+pub fn local_file_header<'a, W: Write + 'a>(
+    input: &'a LocalFileEntry<'a>,
 ) -> impl SerializeFn<W> + 'a {
     tuple((
         slice(LOCAL_FILE_HEADER_SIGNATURE),
@@ -25,7 +35,6 @@ pub fn local_file_entry<'a, W: Write + 'a>(
         extra_field_len(&input.extra_field),
         zip_path(&input.file_name),
         extra_field(input.extra_field),
-        slice(input.compressed_data.bytes()),
     ))
 }
 
@@ -1,13 +1,22 @@
-use cookie_factory::{lib::std::io::Write, multi::all, SerializeFn, WriteContext};
-use zipr_data::borrowed::ZipEntry;
+use cookie_factory::{
+    combinator::slice, gen, lib::std::io::Write, multi::all, GenError, SerializeFn, WriteContext,
+};
+#[cfg(feature = "alloc")]
+use zipr_data::owned::ZipEntryBuf;
+use zipr_data::{borrowed::ZipEntry, constants::SPANNING_SIGNATURE};
 
 use crate::{
-    data::{central_directory_entry, end_of_central_directory, local_file_entry},
-    layout::{layout, ZipPart},
+    data::{
+        central_directory_entry, end_of_central_directory, local_file_entry, local_file_header,
+    },
+    layout::{layout, LayoutIterator, Placement, ZipPart},
 };
 
 fn parts<'a, W: Write + 'a>(input: ZipPart<'a>) -> impl SerializeFn<W> + 'a {
     move |out: WriteContext<W>| match input {
+        ZipPart::SpanningSignature => slice(SPANNING_SIGNATURE)(out),
+        // Only split_file has more than one writer
+        ZipPart::NextDisk(_) => Ok(out),
         ZipPart::LocalFile(x) => local_file_entry(&x)(out),
         ZipPart::DirectoryEntry(x) => central_directory_entry(&x)(out),
         ZipPart::EndOfCentralDirectory(x) => end_of_central_directory(&x)(out),
@@ -18,11 +27,246 @@ fn parts<'a, W: Write + 'a>(input: ZipPart<'a>) -> impl SerializeFn<W> + 'a {
 ///
 /// This will enumerate the list twice, and layout all the
 /// datastructures correctly. It then adds the eocd when complete
-pub fn file<'a, W: Write + 'a, I>(input: I) -> impl SerializeFn<W> + 'a
+pub fn file<'a, W, I>(input: I) -> impl SerializeFn<W> + 'a
 where
     I: Iterator<Item = &'a ZipEntry<'a>> + Clone + 'a,
     W: Write + 'a,
 {
-    let layout = layout(input).map(parts);
+    let layout = layout(input.copied()).map(parts);
+    all(layout)
+}
+
+/// As file, but for owned entries
+#[cfg(feature = "alloc")]
+pub fn file_buf<'a, W, I>(input: I) -> impl SerializeFn<W> + 'a
+where
+    I: Iterator<Item = &'a ZipEntryBuf> + Clone + 'a,
+    W: Write + 'a,
+{
+    let layout = layout(input.map(ZipEntryBuf::as_borrowed)).map(parts);
     all(layout)
 }
+
+/// Writes the parts, moving to the writer for the next disk
+/// where the layout starts one, or the data fills a segment
+fn split<'a, W, I, F>(
+    layout: LayoutIterator<'a, I>,
+    segment_size: u32,
+    mut writer: F,
+) -> Result<u16, GenError>
+where
+    I: Iterator<Item = ZipEntry<'a>> + Clone,
+    W: Write + 'a,
+    F: FnMut(u16) -> Result<W, GenError>,
+{
+    if let Some(smallest) = layout.segment_too_small(segment_size) {
+        return Err(GenError::BufferTooSmall(smallest as usize));
+    }
+    let layout = layout.split(segment_size);
+    let mut out = writer(0)?;
+    let mut placement = Placement::new(segment_size as u64);
+    for part in layout {
+        out = match part {
+            ZipPart::NextDisk(disk) => {
+                placement.disk = disk;
+                placement.offset = 0;
+                writer(disk)?
+            }
+            ZipPart::LocalFile(local) => {
+                let (mut out, written) = gen(local_file_header(&local), out)?;
+                placement.offset += written;
+                let mut data = local.compressed_data.bytes();
+                while data.len() as u64 > placement.room() {
+                    let (now, rest) = data.split_at(placement.room() as usize);
+                    gen(slice(now), out)?;
+                    placement.disk += 1;
+                    placement.offset = 0;
+                    out = writer(placement.disk)?;
+                    data = rest;
+                }
+                let (out, written) = gen(slice(data), out)?;
+                placement.offset += written;
+                out
+            }
+            part => {
+                let (out, written) = gen(parts(part), out)?;
+                placement.offset += written;
+                out
+            }
+        };
+    }
+    Ok(placement.disk)
+}
+
+/// Writes the entries as a split archive, at most segment_size bytes
+/// to each writer. The writer for each disk is made as it is needed,
+/// the last gets the end of central directory record. Gives the
+/// number of the last disk, an archive that fits in one segment is
+/// written to the first writer as if it wasn't split.
+/// Fails with BufferTooSmall, giving the smallest size that works, if a
+/// segment can't hold the spanning signature, a header and a byte of data
+pub fn split_file<'a, W, I, F>(input: I, segment_size: u32, writer: F) -> Result<u16, GenError>
+where
+    I: Iterator<Item = &'a ZipEntry<'a>> + Clone + 'a,
+    W: Write + 'a,
+    F: FnMut(u16) -> Result<W, GenError>,
+{
+    split(layout(input.copied()), segment_size, writer)
+}
+
+/// As split_file, but for owned entries
+#[cfg(feature = "alloc")]
+pub fn split_file_buf<'a, W, I, F>(input: I, segment_size: u32, writer: F) -> Result<u16, GenError>
+where
+    I: Iterator<Item = &'a ZipEntryBuf> + Clone + 'a,
+    W: Write + 'a,
+    F: FnMut(u16) -> Result<W, GenError>,
+{
+    split(
+        layout(input.map(ZipEntryBuf::as_borrowed)),
+        segment_size,
+        writer,
+    )
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+
+    use cookie_factory::{gen_simple, lib::std::io::Cursor};
+    use zipr_data::{
+        borrowed::{file::CompressedData, OEM437Str, ZipPath},
+        constants::END_OF_CENTRAL_DIRECTORY_HEADER,
+        CompressionMethod, DosDate, DosTime, HostCompatibility, Version, ZipSpecification,
+    };
+
+    use super::*;
+
+    fn hello(file_name: ZipPath<'_>) -> ZipEntry<'_> {
+        let version = Version {
+            host: HostCompatibility::MSDOS,
+            spec: ZipSpecification {
+                major: 2u8.try_into().unwrap(),
+                minor: 0u8.try_into().unwrap(),
+            },
+        };
+        let entry = ZipEntry {
+            version_made_by: version,
+            version_needed: version,
+            general_purpose: 0,
+            file_modification_time: DosTime::from_u16_unchecked(0),
+            file_modification_date: DosDate::from_u16_unchecked(0),
+            internal_file_attributes: 0,
+            external_file_attributes: 0,
+            file_name,
+            extra_field: Default::default(),
+            comment: Default::default(),
+            compressed_data: CompressedData::create_unchecked(
+                5,
+                CompressionMethod::Stored,
+                0x3610A686,
+                b"hello",
+            ),
+        };
+        entry
+    }
+
+    #[test]
+    fn owned_matches_borrowed() {
+        let entry = hello(ZipPath::try_from(OEM437Str::from(b"hello.txt")).unwrap());
+        let entries = [entry];
+        let owned = [entry.to_owned()];
+
+        let mut borrowed_out = [0u8; 256];
+        let mut owned_out = [0u8; 256];
+        gen_simple(file(entries.iter()), &mut borrowed_out[..]).unwrap();
+        gen_simple(file_buf(owned.iter()), &mut owned_out[..]).unwrap();
+
+        assert_eq!(borrowed_out, owned_out);
+    }
+
+    #[test]
+    fn owned_keeps_unchecked_names() {
+        let name = ZipPath::from_unchecked(OEM437Str::from(&b"dir\\file.txt"[..]));
+        let entry = hello(name);
+        let entries = [entry];
+        let owned = [entry.to_owned()];
+        assert_eq!(name, owned[0].as_borrowed().file_name);
+
+        let mut borrowed_out = [0u8; 256];
+        let mut owned_out = [0u8; 256];
+        gen_simple(file(entries.iter()), &mut borrowed_out[..]).unwrap();
+        gen_simple(file_buf(owned.iter()), &mut owned_out[..]).unwrap();
+
+        assert_eq!(borrowed_out, owned_out);
+        assert_eq!(b"dir\\file.txt", &owned_out[30..42]);
+    }
+
+    #[test]
+    fn split_data_spans_segments() {
+        let version = Version {
+            host: HostCompatibility::MSDOS,
+            spec: ZipSpecification {
+                major: 2u8.try_into().unwrap(),
+                minor: 0u8.try_into().unwrap(),
+            },
+        };
+        let data = [b'a'; 150];
+        let entry = ZipEntry {
+            version_made_by: version,
+            version_needed: version,
+            general_purpose: 0,
+            file_modification_time: DosTime::from_u16_unchecked(0),
+            file_modification_date: DosDate::from_u16_unchecked(0),
+            internal_file_attributes: 0,
+            external_file_attributes: 0,
+            file_name: ZipPath::try_from(OEM437Str::from(b"a.txt")).unwrap(),
+            extra_field: Default::default(),
+            comment: Default::default(),
+            compressed_data: CompressedData::create_unchecked(
+                150,
+                CompressionMethod::Stored,
+                0,
+                &data,
+            ),
+        };
+        let entries = [entry];
+
+        let mut buffers = [[0u8; 100]; 4];
+        let mut cursors = buffers.each_mut().map(|x| Cursor::new(&mut x[..]));
+        let mut writers = cursors.iter_mut();
+        let last = split_file(entries.iter(), 100, |_| {
+            writers.next().ok_or(GenError::NotYetImplemented)
+        })
+        .unwrap();
+        let lengths = cursors.map(|x| x.position());
+
+        // The header is 35 bytes after the signature, leaving 61 for data.
+        // The directory entry is 51 bytes, and the record 22
+        assert_eq!(2, last);
+        assert_eq!([100, 89, 73, 0], lengths);
+        assert_eq!(SPANNING_SIGNATURE, buffers[0][..4]);
+        assert_eq!(END_OF_CENTRAL_DIRECTORY_HEADER, buffers[2][51..55]);
+    }
+
+    #[test]
+    fn split_segment_too_small() {
+        let entries = [hello(ZipPath::try_from(OEM437Str::from(b"a.txt")).unwrap())];
+        // The signature, the 51 byte directory entry and a byte of data
+        for segment_size in [0, 55] {
+            let result = split_file(entries.iter(), segment_size, |_| {
+                Err::<Cursor<&mut [u8]>, _>(GenError::NotYetImplemented)
+            });
+            assert!(matches!(result, Err(GenError::BufferTooSmall(56))));
+        }
+
+        let mut buffers = [[0u8; 56]; 3];
+        let mut cursors = buffers.each_mut().map(|x| Cursor::new(&mut x[..]));
+        let mut writers = cursors.iter_mut();
+        let last = split_file(entries.iter(), 56, |_| {
+            writers.next().ok_or(GenError::NotYetImplemented)
+        })
+        .unwrap();
+        assert_eq!(2, last);
+    }
+}
@@ -10,6 +10,10 @@ use zipr_domain::zip_entry_to_files;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ZipPart<'a> {
+    /// Starts the first disk of a split archive
+    SpanningSignature,
+    /// The following parts are on the next disk of a split archive
+    NextDisk(u16),
     LocalFile(LocalFileEntry<'a>),
     DirectoryEntry(CentralDirectoryEntry<'a>),
     EndOfCentralDirectory(EndOfCentralDirectory<'a>),
@@ -23,13 +27,83 @@ enum State {
     Done,
 }
 
+/// Where the next byte is written, as a disk and an offset from its start.
+/// Headers are kept on one disk, data spans as many as it needs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Placement {
+    pub(crate) disk: u16,
+    pub(crate) offset: u64,
+    pub(crate) segment_size: u64,
+}
+
+impl Placement {
+    pub(crate) fn new(segment_size: u64) -> Self {
+        Placement {
+            disk: 0,
+            offset: 0,
+            segment_size,
+        }
+    }
+
+    /// Places a header, giving true if it starts a new disk
+    pub(crate) fn header(&mut self, length: u64) -> bool {
+        let next_disk = self.offset > 0 && self.offset + length > self.segment_size;
+        if next_disk {
+            self.disk += 1;
+            self.offset = 0;
+        }
+        self.offset += length;
+        next_disk
+    }
+
+    /// The bytes of data that fit on this disk
+    pub(crate) fn room(&self) -> u64 {
+        self.segment_size.saturating_sub(self.offset)
+    }
+
+    /// Places data, filling each disk before starting the next
+    pub(crate) fn data(&mut self, mut length: u64) {
+        while length > self.room() {
+            length -= self.room();
+            self.disk += 1;
+            self.offset = 0;
+        }
+        self.offset += length;
+    }
+}
+
+fn local_header_len(directory: &CentralDirectoryEntry<'_>) -> u64 {
+    (constants::LOCAL_FILE_MIN_LENGTH as usize
+        + directory.file_name.as_ref().len()
+        + directory.extra_field.serialized_len()) as u64
+}
+
+fn directory_header_len(directory: &CentralDirectoryEntry<'_>) -> u64 {
+    (constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as usize
+        + directory.file_name.as_ref().len()
+        + directory.extra_field.serialized_len()
+        + directory.comment.len()) as u64
+}
+
 #[derive(Clone)]
 pub struct LayoutIterator<'a, I>
 where
-    I: Iterator<Item = &'a ZipEntry<'a>>,
+    I: Iterator<Item = ZipEntry<'a>>,
 {
     items: u16,
-    position: u32,
+    /// Where the local files are, as they are written
+    local: Placement,
+    /// Where the local files are, again for the directory entries
+    position: Placement,
+    /// Where the directory entries are
+    directory: Placement,
+    /// Where the directory starts, once the first entry is placed
+    directory_start: Option<(u16, u64)>,
+    /// The directory entries on the disk of the last one
+    records_on_disk: u16,
+    /// Given after a NextDisk
+    pending: Option<ZipPart<'a>>,
+    spanned: bool,
     state: State,
     first_pass: I,
     second_pass: I,
@@ -37,60 +111,148 @@ where
     size_of_directory: u32,
 }
 
+impl<'a, I> LayoutIterator<'a, I>
+where
+    I: Iterator<Item = ZipEntry<'a>> + Clone,
+{
+    /// Spreads the archive over disks of at most segment_size bytes, as
+    /// read by split_file. An archive that fits in one segment isn't split
+    pub fn split(mut self, segment_size: u32) -> Self {
+        if self.unsplit_size() > segment_size as u64 {
+            let mut start = Placement::new(segment_size as u64);
+            start.header(constants::SPANNING_SIGNATURE.len() as u64);
+            self.local = start;
+            self.position = start;
+            self.spanned = true;
+        }
+        self
+    }
+
+    fn eocd_len(&self) -> u64 {
+        (constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE + self.comment.len()) as u64
+    }
+
+    /// The bytes of the whole archive when it is in one file
+    fn unsplit_size(&self) -> u64 {
+        let entries = self.first_pass.clone().map(|x| {
+            let (_, directory) = zip_entry_to_files(0, &x);
+            local_header_len(&directory)
+                + directory.compressed_size as u64
+                + directory_header_len(&directory)
+        });
+        entries.sum::<u64>() + self.eocd_len()
+    }
+
+    /// The smallest segment size that can hold this archive, if segment_size is smaller.
+    /// Each segment needs room for the spanning signature, the largest header and
+    /// a byte of data, otherwise the data never fits
+    pub(crate) fn segment_too_small(&self, segment_size: u32) -> Option<u64> {
+        if self.unsplit_size() <= segment_size as u64 {
+            return None;
+        }
+        let headers = self.first_pass.clone().map(|x| {
+            let (_, directory) = zip_entry_to_files(0, &x);
+            local_header_len(&directory).max(directory_header_len(&directory))
+        });
+        let largest = headers.fold(self.eocd_len(), u64::max);
+        let smallest = constants::SPANNING_SIGNATURE.len() as u64 + largest + 1;
+        Some(smallest).filter(|x| *x > segment_size as u64)
+    }
+
+    /// A part, after moving to the next disk if it started one
+    fn place(&mut self, next_disk: bool, disk: u16, part: ZipPart<'a>) -> ZipPart<'a> {
+        match next_disk {
+            true => {
+                self.pending = Some(part);
+                ZipPart::NextDisk(disk)
+            }
+            false => part,
+        }
+    }
+}
+
 impl<'a, I> Iterator for LayoutIterator<'a, I>
 where
-    I: Iterator<Item = &'a ZipEntry<'a>>,
+    I: Iterator<Item = ZipEntry<'a>> + Clone,
 {
     type Item = ZipPart<'a>;
     fn next(&mut self) -> Option<Self::Item> {
-        let state = &mut self.state;
+        if let Some(part) = self.pending.take() {
+            return Some(part);
+        }
+        if self.spanned {
+            self.spanned = false;
+            return Some(ZipPart::SpanningSignature);
+        }
         // If in local files. Just emit the structure
-        if state == &State::LocalFiles {
+        if self.state == State::LocalFiles {
             match self.first_pass.next() {
                 None => {
-                    *state = State::Directory;
+                    self.directory = self.local;
+                    self.state = State::Directory;
                 }
                 Some(x) => {
-                    let (local, _) = zip_entry_to_files(0, x);
-                    return Some(ZipPart::LocalFile(local));
+                    let (local, directory) = zip_entry_to_files(0, &x);
+                    let next_disk = self.local.header(local_header_len(&directory));
+                    self.local.data(directory.compressed_size as u64);
+                    let disk = self.local.disk;
+                    return Some(self.place(next_disk, disk, ZipPart::LocalFile(local)));
                 }
             }
         };
 
         // In directory emmitting mode
-        if state == &State::Directory {
+        if self.state == State::Directory {
             match self.second_pass.next() {
-                None => *state = State::Eocd,
+                None => self.state = State::Eocd,
                 Some(x) => {
-                    let (_, directory) = zip_entry_to_files(self.position, x);
-                    let increment = constants::LOCAL_FILE_MIN_LENGTH as u32
-                        + directory.file_name.as_ref().len() as u32
-                        + directory.compressed_size as u32
-                        + directory.extra_field.serialized_len() as u32;
-                    let directory_increment = constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as u32
-                        + directory.file_name.as_ref().len() as u32
-                        + directory.extra_field.serialized_len() as u32
-                        + directory.comment.len() as u32;
-
-                    self.position += increment;
-                    self.size_of_directory += directory_increment;
+                    let (_, mut directory) = zip_entry_to_files(0, &x);
+                    self.position.header(local_header_len(&directory));
+                    directory.disk_number_start = self.position.disk;
+                    directory.relative_offset =
+                        (self.position.offset - local_header_len(&directory)) as u32;
+                    self.position.data(directory.compressed_size as u64);
+
+                    let length = directory_header_len(&directory);
+                    let next_disk = self.directory.header(length);
+                    let disk = self.directory.disk;
+                    let start = (disk, self.directory.offset - length);
+                    self.directory_start.get_or_insert(start);
+                    self.records_on_disk = match next_disk {
+                        true => 1,
+                        false => self.records_on_disk + 1,
+                    };
+                    self.size_of_directory += length as u32;
                     self.items += 1;
 
-                    return Some(ZipPart::DirectoryEntry(directory));
+                    let part = ZipPart::DirectoryEntry(directory);
+                    return Some(self.place(next_disk, disk, part));
                 }
             }
         };
 
         // Finally write a single Eocd record out
-        if state == &State::Eocd {
+        if self.state == State::Eocd {
+            let length = constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE + self.comment.len();
+            let before = (self.directory.disk, self.directory.offset);
+            let next_disk = self.directory.header(length as u64);
+            if next_disk {
+                self.records_on_disk = 0;
+            }
+            let (directory_start_disk, offset_start_directory) =
+                self.directory_start.unwrap_or(before);
             let eocd = EndOfCentralDirectory {
+                number_of_this_disk: self.directory.disk,
+                directory_start_disk,
+                records_on_this_disk: self.records_on_disk,
                 total_number_records: self.items,
                 size_of_directory: self.size_of_directory,
-                offset_start_directory: self.position,
+                offset_start_directory: offset_start_directory as u32,
                 comment: self.comment,
             };
-            *state = State::Done;
-            return Some(ZipPart::EndOfCentralDirectory(eocd));
+            self.state = State::Done;
+            let disk = self.directory.disk;
+            return Some(self.place(next_disk, disk, ZipPart::EndOfCentralDirectory(eocd)));
         }
 
         // Else we have nothing more for you
@@ -102,14 +264,22 @@ where
 /// Note this is O(2N), as we need to run through the list twice
 pub fn layout<'a, I>(data: I) -> LayoutIterator<'a, I>
 where
-    I: Iterator<Item = &'a ZipEntry<'a>> + Clone,
+    I: Iterator<Item = ZipEntry<'a>> + Clone,
 {
     let first_pass = data.clone();
     let second_pass = data;
     let state = State::LocalFiles;
+    // Never full, so everything is on the first disk
+    let start = Placement::new(u64::MAX);
     LayoutIterator {
         items: 0,
-        position: 0,
+        local: start,
+        position: start,
+        directory: start,
+        directory_start: None,
+        records_on_disk: 0,
+        pending: None,
+        spanned: false,
         first_pass,
         second_pass,
         state,
@@ -176,7 +346,7 @@ mod tests {
     #[test]
     fn localfile_is_first() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.next().unwrap();
 
         match first_item {
@@ -188,7 +358,7 @@ mod tests {
     #[test]
     fn directory_is_third() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(2).unwrap();
 
         match first_item {
@@ -200,7 +370,7 @@ mod tests {
     #[test]
     fn directory_is_fourth() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(3).unwrap();
 
         match first_item {
@@ -212,7 +382,7 @@ mod tests {
     #[test]
     fn eocd_is_fifth() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(4).unwrap();
 
         match first_item {
@@ -224,7 +394,7 @@ mod tests {
     #[test]
     fn ends_after_eocd() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(5);
 
         assert_eq!(None, first_item);
@@ -233,7 +403,7 @@ mod tests {
     #[test]
     fn directory_is_third_name() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(2).unwrap();
 
         match first_item {
@@ -247,7 +417,7 @@ mod tests {
     #[test]
     fn directory_is_fourth_name() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(3).unwrap();
 
         match first_item {
@@ -261,7 +431,7 @@ mod tests {
     #[test]
     fn directory_is_third_offset() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(2).unwrap();
 
         match first_item {
@@ -275,7 +445,7 @@ mod tests {
     #[test]
     fn directory_is_fourth_offset() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(3).unwrap();
 
         let file_name_length = input[0].file_name.as_ref().len() as u32;
@@ -293,7 +463,7 @@ mod tests {
     #[test]
     fn eocd_is_fifth_directory_position() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(4).unwrap();
 
         let file_name_length_first = input[0].file_name.as_ref().len() as u32;
@@ -318,13 +488,13 @@ mod tests {
     #[test]
     fn eocd_is_fifth_size() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(4).unwrap();
         let file_names = 9 + 6_u32;
         let size = (2 * constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as u32) + file_names;
         match first_item {
             ZipPart::EndOfCentralDirectory(x) => {
-                assert_eq!(size as u32, x.size_of_directory)
+                assert_eq!(size, x.size_of_directory)
             }
             x => panic!("Expected Localfileentry: {:?}", x),
         }
@@ -333,7 +503,7 @@ mod tests {
     #[test]
     fn eocd_is_fifth_start() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(4).unwrap();
 
         let start_of_directory = 85_u32;
@@ -349,7 +519,7 @@ mod tests {
     #[test]
     fn eocd_is_fifth_count() {
         let input = two_entries();
-        let mut result = layout(input.iter());
+        let mut result = layout(input.iter().copied());
         let first_item = result.nth(4).unwrap();
 
         match first_item {
@@ -359,4 +529,48 @@ mod tests {
             x => panic!("Expected Localfileentry: {:?}", x),
         }
     }
+
+    #[test]
+    fn fits_in_segment_not_split() {
+        let input = two_entries();
+        let split = layout(input.iter().copied()).split(1000);
+
+        assert!(split.eq(layout(input.iter().copied())));
+    }
+
+    #[test]
+    fn split_disk_numbers() {
+        let input = two_entries();
+        // The locals are 44 and 41 bytes, the directory entries 55 and 52
+        let parts = layout(input.iter().copied()).split(100);
+        let mut disks = [None; 3];
+        let mut directories = [(0, 0); 2];
+        let mut index = 0;
+        let mut eocd = None;
+        for part in parts {
+            match part {
+                ZipPart::NextDisk(x) => disks[x as usize] = Some(index),
+                ZipPart::DirectoryEntry(x) => {
+                    directories[index] = (x.disk_number_start, x.relative_offset);
+                    index += 1;
+                }
+                ZipPart::EndOfCentralDirectory(x) => eocd = Some(x),
+                _ => (),
+            }
+        }
+        let eocd = eocd.unwrap();
+
+        // After the spanning signature, then after the first local
+        assert_eq!([(0, 4), (0, 48)], directories);
+        assert_eq!([None, Some(0), Some(1)], disks);
+        assert_eq!(2, eocd.number_of_this_disk);
+        assert_eq!(
+            (1, 0),
+            (eocd.directory_start_disk, eocd.offset_start_directory)
+        );
+        assert_eq!(
+            (1, 2),
+            (eocd.records_on_this_disk, eocd.total_number_records)
+        );
+    }
 }
@@ -0,0 +1,23 @@
+/// Info-ZIP extended timestamp extra field (0x5455)
+/// Times are seconds since the unix epoch.
+/// Note: the central directory copy usually only holds the
+/// modification time, even when the flags say more are present
+/// https://opensource.apple.com/source/zip/zip-6/unzip/unzip/proginfo/extra.fld
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExtendedTimestamp {
+    pub flags: u8,
+    pub modify: Option<i32>,
+    pub access: Option<i32>,
+    pub create: Option<i32>,
+}
+
+impl ExtendedTimestamp {
+    /// The length including the 4 byte header
+    pub fn serialized_len(&self) -> usize {
+        let times = [self.modify, self.access, self.create]
+            .iter()
+            .filter(|x| x.is_some())
+            .count();
+        4 + 1 + 4 * times
+    }
+}
@@ -2,8 +2,9 @@
 //! this can have quite a few values
 use crate::constants;
 
-use self::ntfs::NTFS;
+use self::{extended_timestamp::ExtendedTimestamp, ntfs::NTFS};
 
+pub mod extended_timestamp;
 pub mod ntfs;
 
 /// Extra fields that can be present
@@ -14,6 +15,7 @@ pub mod ntfs;
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ExtraField<'a> {
     NTFS(NTFS),
+    ExtendedTimestamp(ExtendedTimestamp),
     Unknown(&'a [u8]),
 }
 
@@ -30,6 +32,7 @@ impl ExtraField<'_> {
         match self {
             ExtraField::Unknown(x) => (*x).len(),
             ExtraField::NTFS(_) => constants::EXTRA_FIELD_NTFS_LENGTH as usize,
+            ExtraField::ExtendedTimestamp(x) => x.serialized_len(),
         }
     }
 }
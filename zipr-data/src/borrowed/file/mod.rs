@@ -14,6 +14,11 @@ use oem_437::OEM437Str;
 /// starts
 #[derive(Debug, PartialEq, Default, Copy, Clone)]
 pub struct EndOfCentralDirectory<'a> {
+    /// The disk the record is on, the last one of a split archive
+    pub number_of_this_disk: u16,
+    /// The disk the central directory starts on
+    pub directory_start_disk: u16,
+    pub records_on_this_disk: u16,
     pub total_number_records: u16,
     pub size_of_directory: u32,
     pub offset_start_directory: u32,
@@ -36,6 +41,9 @@ pub struct CentralDirectoryEntry<'a> {
     pub uncompressed_size: u32,
     pub internal_file_attributes: u16,
     pub external_file_attributes: u32,
+    /// The disk the local file starts on
+    pub disk_number_start: u16,
+    /// From the start of the disk the local file is on
     pub relative_offset: u32,
     pub file_name: ZipPath<'a>,
     pub extra_field: ExtraField<'a>,
@@ -56,3 +64,12 @@ pub struct LocalFileEntry<'a> {
     pub extra_field: ExtraField<'a>,
     pub compressed_data: CompressedData<'a>,
 }
+
+/// Written after the data when the local header couldn't hold
+/// the crc and sizes, eg when streaming. See GENERAL_PURPOSE_DATA_DESCRIPTOR
+#[derive(Debug, PartialEq, Eq, Default, Copy, Clone)]
+pub struct DataDescriptor {
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+}
@@ -10,7 +10,7 @@ use super::{extra_field::ExtraField, file::CompressedData, ZipPath};
 /// and the directory entry, which has some permissions
 /// Note: as is this doesn't represent items in a zip file
 /// But it is easier to work with in an applicative style
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct ZipEntry<'a> {
     pub version_made_by: Version,
     pub version_needed: Version,
@@ -16,14 +16,57 @@ use oem_437::OEM437Str;
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct ZipPath<'a>(OEM437Str<'a>);
 
-#[derive(Debug)]
-pub enum ZipPathError {}
+/// Why a name isn't a valid zip path
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ZipPathError {
+    Empty,
+    /// Paths must be relative, eg not /etc/passwd
+    LeadingSlash,
+    /// Only forward slashes are allowed, this is the position of the first backslash
+    Backslash(usize),
+    /// Paths can't start with a drive or device, eg C:
+    DriveLetter,
+    /// A nul byte at this position
+    Nul(usize),
+}
+
+impl core::fmt::Display for ZipPathError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ZipPathError::Empty => f.write_str("Path is empty"),
+            ZipPathError::LeadingSlash => f.write_str("Path starts with a slash"),
+            ZipPathError::Backslash(x) => write!(f, "Path has a backslash at {}", x),
+            ZipPathError::DriveLetter => f.write_str("Path starts with a drive letter"),
+            ZipPathError::Nul(x) => write!(f, "Path has a nul byte at {}", x),
+        }
+    }
+}
 
-/// Creates a the zippath from a oem437string
+/// Checks the rules from APPNOTE 4.4.17
+fn validate(bytes: &[u8]) -> Result<(), ZipPathError> {
+    match bytes {
+        [] => return Err(ZipPathError::Empty),
+        [b'/', ..] => return Err(ZipPathError::LeadingSlash),
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => {
+            return Err(ZipPathError::DriveLetter)
+        }
+        _ => {}
+    }
+    if let Some(x) = bytes.iter().position(|x| *x == b'\\') {
+        return Err(ZipPathError::Backslash(x));
+    }
+    if let Some(x) = bytes.iter().position(|x| *x == 0) {
+        return Err(ZipPathError::Nul(x));
+    }
+    Ok(())
+}
+
+/// Creates a the zippath from a oem437string, checking it is valid
 impl<'a> TryFrom<OEM437Str<'a>> for ZipPath<'a> {
     type Error = ZipPathError;
 
     fn try_from(value: OEM437Str<'a>) -> Result<Self, Self::Error> {
+        validate(&value)?;
         Ok(ZipPath(value))
     }
 }
@@ -48,15 +91,92 @@ impl<'a> AsRef<OEM437Str<'a>> for ZipPath<'a> {
     }
 }
 
-/*
 impl<'a> ZipPath<'a> {
-   pub fn from_cp437(string: OEM437Str<'a>) -> Result<Self, ZipPathError> {
-        // need to validate this in future
-        Ok(ZipPath(string))
+    /// Wraps the name without checking it.
+    /// Names read from existing archives may break the rules,
+    /// so parsers keep them as found. See validate
+    pub fn from_unchecked(name: OEM437Str<'a>) -> Self {
+        ZipPath(name)
+    }
+
+    /// Checks the name follows the zip path rules
+    pub fn validate(&self) -> Result<(), ZipPathError> {
+        validate(&self.0)
+    }
+
+    /// The parts between slashes, skipping empty ones
+    pub fn components(&self) -> impl Iterator<Item = OEM437Str<'a>> + 'a {
+        let bytes = self.0.as_bytes();
+        bytes
+            .split(|x| *x == b'/')
+            .filter(|x| !x.is_empty())
+            .map(OEM437Str::from)
+    }
+
+    /// The containing directory, including its trailing slash
+    /// so it matches the directory entry name. None at the top level
+    pub fn parent(&self) -> Option<ZipPath<'a>> {
+        let bytes = self.0.as_bytes();
+        let trimmed = bytes.strip_suffix(b"/").unwrap_or(bytes);
+        let end = trimmed.iter().rposition(|x| *x == b'/')?;
+        Some(ZipPath(OEM437Str::from(&trimmed[..=end])))
+    }
+
+    /// The last component, without any trailing slash
+    pub fn file_name(&self) -> Option<OEM437Str<'a>> {
+        self.components().last()
+    }
+
+    /// Directory entries end with a slash
+    pub fn is_dir(&self) -> bool {
+        self.0.last() == Some(&b'/')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(x: &'static [u8]) -> Result<ZipPath<'static>, ZipPathError> {
+        ZipPath::try_from(x)
+    }
+
+    #[test]
+    fn valid() {
+        assert!(path(b"file.txt").is_ok());
+        assert!(path(b"folder/").is_ok());
+        assert!(path(b"a/b/c.txt").is_ok());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Err(ZipPathError::Empty), path(b""));
+        assert_eq!(Err(ZipPathError::LeadingSlash), path(b"/etc/passwd"));
+        assert_eq!(Err(ZipPathError::Backslash(1)), path(b"a\\b"));
+        assert_eq!(Err(ZipPathError::DriveLetter), path(b"C:/windows"));
+        assert_eq!(Err(ZipPathError::Nul(1)), path(b"a\0b"));
+    }
+
+    #[test]
+    fn components() {
+        let x = path(b"a/b/c.txt").unwrap();
+        let parts: [&[u8]; 3] = [b"a", b"b", b"c.txt"];
+        assert!(x
+            .components()
+            .map(|x| x.as_bytes())
+            .eq(parts.iter().copied()));
+        assert_eq!(Some(OEM437Str::from(b"c.txt")), x.file_name());
+        assert_eq!(Some(path(b"a/b/").unwrap()), x.parent());
+        assert!(!x.is_dir());
     }
 
-    pub fn to_cp437(&self) -> OEM437Str<'a> {
-        self.0
+    #[test]
+    fn directories() {
+        let x = path(b"a/b/").unwrap();
+        assert!(x.is_dir());
+        assert_eq!(Some(OEM437Str::from(b"b")), x.file_name());
+        assert_eq!(Some(path(b"a/").unwrap()), x.parent());
+        assert_eq!(None, path(b"a/").unwrap().parent());
+        assert_eq!(None, path(b"a").unwrap().parent());
     }
 }
- */
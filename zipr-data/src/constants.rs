@@ -4,6 +4,9 @@
 
 pub const END_OF_CENTRAL_DIRECTORY_HEADER: [u8; 4] = [0x50, 0x4B, 0x05, 0x06];
 pub const END_OF_CENTRAL_DIRECTORY_MIN_SIZE: usize = 22;
+/// The record with the longest possible comment
+pub const END_OF_CENTRAL_DIRECTORY_MAX_SIZE: usize =
+    END_OF_CENTRAL_DIRECTORY_MIN_SIZE + u16::MAX as usize;
 
 pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x01, 0x02];
 pub const CENTRAL_DIRECTORY_HEAD_MIN_LENGTH: u8 = 46;
@@ -11,8 +14,27 @@ pub const CENTRAL_DIRECTORY_HEAD_MIN_LENGTH: u8 = 46;
 pub const LOCAL_FILE_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x3, 0x4];
 pub const LOCAL_FILE_MIN_LENGTH: u8 = 30;
 
+pub const DATA_DESCRIPTOR_SIGNATURE: [u8; 4] = [0x50, 0x4B, 0x07, 0x08];
+/// Starts the first segment of a split or spanned archive,
+/// the same bytes as the data descriptor signature
+pub const SPANNING_SIGNATURE: [u8; 4] = DATA_DESCRIPTOR_SIGNATURE;
+/// crc32, compressed and uncompressed size, the signature is optional
+pub const DATA_DESCRIPTOR_LENGTH: u8 = 12;
+
+/// Bit 3, the crc and sizes are in a data descriptor after the data
+/// so the local header holds zeros
+pub const GENERAL_PURPOSE_DATA_DESCRIPTOR: u16 = 0x08;
+
 pub const EXTRA_FIELD_NTFS_HEADER: [u8; 2] = [0x0a, 0x00];
 pub const EXTRA_FIELD_NTFS_LENGTH: u8 = 36;
 
+pub const EXTRA_FIELD_EXTENDED_TIMESTAMP_HEADER: [u8; 2] = [0x55, 0x54];
+
 pub const COMPRESSION_STORED: [u8; 2] = [0x00, 0x00];
 pub const COMPRESSION_DEFLATE: [u8; 2] = [0x08, 0x00];
+
+pub const EXTERNAL_ATTRIBUTE_DOS_READ_ONLY: u32 = 0x01;
+pub const EXTERNAL_ATTRIBUTE_DOS_DIRECTORY: u32 = 0x10;
+pub const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+pub const UNIX_DIRECTORY: u32 = 0o040000;
+pub const UNIX_SYMLINK: u32 = 0o120000;
@@ -1,20 +1,36 @@
 use core::fmt::Debug;
+
+use crate::{days_in_month, DosDateTimeError, DOS_MAX_YEAR, DOS_MIN_YEAR};
 /// Dos Date format as u16.
 #[derive(PartialEq, Copy, Clone)]
 pub struct DosDate(u16);
 
 impl DosDate {
-    pub fn from_u16_unchecked(date: u16) -> Self {
+    pub const fn from_u16_unchecked(date: u16) -> Self {
         DosDate(date)
     }
 
+    /// Checked constructor, the year must be in 1980-2107
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, DosDateTimeError> {
+        if !(DOS_MIN_YEAR..=DOS_MAX_YEAR).contains(&year) {
+            return Err(DosDateTimeError::YearOutOfRange(year.into()));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(DosDateTimeError::InvalidMonth(month));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(DosDateTimeError::InvalidDay { year, month, day });
+        }
+        let date = (year - DOS_MIN_YEAR) << 9 | (month as u16) << 5 | day as u16;
+        Ok(DosDate(date))
+    }
+
     pub fn as_bytes(&self) -> u16 {
         self.0
     }
 
     pub fn year(&self) -> u16 {
-        let year = (self.0 >> 9) + 1980;
-        year as u16
+        (self.0 >> 9) + 1980
     }
 
     pub fn month(&self) -> u8 {
@@ -71,4 +87,12 @@ mod tests {
         let day = date.day();
         assert_eq!(3, day);
     }
+
+    #[test]
+    fn test_new() {
+        let date = DosDate::new(2020, 12, 3).unwrap();
+        assert_eq!(DATE1, date.as_bytes());
+        assert!(DosDate::new(2020, 13, 3).is_err());
+        assert!(DosDate::new(2108, 1, 1).is_err());
+    }
 }
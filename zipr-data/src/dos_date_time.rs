@@ -0,0 +1,288 @@
+use core::fmt::Display;
+
+use crate::{DosDate, DosTime};
+
+/// The first year a dos date can hold
+pub const DOS_MIN_YEAR: u16 = 1980;
+/// The last year a dos date can hold
+pub const DOS_MAX_YEAR: u16 = 2107;
+
+/// Reasons a date or time can't be stored in dos format
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DosDateTimeError {
+    /// Dos dates only cover 1980 to 2107
+    YearOutOfRange(i32),
+    InvalidMonth(u8),
+    /// The day doesn't exist in the month, eg Feb 30
+    InvalidDay {
+        year: u16,
+        month: u8,
+        day: u8,
+    },
+    InvalidHour(u8),
+    InvalidMinute(u8),
+    InvalidSecond(u8),
+    /// Dos times are local, and this one was skipped
+    /// by a daylight savings change
+    NonexistentLocalTime,
+}
+
+impl Display for DosDateTimeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DosDateTimeError::YearOutOfRange(year) => write!(
+                f,
+                "Year {} is outside the dos range {}-{}",
+                year, DOS_MIN_YEAR, DOS_MAX_YEAR
+            ),
+            DosDateTimeError::InvalidMonth(month) => write!(f, "Invalid month {}", month),
+            DosDateTimeError::InvalidDay { year, month, day } => {
+                write!(f, "Invalid date {:04}-{:02}-{:02}", year, month, day)
+            }
+            DosDateTimeError::InvalidHour(hour) => write!(f, "Invalid hour {}", hour),
+            DosDateTimeError::InvalidMinute(min) => write!(f, "Invalid minute {}", min),
+            DosDateTimeError::InvalidSecond(sec) => write!(f, "Invalid second {}", sec),
+            DosDateTimeError::NonexistentLocalTime => f.write_str("Nonexistent local time"),
+        }
+    }
+}
+
+pub(crate) fn days_in_month(year: u16, month: u8) -> u8 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A dos date and time together, as stored in zip headers.
+/// These have no timezone, and are normally local time
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DosDateTime {
+    pub date: DosDate,
+    pub time: DosTime,
+}
+
+impl DosDateTime {
+    /// 1980-01-01 00:00:00
+    pub const MIN: DosDateTime = DosDateTime {
+        date: DosDate::from_u16_unchecked(1 << 5 | 1),
+        time: DosTime::from_u16_unchecked(0),
+    };
+
+    /// 2107-12-31 23:59:58
+    pub const MAX: DosDateTime = DosDateTime {
+        date: DosDate::from_u16_unchecked(127 << 9 | 12 << 5 | 31),
+        time: DosTime::from_u16_unchecked(23 << 11 | 59 << 5 | 29),
+    };
+
+    /// Checked constructor, odd seconds are rounded down
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        min: u8,
+        sec: u8,
+    ) -> Result<Self, DosDateTimeError> {
+        Ok(DosDateTime {
+            date: DosDate::new(year, month, day)?,
+            time: DosTime::new(hour, min, sec)?,
+        })
+    }
+
+    /// As new, but years before 1980 become MIN and after 2107 become MAX.
+    /// Invalid fields are still an error
+    pub fn clamped(
+        year: i32,
+        month: u8,
+        day: u8,
+        hour: u8,
+        min: u8,
+        sec: u8,
+    ) -> Result<Self, DosDateTimeError> {
+        match year {
+            year if year < DOS_MIN_YEAR as i32 => Ok(DosDateTime::MIN),
+            year if year > DOS_MAX_YEAR as i32 => Ok(DosDateTime::MAX),
+            year => DosDateTime::new(year as u16, month, day, hour, min, sec),
+        }
+    }
+
+    /// Checks the stored fields are a real date and time.
+    /// Values read from a file may not be
+    pub fn validate(&self) -> Result<(), DosDateTimeError> {
+        let (date, time) = (self.date.as_bytes(), self.time.as_bytes());
+        let month = ((date >> 5) & 0x0F) as u8;
+        let day = (date & 0x1F) as u8;
+        DosDate::new(self.date.year(), month, day)?;
+        DosTime::new(self.time.hour(), self.time.min(), ((time & 0x1F) * 2) as u8)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_conversions {
+    use core::convert::TryFrom;
+
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+
+    use super::{DosDateTime, DosDateTimeError};
+
+    impl TryFrom<NaiveDateTime> for DosDateTime {
+        type Error = DosDateTimeError;
+
+        fn try_from(value: NaiveDateTime) -> Result<Self, Self::Error> {
+            let year = u16::try_from(value.year())
+                .map_err(|_| DosDateTimeError::YearOutOfRange(value.year()))?;
+            DosDateTime::new(
+                year,
+                value.month() as u8,
+                value.day() as u8,
+                value.hour() as u8,
+                value.minute() as u8,
+                value.second() as u8,
+            )
+        }
+    }
+
+    impl TryFrom<DosDateTime> for NaiveDateTime {
+        type Error = DosDateTimeError;
+
+        fn try_from(value: DosDateTime) -> Result<Self, Self::Error> {
+            value.validate()?;
+            let (date, time) = (value.date, value.time);
+            NaiveDate::from_ymd_opt(date.year().into(), date.month().into(), date.day().into())
+                .and_then(|x| {
+                    x.and_hms_opt(time.hour().into(), time.min().into(), time.sec().into())
+                })
+                .ok_or(DosDateTimeError::YearOutOfRange(date.year().into()))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_conversions {
+    use core::convert::TryFrom;
+
+    use time::{Date, Month, PrimitiveDateTime, Time};
+
+    use super::{DosDateTime, DosDateTimeError};
+
+    impl TryFrom<PrimitiveDateTime> for DosDateTime {
+        type Error = DosDateTimeError;
+
+        fn try_from(value: PrimitiveDateTime) -> Result<Self, Self::Error> {
+            let year = u16::try_from(value.year())
+                .map_err(|_| DosDateTimeError::YearOutOfRange(value.year()))?;
+            DosDateTime::new(
+                year,
+                value.month() as u8,
+                value.day(),
+                value.hour(),
+                value.minute(),
+                value.second(),
+            )
+        }
+    }
+
+    impl TryFrom<DosDateTime> for PrimitiveDateTime {
+        type Error = DosDateTimeError;
+
+        fn try_from(value: DosDateTime) -> Result<Self, Self::Error> {
+            value.validate()?;
+            let (date, time) = (value.date, value.time);
+            let month = Month::try_from(date.month())
+                .map_err(|_| DosDateTimeError::InvalidMonth(date.month()))?;
+            let day = Date::from_calendar_date(date.year().into(), month, date.day())
+                .map_err(|_| DosDateTimeError::YearOutOfRange(date.year().into()))?;
+            let time = Time::from_hms(time.hour(), time.min(), time.sec())
+                .map_err(|_| DosDateTimeError::InvalidHour(time.hour()))?;
+            Ok(PrimitiveDateTime::new(day, time))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_rounds_seconds_down() {
+        let x = DosDateTime::new(2020, 12, 3, 20, 6, 25).unwrap();
+        assert_eq!(24, x.time.sec());
+        assert_eq!(Ok(()), x.validate());
+    }
+
+    #[test]
+    fn new_rejects_invalid() {
+        assert_eq!(
+            Err(DosDateTimeError::InvalidDay {
+                year: 2021,
+                month: 2,
+                day: 29
+            }),
+            DosDateTime::new(2021, 2, 29, 0, 0, 0)
+        );
+        assert!(DosDateTime::new(2020, 2, 29, 0, 0, 0).is_ok());
+        assert_eq!(
+            Err(DosDateTimeError::YearOutOfRange(1979)),
+            DosDateTime::new(1979, 1, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            Err(DosDateTimeError::InvalidMinute(60)),
+            DosDateTime::new(2000, 1, 1, 0, 60, 0)
+        );
+    }
+
+    #[test]
+    fn clamped() {
+        assert_eq!(
+            Ok(DosDateTime::MIN),
+            DosDateTime::clamped(1970, 1, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            Ok(DosDateTime::MAX),
+            DosDateTime::clamped(2200, 1, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            DosDateTime::new(2107, 12, 31, 23, 59, 59),
+            Ok(DosDateTime::MAX)
+        );
+    }
+
+    #[test]
+    fn validate_stored() {
+        let x = DosDateTime {
+            date: DosDate::from_u16_unchecked(41 << 9 | 2 << 5 | 30),
+            time: DosTime::from_u16_unchecked(0),
+        };
+        assert!(matches!(
+            x.validate(),
+            Err(DosDateTimeError::InvalidDay { .. })
+        ));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_round_trip() {
+        use core::convert::TryFrom;
+        let naive = chrono::NaiveDate::from_ymd_opt(2020, 12, 3)
+            .unwrap()
+            .and_hms_opt(20, 6, 24)
+            .unwrap();
+        let dos = DosDateTime::try_from(naive).unwrap();
+        assert_eq!(Ok(naive), chrono::NaiveDateTime::try_from(dos));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time_round_trip() {
+        use core::convert::TryFrom;
+        let date = time::Date::from_calendar_date(2020, time::Month::December, 3).unwrap();
+        let primitive = date.with_hms(20, 6, 24).unwrap();
+        let dos = DosDateTime::try_from(primitive).unwrap();
+        assert_eq!(Ok(primitive), time::PrimitiveDateTime::try_from(dos));
+    }
+}
@@ -1,5 +1,7 @@
 use core::fmt::Debug;
 
+use crate::DosDateTimeError;
+
 /// 16 bit dos time data structure. Stored internally in u16
 /// Note: dostime stores 2 second intervals, so there are some limitations
 /// to this time format
@@ -7,10 +9,26 @@ use core::fmt::Debug;
 pub struct DosTime(u16);
 
 impl DosTime {
-    pub fn from_u16_unchecked(time: u16) -> Self {
+    pub const fn from_u16_unchecked(time: u16) -> Self {
         DosTime(time)
     }
 
+    /// Checked constructor, odd seconds are rounded down
+    /// as only 2 second intervals can be stored
+    pub fn new(hour: u8, min: u8, sec: u8) -> Result<Self, DosDateTimeError> {
+        if hour > 23 {
+            return Err(DosDateTimeError::InvalidHour(hour));
+        }
+        if min > 59 {
+            return Err(DosDateTimeError::InvalidMinute(min));
+        }
+        if sec > 59 {
+            return Err(DosDateTimeError::InvalidSecond(sec));
+        }
+        let time = (hour as u16) << 11 | (min as u16) << 5 | (sec as u16 / 2);
+        Ok(DosTime(time))
+    }
+
     pub fn as_bytes(&self) -> u16 {
         self.0
     }
@@ -66,4 +84,12 @@ mod tests {
         let min = time.min();
         assert_eq!(6, min);
     }
+
+    #[test]
+    fn test_new() {
+        let time = DosTime::new(20, 6, 25).unwrap();
+        assert_eq!(TIME1, time.as_bytes());
+        assert!(DosTime::new(24, 0, 0).is_err());
+        assert!(DosTime::new(0, 0, 60).is_err());
+    }
 }
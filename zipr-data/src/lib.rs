@@ -9,20 +9,26 @@
 //! parse it through to the serialization libraries
 
 #![no_std]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod borrowed;
 pub mod constants;
+#[cfg(feature = "alloc")]
+pub mod owned;
 mod util;
 
 mod compression_method;
 mod cp437;
 mod dos_date;
+mod dos_date_time;
 mod dos_time;
 mod version;
 mod wintimestamp;
 
 pub use compression_method::*;
-pub use cp437::*;
 pub use dos_date::*;
+pub use dos_date_time::*;
 pub use dos_time::*;
 pub use version::*;
 pub use wintimestamp::*;
@@ -0,0 +1,74 @@
+use alloc::vec::Vec;
+
+use crate::{borrowed::file::CompressedData, CompressionMethod};
+
+/// An owned CompressedData
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompressedDataBuf {
+    bytes: Vec<u8>,
+    crc32: u32,
+    uncompressed_size: u32,
+    compression_method: CompressionMethod,
+}
+
+impl CompressedDataBuf {
+    pub fn create_unchecked(
+        uncompressed_size: u32,
+        compression_method: CompressionMethod,
+        crc32: u32,
+        bytes: Vec<u8>,
+    ) -> Self {
+        CompressedDataBuf {
+            bytes,
+            crc32,
+            uncompressed_size,
+            compression_method,
+        }
+    }
+
+    pub fn compression_method(&self) -> CompressionMethod {
+        self.compression_method
+    }
+
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    pub fn uncompressed_size(&self) -> u32 {
+        self.uncompressed_size
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn as_borrowed(&self) -> CompressedData<'_> {
+        CompressedData::create_unchecked(
+            self.uncompressed_size,
+            self.compression_method,
+            self.crc32,
+            &self.bytes,
+        )
+    }
+}
+
+impl CompressedData<'_> {
+    pub fn to_owned(&self) -> CompressedDataBuf {
+        CompressedDataBuf::create_unchecked(
+            self.uncompressed_size(),
+            self.compression_method(),
+            self.crc32(),
+            self.bytes().to_vec(),
+        )
+    }
+}
+
+impl<'a> From<CompressedData<'a>> for CompressedDataBuf {
+    fn from(value: CompressedData<'a>) -> Self {
+        value.to_owned()
+    }
+}
@@ -0,0 +1,43 @@
+use alloc::vec::Vec;
+
+use crate::borrowed::extra_field::{extended_timestamp::ExtendedTimestamp, ntfs::NTFS, ExtraField};
+
+/// An owned ExtraField
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExtraFieldBuf {
+    NTFS(NTFS),
+    ExtendedTimestamp(ExtendedTimestamp),
+    Unknown(Vec<u8>),
+}
+
+impl Default for ExtraFieldBuf {
+    fn default() -> Self {
+        ExtraFieldBuf::Unknown(Vec::new())
+    }
+}
+
+impl ExtraFieldBuf {
+    pub fn as_borrowed(&self) -> ExtraField<'_> {
+        match self {
+            ExtraFieldBuf::NTFS(x) => ExtraField::NTFS(*x),
+            ExtraFieldBuf::ExtendedTimestamp(x) => ExtraField::ExtendedTimestamp(*x),
+            ExtraFieldBuf::Unknown(x) => ExtraField::Unknown(x),
+        }
+    }
+}
+
+impl ExtraField<'_> {
+    pub fn to_owned(&self) -> ExtraFieldBuf {
+        match self {
+            ExtraField::NTFS(x) => ExtraFieldBuf::NTFS(*x),
+            ExtraField::ExtendedTimestamp(x) => ExtraFieldBuf::ExtendedTimestamp(*x),
+            ExtraField::Unknown(x) => ExtraFieldBuf::Unknown(x.to_vec()),
+        }
+    }
+}
+
+impl<'a> From<ExtraField<'a>> for ExtraFieldBuf {
+    fn from(value: ExtraField<'a>) -> Self {
+        value.to_owned()
+    }
+}
@@ -0,0 +1,16 @@
+//! Owned data structures for zip files
+//!
+//! These mirror the types in borrowed, but own their bytes.
+//! This allows entries to outlive the buffer they were parsed from,
+//! be sent across threads, or be built from generated data.
+//! Use as_borrowed to get the borrowed form for serializing,
+//! and to_owned on the borrowed types to go the other way
+mod compressed_data_buf;
+mod extra_field_buf;
+mod zip_entry_buf;
+mod zip_path_buf;
+
+pub use compressed_data_buf::*;
+pub use extra_field_buf::*;
+pub use zip_entry_buf::*;
+pub use zip_path_buf::*;
@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+
+use oem_437::OEM437Str;
+
+use crate::{borrowed::ZipEntry, DosDate, DosTime, Version};
+
+use super::{CompressedDataBuf, ExtraFieldBuf, ZipPathBuf};
+
+/// An owned ZipEntry
+/// The comment is kept as OEM437 bytes
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZipEntryBuf {
+    pub version_made_by: Version,
+    pub version_needed: Version,
+    pub general_purpose: u16,
+    pub file_modification_time: DosTime,
+    pub file_modification_date: DosDate,
+    pub internal_file_attributes: u16,
+    pub external_file_attributes: u32,
+    pub file_name: ZipPathBuf,
+    pub extra_field: ExtraFieldBuf,
+    pub comment: Vec<u8>,
+    pub compressed_data: CompressedDataBuf,
+}
+
+impl ZipEntryBuf {
+    pub fn as_borrowed(&self) -> ZipEntry<'_> {
+        ZipEntry {
+            version_made_by: self.version_made_by,
+            version_needed: self.version_needed,
+            general_purpose: self.general_purpose,
+            file_modification_time: self.file_modification_time,
+            file_modification_date: self.file_modification_date,
+            internal_file_attributes: self.internal_file_attributes,
+            external_file_attributes: self.external_file_attributes,
+            file_name: self.file_name.as_borrowed(),
+            extra_field: self.extra_field.as_borrowed(),
+            comment: OEM437Str::from(&self.comment[..]),
+            compressed_data: self.compressed_data.as_borrowed(),
+        }
+    }
+}
+
+impl ZipEntry<'_> {
+    pub fn to_owned(&self) -> ZipEntryBuf {
+        ZipEntryBuf {
+            version_made_by: self.version_made_by,
+            version_needed: self.version_needed,
+            general_purpose: self.general_purpose,
+            file_modification_time: self.file_modification_time,
+            file_modification_date: self.file_modification_date,
+            internal_file_attributes: self.internal_file_attributes,
+            external_file_attributes: self.external_file_attributes,
+            file_name: self.file_name.to_owned(),
+            extra_field: self.extra_field.to_owned(),
+            comment: self.comment.to_vec(),
+            compressed_data: self.compressed_data.to_owned(),
+        }
+    }
+}
+
+impl<'a> From<&ZipEntry<'a>> for ZipEntryBuf {
+    fn from(value: &ZipEntry<'a>) -> Self {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+
+    use crate::{
+        borrowed::{extra_field::ExtraField, file::CompressedData, ZipPath},
+        CompressionMethod, HostCompatibility, ZipSpecification,
+    };
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let version = Version {
+            host: HostCompatibility::UNIX,
+            spec: ZipSpecification {
+                major: 2u8.try_into().unwrap(),
+                minor: 0u8.try_into().unwrap(),
+            },
+        };
+        let entry = ZipEntry {
+            version_made_by: version,
+            version_needed: version,
+            general_purpose: 0,
+            file_modification_time: DosTime::from_u16_unchecked(41164),
+            file_modification_date: DosDate::from_u16_unchecked(20867),
+            internal_file_attributes: 0,
+            external_file_attributes: 0o100644 << 16,
+            file_name: ZipPath::try_from(&b"hello.txt"[..]).unwrap(),
+            extra_field: ExtraField::Unknown(&[1, 2, 3, 4]),
+            comment: OEM437Str::from(&b"comment"[..]),
+            compressed_data: CompressedData::create_unchecked(
+                5,
+                CompressionMethod::Stored,
+                0x3610A686,
+                b"hello",
+            ),
+        };
+
+        let owned = entry.to_owned();
+        assert_eq!(entry, owned.as_borrowed());
+        assert_eq!(b"hello", owned.compressed_data.bytes());
+    }
+}
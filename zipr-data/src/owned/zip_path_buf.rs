@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use oem_437::OEM437Str;
+
+use crate::borrowed::{ZipPath, ZipPathError};
+
+/// An owned ZipPath
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct ZipPathBuf(Vec<u8>);
+
+impl ZipPathBuf {
+    /// Names copied from parsed archives aren't validated, see ZipPath::from_unchecked
+    pub fn as_borrowed(&self) -> ZipPath<'_> {
+        ZipPath::from_unchecked(OEM437Str::from(&self.0[..]))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Leniently fixes up a name before validating it.
+    /// Backslashes become forward slashes, and any leading
+    /// slashes and ./ are removed
+    pub fn normalize(name: &[u8]) -> Result<Self, ZipPathError> {
+        let mut bytes: Vec<u8> = name
+            .iter()
+            .map(|x| if *x == b'\\' { b'/' } else { *x })
+            .collect();
+        let mut start = 0;
+        loop {
+            match &bytes[start..] {
+                [b'/', ..] => start += 1,
+                [b'.', b'/', ..] => start += 2,
+                _ => break,
+            }
+        }
+        bytes.drain(..start);
+        ZipPathBuf::try_from(bytes)
+    }
+}
+
+impl ZipPath<'_> {
+    pub fn to_owned(&self) -> ZipPathBuf {
+        let str: &OEM437Str = self.as_ref();
+        ZipPathBuf(str.to_vec())
+    }
+}
+
+/// Validates the bytes as a ZipPath
+impl TryFrom<Vec<u8>> for ZipPathBuf {
+    type Error = ZipPathError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        ZipPath::try_from(&value[..])?;
+        Ok(ZipPathBuf(value))
+    }
+}
+
+impl<'a> From<ZipPath<'a>> for ZipPathBuf {
+    fn from(value: ZipPath<'a>) -> Self {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = ZipPath::try_from(&b"folder/file.txt"[..]).unwrap();
+        let owned = path.to_owned();
+        assert_eq!(path, owned.as_borrowed());
+        assert_eq!(
+            owned,
+            ZipPathBuf::try_from(b"folder/file.txt".to_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_unchecked() {
+        let path = ZipPath::from_unchecked(OEM437Str::from(&b"dir\\file.txt"[..]));
+        let owned = path.to_owned();
+        assert_eq!(path, owned.as_borrowed());
+        assert!(owned.as_borrowed().validate().is_err());
+    }
+
+    #[test]
+    fn normalize() {
+        let expected = ZipPathBuf::try_from(b"a/b.txt".to_vec()).unwrap();
+        assert_eq!(Ok(expected.clone()), ZipPathBuf::normalize(b"a\\b.txt"));
+        assert_eq!(Ok(expected.clone()), ZipPathBuf::normalize(b"/./a/b.txt"));
+        assert_eq!(Ok(expected), ZipPathBuf::normalize(b"\\\\a\\b.txt"));
+        assert_eq!(Err(ZipPathError::Empty), ZipPathBuf::normalize(b"./"));
+        assert_eq!(
+            Err(ZipPathError::DriveLetter),
+            ZipPathBuf::normalize(b"C:\\a")
+        );
+    }
+}
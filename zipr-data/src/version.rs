@@ -71,3 +71,14 @@ impl TryFrom<u8> for MinorVersion {
         Ok(MinorVersion(bounded))
     }
 }
+
+/// From the combined form stored in headers, eg 20 for 2.0
+impl TryFrom<u8> for ZipSpecification {
+    type Error = OutOfRangeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let major = (value / 10).try_into()?;
+        let minor = (value % 10).try_into()?;
+        Ok(ZipSpecification { major, minor })
+    }
+}
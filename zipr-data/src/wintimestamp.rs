@@ -6,10 +6,53 @@ use core::convert::{From, TryFrom};
 pub struct WinTimestamp(u64);
 
 /// Error type for when a u64 is not a valid
-/// wintimestamp
-#[derive(Debug)]
+/// wintimestamp, or a time is outside of its range
+#[derive(Debug, PartialEq)]
 pub struct WinTimestampCreateError();
 
+/// Seconds between 1601-01-01 and 1970-01-01
+pub const WINDOWS_TO_UNIX_EPOCH_SECONDS: u64 = 11_644_473_600;
+
+/// Windows timestamps count 100ns intervals
+const TICKS_PER_SECOND: u64 = 10_000_000;
+const NANOS_PER_TICK: u32 = 100;
+
+impl WinTimestamp {
+    /// 100ns intervals since 1601-01-01 UTC
+    pub const fn from_ticks(ticks: u64) -> Self {
+        WinTimestamp(ticks)
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.0
+    }
+
+    /// Seconds and nanoseconds since the unix epoch.
+    /// Seconds are negative before 1970, the nanoseconds are always added
+    pub fn to_unix(&self) -> (i64, u32) {
+        let seconds = (self.0 / TICKS_PER_SECOND) as i64 - WINDOWS_TO_UNIX_EPOCH_SECONDS as i64;
+        let nanos = (self.0 % TICKS_PER_SECOND) as u32 * NANOS_PER_TICK;
+        (seconds, nanos)
+    }
+
+    /// From seconds and nanoseconds since the unix epoch.
+    /// Nanoseconds are truncated to 100ns, times before 1601 are an error
+    pub fn from_unix(seconds: i64, nanos: u32) -> Result<Self, WinTimestampCreateError> {
+        if nanos >= 1_000_000_000 {
+            return Err(WinTimestampCreateError());
+        }
+        let since_1601 = seconds
+            .checked_add(WINDOWS_TO_UNIX_EPOCH_SECONDS as i64)
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or(WinTimestampCreateError())?;
+        since_1601
+            .checked_mul(TICKS_PER_SECOND)
+            .and_then(|x| x.checked_add((nanos / NANOS_PER_TICK) as u64))
+            .map(WinTimestamp)
+            .ok_or(WinTimestampCreateError())
+    }
+}
+
 impl TryFrom<u64> for WinTimestamp {
     type Error = WinTimestampCreateError;
 
@@ -23,3 +66,31 @@ impl From<&WinTimestamp> for u64 {
         x.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_epoch() {
+        let epoch = WinTimestamp::from_ticks(WINDOWS_TO_UNIX_EPOCH_SECONDS * TICKS_PER_SECOND);
+        assert_eq!((0, 0), epoch.to_unix());
+        assert_eq!(Ok(epoch), WinTimestamp::from_unix(0, 0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let time = WinTimestamp::from_unix(1_000_000_000, 123_456_789).unwrap();
+        assert_eq!((1_000_000_000, 123_456_700), time.to_unix());
+        let before = WinTimestamp::from_unix(-1, 500_000_000).unwrap();
+        assert_eq!((-1, 500_000_000), before.to_unix());
+    }
+
+    #[test]
+    fn out_of_range() {
+        let before_1601 = -(WINDOWS_TO_UNIX_EPOCH_SECONDS as i64) - 1;
+        assert!(WinTimestamp::from_unix(before_1601, 0).is_err());
+        assert!(WinTimestamp::from_unix(0, 1_000_000_000).is_err());
+        assert!(WinTimestamp::from_unix(i64::MAX, 0).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use zipr_data::{
+    borrowed::ZipEntry,
+    constants::{UNIX_FILE_TYPE_MASK, UNIX_SYMLINK},
+    HostCompatibility,
+};
+
+/// The unix mode stored in the upper 16 bits of the external attributes
+/// This is only meaningful when the entry was made on a unix like host
+pub fn unix_mode(entry: &ZipEntry<'_>) -> Option<u32> {
+    match entry.version_made_by.host {
+        HostCompatibility::UNIX | HostCompatibility::OSX => {
+            let mode = entry.external_file_attributes >> 16;
+            if mode == 0 {
+                None
+            } else {
+                Some(mode)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// True if the entry is a symlink, its data being the link target
+pub fn is_symlink(entry: &ZipEntry<'_>) -> bool {
+    match unix_mode(entry) {
+        Some(mode) => mode & UNIX_FILE_TYPE_MASK == UNIX_SYMLINK,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+
+    use zipr_data::{
+        borrowed::{extra_field::ExtraField, file::CompressedData, ZipPath},
+        CompressionMethod, DosDate, DosTime, Version, ZipSpecification,
+    };
+
+    use super::*;
+
+    fn entry(host: HostCompatibility, external_file_attributes: u32) -> ZipEntry<'static> {
+        let version = Version {
+            host,
+            spec: ZipSpecification {
+                major: 2u8.try_into().unwrap(),
+                minor: 0u8.try_into().unwrap(),
+            },
+        };
+        ZipEntry {
+            version_made_by: version,
+            version_needed: version,
+            general_purpose: 0,
+            file_modification_time: DosTime::from_u16_unchecked(0),
+            file_modification_date: DosDate::from_u16_unchecked(0),
+            internal_file_attributes: 0,
+            external_file_attributes,
+            file_name: ZipPath::try_from(&b"file"[..]).unwrap(),
+            extra_field: ExtraField::Unknown(&[]),
+            comment: Default::default(),
+            compressed_data: CompressedData::create_unchecked(0, CompressionMethod::Stored, 0, &[]),
+        }
+    }
+
+    #[test]
+    fn mode_only_from_unix_hosts() {
+        assert_eq!(
+            None,
+            unix_mode(&entry(HostCompatibility::MSDOS, 0o100644 << 16))
+        );
+        let file = entry(HostCompatibility::UNIX, 0o100644 << 16);
+        assert_eq!(Some(0o100644), unix_mode(&file));
+        assert!(!is_symlink(&file));
+    }
+
+    #[test]
+    fn symlink() {
+        assert!(is_symlink(&entry(HostCompatibility::UNIX, 0o120777 << 16)));
+    }
+}
@@ -0,0 +1,365 @@
+use core::{convert::TryFrom, fmt::Display};
+
+use zipr_data::{
+    borrowed::{extra_field::ExtraField, file::CompressedData, OEM437Str, ZipEntry, ZipPath},
+    constants::{
+        EXTERNAL_ATTRIBUTE_DOS_DIRECTORY, EXTERNAL_ATTRIBUTE_DOS_READ_ONLY, UNIX_DIRECTORY,
+        UNIX_FILE_TYPE_MASK,
+    },
+    CompressionMethod, DosDateTime, DosDateTimeError, HostCompatibility, Version, ZipSpecification,
+};
+
+use crate::is_directory;
+
+/// The general purpose flag for encrypted entries
+const GENERAL_PURPOSE_ENCRYPTED: u16 = 0x01;
+
+/// Version 1.0, the default needed to extract
+const SPEC_DEFAULT: u8 = 10;
+/// Version 2.0, needed for folders, deflate and traditional encryption
+/// This is also what zipr supports, so is used for made by
+const SPEC_DEFLATE: u8 = 20;
+
+fn specification(value: u8) -> ZipSpecification {
+    ZipSpecification::try_from(value).unwrap_or_else(|_| unreachable!())
+}
+
+/// Problems found when building an entry
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ZipEntryBuildError {
+    /// Directories can't have any data
+    DirectoryWithData,
+    /// The mode is a directory, but the name doesn't end with a slash
+    DirectoryModeMismatch,
+    /// The unix mode is only read from unix hosts
+    ModeWithoutUnixHost(HostCompatibility),
+    /// The version needed given is lower than the entry requires
+    VersionTooLow { given: u8, required: u8 },
+    /// The encrypted flag was set, but zipr doesn't encrypt data
+    Encrypted,
+    /// The modification time isn't a valid date and time
+    InvalidModified(DosDateTimeError),
+    /// No modification time was given
+    MissingModified,
+}
+
+impl Display for ZipEntryBuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ZipEntryBuildError::DirectoryWithData => f.write_str("Directories can't have data"),
+            ZipEntryBuildError::DirectoryModeMismatch => {
+                f.write_str("Directory mode on a name without a trailing slash")
+            }
+            ZipEntryBuildError::ModeWithoutUnixHost(host) => {
+                write!(f, "Unix mode can't be stored for host {:?}", host)
+            }
+            ZipEntryBuildError::VersionTooLow { given, required } => write!(
+                f,
+                "Version needed {} is lower than the required {}",
+                given, required
+            ),
+            ZipEntryBuildError::Encrypted => f.write_str("Encryption is not supported"),
+            ZipEntryBuildError::InvalidModified(e) => write!(f, "Invalid modified time: {}", e),
+            ZipEntryBuildError::MissingModified => f.write_str("No modified time was given"),
+        }
+    }
+}
+
+/// Builds a ZipEntry with sensible defaults
+///
+/// The version needed comes from the compression method and whether
+/// it is a directory, made by from the host. The host is unix when a mode
+/// is given, otherwise msdos. A modified time must always be given
+pub struct ZipEntryBuilder<'a> {
+    file_name: ZipPath<'a>,
+    compressed_data: CompressedData<'a>,
+    host: Option<HostCompatibility>,
+    version_needed: Option<ZipSpecification>,
+    general_purpose: u16,
+    modified: Option<DosDateTime>,
+    mode: Option<u32>,
+    read_only: bool,
+    internal_file_attributes: u16,
+    extra_field: ExtraField<'a>,
+    comment: OEM437Str<'a>,
+}
+
+impl<'a> ZipEntryBuilder<'a> {
+    pub fn new(file_name: ZipPath<'a>, compressed_data: CompressedData<'a>) -> Self {
+        ZipEntryBuilder {
+            file_name,
+            compressed_data,
+            host: None,
+            version_needed: None,
+            general_purpose: 0,
+            modified: None,
+            mode: None,
+            read_only: false,
+            internal_file_attributes: 0,
+            extra_field: ExtraField::default(),
+            comment: OEM437Str::default(),
+        }
+    }
+
+    /// A directory entry, which has no data. The name should end with a slash
+    pub fn directory(file_name: ZipPath<'a>) -> Self {
+        let empty = CompressedData::create_unchecked(0, CompressionMethod::Stored, 0, &[]);
+        ZipEntryBuilder::new(file_name, empty)
+    }
+
+    /// The host the entry was made on
+    pub fn host(mut self, host: HostCompatibility) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    /// Overrides the version needed to extract
+    pub fn version_needed(mut self, version: ZipSpecification) -> Self {
+        self.version_needed = Some(version);
+        self
+    }
+
+    pub fn general_purpose(mut self, flags: u16) -> Self {
+        self.general_purpose = flags;
+        self
+    }
+
+    /// The local modification time
+    pub fn modified(mut self, modified: DosDateTime) -> Self {
+        self.modified = Some(modified);
+        self
+    }
+
+    /// The unix mode, including the file type bits
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Sets the dos read only attribute
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    pub fn internal_file_attributes(mut self, attributes: u16) -> Self {
+        self.internal_file_attributes = attributes;
+        self
+    }
+
+    pub fn extra_field(mut self, extra_field: ExtraField<'a>) -> Self {
+        self.extra_field = extra_field;
+        self
+    }
+
+    pub fn comment(mut self, comment: OEM437Str<'a>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    fn is_directory(&self) -> bool {
+        let mode = self.mode.unwrap_or(0) << 16;
+        is_directory(&self.file_name, mode)
+    }
+
+    /// The lowest version able to extract the entry
+    fn required_version(&self) -> u8 {
+        let compression = match self.compressed_data.compression_method() {
+            CompressionMethod::Stored => SPEC_DEFAULT,
+            CompressionMethod::Deflate => SPEC_DEFLATE,
+        };
+        let directory = if self.is_directory() {
+            SPEC_DEFLATE
+        } else {
+            SPEC_DEFAULT
+        };
+        compression.max(directory)
+    }
+
+    fn external_file_attributes(&self) -> u32 {
+        let mut attributes = self.mode.unwrap_or(0) << 16;
+        if self.is_directory() {
+            attributes |= EXTERNAL_ATTRIBUTE_DOS_DIRECTORY;
+        }
+        let no_write = matches!(self.mode, Some(mode) if mode & 0o222 == 0);
+        if self.read_only || no_write {
+            attributes |= EXTERNAL_ATTRIBUTE_DOS_READ_ONLY;
+        }
+        attributes
+    }
+
+    /// Checks the options are consistent and creates the entry
+    pub fn build(self) -> Result<ZipEntry<'a>, ZipEntryBuildError> {
+        let host = match (self.host, self.mode) {
+            (Some(host), _) => host,
+            (None, Some(_)) => HostCompatibility::UNIX,
+            (None, None) => HostCompatibility::MSDOS,
+        };
+        if self.mode.is_some() && !matches!(host, HostCompatibility::UNIX | HostCompatibility::OSX)
+        {
+            return Err(ZipEntryBuildError::ModeWithoutUnixHost(host));
+        }
+
+        let name_is_directory = is_directory(&self.file_name, 0);
+        let mode_is_directory =
+            matches!(self.mode, Some(mode) if mode & UNIX_FILE_TYPE_MASK == UNIX_DIRECTORY);
+        if mode_is_directory && !name_is_directory {
+            return Err(ZipEntryBuildError::DirectoryModeMismatch);
+        }
+        let data = &self.compressed_data;
+        if self.is_directory() && (data.uncompressed_size() != 0 || !data.bytes().is_empty()) {
+            return Err(ZipEntryBuildError::DirectoryWithData);
+        }
+        if self.general_purpose & GENERAL_PURPOSE_ENCRYPTED != 0 {
+            return Err(ZipEntryBuildError::Encrypted);
+        }
+        let modified = self.modified.ok_or(ZipEntryBuildError::MissingModified)?;
+        modified
+            .validate()
+            .map_err(ZipEntryBuildError::InvalidModified)?;
+
+        let required = self.required_version();
+        let version_needed = match self.version_needed {
+            Some(given) if u8::from(given) < required => {
+                return Err(ZipEntryBuildError::VersionTooLow {
+                    given: given.into(),
+                    required,
+                })
+            }
+            Some(given) => given,
+            None => specification(required),
+        };
+        let made_by = specification(SPEC_DEFLATE.max(version_needed.into()));
+
+        Ok(ZipEntry {
+            version_made_by: Version {
+                host,
+                spec: made_by,
+            },
+            version_needed: Version {
+                host,
+                spec: version_needed,
+            },
+            general_purpose: self.general_purpose,
+            file_modification_time: modified.time,
+            file_modification_date: modified.date,
+            internal_file_attributes: self.internal_file_attributes,
+            external_file_attributes: self.external_file_attributes(),
+            file_name: self.file_name,
+            extra_field: self.extra_field,
+            comment: self.comment,
+            compressed_data: self.compressed_data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &'static [u8]) -> ZipPath<'static> {
+        ZipPath::try_from(name).unwrap()
+    }
+
+    fn data(method: CompressionMethod) -> CompressedData<'static> {
+        CompressedData::create_unchecked(5, method, 0x3610A686, b"hello")
+    }
+
+    #[test]
+    fn defaults() {
+        let entry = ZipEntryBuilder::new(name(b"hello.txt"), data(CompressionMethod::Stored))
+            .modified(DosDateTime::MIN)
+            .build()
+            .unwrap();
+        assert_eq!(HostCompatibility::MSDOS, entry.version_made_by.host);
+        assert_eq!(10u8, entry.version_needed.spec.into());
+        assert_eq!(20u8, entry.version_made_by.spec.into());
+        assert_eq!(0, entry.external_file_attributes);
+        assert_eq!(DosDateTime::MIN.date, entry.file_modification_date);
+    }
+
+    #[test]
+    fn deflate_and_mode() {
+        let modified = DosDateTime::new(2020, 12, 3, 20, 6, 24).unwrap();
+        let entry = ZipEntryBuilder::new(name(b"hello.txt"), data(CompressionMethod::Deflate))
+            .mode(0o100444)
+            .modified(modified)
+            .build()
+            .unwrap();
+        assert_eq!(HostCompatibility::UNIX, entry.version_made_by.host);
+        assert_eq!(20u8, entry.version_needed.spec.into());
+        assert_eq!(
+            0o100444 << 16 | EXTERNAL_ATTRIBUTE_DOS_READ_ONLY,
+            entry.external_file_attributes
+        );
+        assert_eq!(modified.time, entry.file_modification_time);
+    }
+
+    #[test]
+    fn directory() {
+        let entry = ZipEntryBuilder::directory(name(b"folder/"))
+            .mode(0o040755)
+            .modified(DosDateTime::MIN)
+            .build()
+            .unwrap();
+        assert_eq!(20u8, entry.version_needed.spec.into());
+        assert_eq!(
+            0o040755 << 16 | EXTERNAL_ATTRIBUTE_DOS_DIRECTORY,
+            entry.external_file_attributes
+        );
+    }
+
+    #[test]
+    fn invalid_combinations() {
+        let stored = || data(CompressionMethod::Stored);
+        let builder = |name, data| ZipEntryBuilder::new(name, data).modified(DosDateTime::MIN);
+        let with_data = builder(name(b"folder/"), stored()).build();
+        assert_eq!(Err(ZipEntryBuildError::DirectoryWithData), with_data);
+
+        let mismatch = ZipEntryBuilder::directory(name(b"folder"))
+            .mode(0o040755)
+            .modified(DosDateTime::MIN)
+            .build();
+        assert_eq!(Err(ZipEntryBuildError::DirectoryModeMismatch), mismatch);
+
+        let dos_mode = builder(name(b"a"), stored())
+            .host(HostCompatibility::MSDOS)
+            .mode(0o100644)
+            .build();
+        assert!(matches!(
+            dos_mode,
+            Err(ZipEntryBuildError::ModeWithoutUnixHost(_))
+        ));
+
+        let low = builder(name(b"a"), data(CompressionMethod::Deflate))
+            .version_needed(specification(10))
+            .build();
+        assert_eq!(
+            Err(ZipEntryBuildError::VersionTooLow {
+                given: 10,
+                required: 20
+            }),
+            low
+        );
+
+        let encrypted = builder(name(b"a"), stored())
+            .general_purpose(GENERAL_PURPOSE_ENCRYPTED)
+            .build();
+        assert_eq!(Err(ZipEntryBuildError::Encrypted), encrypted);
+
+        let invalid = DosDateTime {
+            date: zipr_data::DosDate::from_u16_unchecked(0),
+            time: zipr_data::DosTime::from_u16_unchecked(0),
+        };
+        let modified = ZipEntryBuilder::new(name(b"a"), stored())
+            .modified(invalid)
+            .build();
+        assert!(matches!(
+            modified,
+            Err(ZipEntryBuildError::InvalidModified(_))
+        ));
+
+        let missing = ZipEntryBuilder::new(name(b"a"), stored()).build();
+        assert_eq!(Err(ZipEntryBuildError::MissingModified), missing);
+    }
+}
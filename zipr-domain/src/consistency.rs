@@ -0,0 +1,301 @@
+use core::fmt::Display;
+
+use zipr_data::{
+    borrowed::file::{CentralDirectoryEntry, LocalFileEntry},
+    constants::GENERAL_PURPOSE_DATA_DESCRIPTOR,
+    CompressionMethod,
+};
+
+/// A field that differs between the central directory and local header
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Discrepancy {
+    FileName,
+    VersionNeeded {
+        central: u8,
+        local: u8,
+    },
+    GeneralPurpose {
+        central: u16,
+        local: u16,
+    },
+    CompressionMethod {
+        central: CompressionMethod,
+        local: CompressionMethod,
+    },
+    Crc32 {
+        central: u32,
+        local: u32,
+    },
+    CompressedSize {
+        central: u32,
+        local: u32,
+    },
+    UncompressedSize {
+        central: u32,
+        local: u32,
+    },
+    ModificationTime,
+}
+
+impl Discrepancy {
+    /// Whether the difference changes what data is extracted or where to.
+    /// These are how one archive is read differently by different tools
+    pub fn is_critical(&self) -> bool {
+        !matches!(
+            self,
+            Discrepancy::VersionNeeded { .. }
+                | Discrepancy::GeneralPurpose { .. }
+                | Discrepancy::ModificationTime
+        )
+    }
+}
+
+impl Display for Discrepancy {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Discrepancy::FileName => f.write_str("file names differ"),
+            Discrepancy::VersionNeeded { central, local } => write!(
+                f,
+                "version needed differs (central {}, local {})",
+                central, local
+            ),
+            Discrepancy::GeneralPurpose { central, local } => write!(
+                f,
+                "general purpose flags differ (central 0x{:04x}, local 0x{:04x})",
+                central, local
+            ),
+            Discrepancy::CompressionMethod { central, local } => write!(
+                f,
+                "compression method differs (central {:?}, local {:?})",
+                central, local
+            ),
+            Discrepancy::Crc32 { central, local } => write!(
+                f,
+                "crc32 differs (central 0x{:08x}, local 0x{:08x})",
+                central, local
+            ),
+            Discrepancy::CompressedSize { central, local } => write!(
+                f,
+                "compressed size differs (central {}, local {})",
+                central, local
+            ),
+            Discrepancy::UncompressedSize { central, local } => write!(
+                f,
+                "uncompressed size differs (central {}, local {})",
+                central, local
+            ),
+            Discrepancy::ModificationTime => f.write_str("modification times differ"),
+        }
+    }
+}
+
+/// How strictly the headers must agree
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ConsistencyPolicy {
+    /// Any discrepancy is an error
+    Strict,
+    /// Only critical discrepancies are errors, see Discrepancy::is_critical
+    #[default]
+    Lenient,
+}
+
+impl ConsistencyPolicy {
+    /// Whether the policy treats the discrepancy as an error
+    pub fn rejects(&self, discrepancy: &Discrepancy) -> bool {
+        match self {
+            ConsistencyPolicy::Strict => true,
+            ConsistencyPolicy::Lenient => discrepancy.is_critical(),
+        }
+    }
+
+    /// The first discrepancy the policy doesn't allow
+    pub fn check(
+        &self,
+        central: &CentralDirectoryEntry<'_>,
+        local: &LocalFileEntry<'_>,
+    ) -> Result<(), Discrepancy> {
+        discrepancies(central, local)
+            .find(|x| self.rejects(x))
+            .map_or(Ok(()), Err)
+    }
+}
+
+const CHECKS: usize = 8;
+
+/// Lazily compares each field of the two headers
+pub struct Discrepancies<'a> {
+    central: CentralDirectoryEntry<'a>,
+    local: LocalFileEntry<'a>,
+    check: usize,
+}
+
+/// Compares the central directory entry with its local header
+///
+/// When the data descriptor flag is set the local crc and sizes
+/// may be zero, so are only compared if they are not
+pub fn discrepancies<'a>(
+    central: &CentralDirectoryEntry<'a>,
+    local: &LocalFileEntry<'a>,
+) -> Discrepancies<'a> {
+    Discrepancies {
+        central: *central,
+        local: *local,
+        check: 0,
+    }
+}
+
+impl Discrepancies<'_> {
+    fn compare(&self, check: usize) -> Option<Discrepancy> {
+        let (central, local) = (&self.central, &self.local);
+        let data = &local.compressed_data;
+        let deferred = local.general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0;
+        let differs = |central: u32, local: u32| central != local && !(deferred && local == 0);
+        match check {
+            0 if central.file_name != local.file_name => Some(Discrepancy::FileName),
+            1 if central.version_needed.spec != local.version_needed.spec => {
+                Some(Discrepancy::VersionNeeded {
+                    central: central.version_needed.spec.into(),
+                    local: local.version_needed.spec.into(),
+                })
+            }
+            2 if central.general_purpose != local.general_purpose => {
+                Some(Discrepancy::GeneralPurpose {
+                    central: central.general_purpose,
+                    local: local.general_purpose,
+                })
+            }
+            3 if central.compression_method != data.compression_method() => {
+                Some(Discrepancy::CompressionMethod {
+                    central: central.compression_method,
+                    local: data.compression_method(),
+                })
+            }
+            4 if differs(central.crc32, data.crc32()) => Some(Discrepancy::Crc32 {
+                central: central.crc32,
+                local: data.crc32(),
+            }),
+            5 if differs(central.compressed_size, data.bytes().len() as u32) => {
+                Some(Discrepancy::CompressedSize {
+                    central: central.compressed_size,
+                    local: data.bytes().len() as u32,
+                })
+            }
+            6 if differs(central.uncompressed_size, data.uncompressed_size()) => {
+                Some(Discrepancy::UncompressedSize {
+                    central: central.uncompressed_size,
+                    local: data.uncompressed_size(),
+                })
+            }
+            7 if central.file_modification_date != local.file_modification_date
+                || central.file_modification_time != local.file_modification_time =>
+            {
+                Some(Discrepancy::ModificationTime)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for Discrepancies<'_> {
+    type Item = Discrepancy;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.check < CHECKS {
+            let check = self.check;
+            self.check += 1;
+            if let Some(found) = self.compare(check) {
+                return Some(found);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::{TryFrom, TryInto};
+
+    use zipr_data::{
+        borrowed::{file::CompressedData, ZipEntry, ZipPath},
+        DosDate, DosTime, HostCompatibility, Version, ZipSpecification,
+    };
+
+    use super::*;
+    use crate::zip_entry_to_files;
+
+    fn entry() -> ZipEntry<'static> {
+        let version = Version {
+            host: HostCompatibility::MSDOS,
+            spec: ZipSpecification {
+                major: 2u8.try_into().unwrap(),
+                minor: 0u8.try_into().unwrap(),
+            },
+        };
+        ZipEntry {
+            version_made_by: version,
+            version_needed: version,
+            general_purpose: 0,
+            file_modification_time: DosTime::from_u16_unchecked(41164),
+            file_modification_date: DosDate::from_u16_unchecked(20867),
+            internal_file_attributes: 0,
+            external_file_attributes: 0,
+            file_name: ZipPath::try_from(&b"hello.txt"[..]).unwrap(),
+            extra_field: Default::default(),
+            comment: Default::default(),
+            compressed_data: CompressedData::create_unchecked(
+                5,
+                CompressionMethod::Stored,
+                0x3610A686,
+                b"hello",
+            ),
+        }
+    }
+
+    #[test]
+    fn consistent() {
+        let entry = entry();
+        let (local, central) = zip_entry_to_files(0, &entry);
+        assert_eq!(0, discrepancies(&central, &local).count());
+        assert_eq!(Ok(()), ConsistencyPolicy::Strict.check(&central, &local));
+    }
+
+    #[test]
+    fn name_and_crc() {
+        let entry = entry();
+        let (mut local, mut central) = zip_entry_to_files(0, &entry);
+        local.file_name = ZipPath::try_from(&b"other.txt"[..]).unwrap();
+        central.crc32 = 1;
+        let mut found = discrepancies(&central, &local);
+        assert_eq!(Some(Discrepancy::FileName), found.next());
+        assert_eq!(
+            Some(Discrepancy::Crc32 {
+                central: 1,
+                local: 0x3610A686
+            }),
+            found.next()
+        );
+        assert_eq!(None, found.next());
+    }
+
+    #[test]
+    fn policies() {
+        let entry = entry();
+        let (mut local, central) = zip_entry_to_files(0, &entry);
+        local.file_modification_time = DosTime::from_u16_unchecked(0);
+        assert_eq!(Ok(()), ConsistencyPolicy::Lenient.check(&central, &local));
+        assert_eq!(
+            Err(Discrepancy::ModificationTime),
+            ConsistencyPolicy::Strict.check(&central, &local)
+        );
+    }
+
+    #[test]
+    fn data_descriptor() {
+        let mut entry = entry();
+        entry.general_purpose = GENERAL_PURPOSE_DATA_DESCRIPTOR;
+        let (mut local, central) = zip_entry_to_files(0, &entry);
+        local.compressed_data =
+            CompressedData::create_unchecked(0, CompressionMethod::Stored, 0, b"hello");
+        assert_eq!(0, discrepancies(&central, &local).count());
+    }
+}
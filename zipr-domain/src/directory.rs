@@ -0,0 +1,63 @@
+use zipr_data::{
+    borrowed::{file::CentralDirectoryEntry, ZipEntry, ZipPath},
+    constants::{EXTERNAL_ATTRIBUTE_DOS_DIRECTORY, UNIX_DIRECTORY, UNIX_FILE_TYPE_MASK},
+};
+
+/// Decides if an entry is a directory from its name and attributes
+/// A trailing slash is the usual marker, but some tools only set
+/// the dos directory attribute, or a unix directory mode in the upper 16 bits
+pub fn is_directory(file_name: &ZipPath<'_>, external_file_attributes: u32) -> bool {
+    let trailing_slash = file_name.as_ref().last() == Some(&b'/');
+    let dos_directory = external_file_attributes & EXTERNAL_ATTRIBUTE_DOS_DIRECTORY != 0;
+    let unix_mode = external_file_attributes >> 16;
+    let unix_directory = unix_mode & UNIX_FILE_TYPE_MASK == UNIX_DIRECTORY;
+    trailing_slash || dos_directory || unix_directory
+}
+
+/// Helper for the structures that can represent a directory
+pub trait IsDirectory {
+    fn is_directory(&self) -> bool;
+}
+
+impl IsDirectory for ZipEntry<'_> {
+    fn is_directory(&self) -> bool {
+        is_directory(&self.file_name, self.external_file_attributes)
+    }
+}
+
+impl IsDirectory for CentralDirectoryEntry<'_> {
+    fn is_directory(&self) -> bool {
+        is_directory(&self.file_name, self.external_file_attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn trailing_slash() {
+        let path = ZipPath::try_from(&b"folder/"[..]).unwrap();
+        assert!(is_directory(&path, 0));
+    }
+
+    #[test]
+    fn dos_attribute() {
+        let path = ZipPath::try_from(&b"folder"[..]).unwrap();
+        assert!(is_directory(&path, 0x10));
+    }
+
+    #[test]
+    fn unix_mode() {
+        let path = ZipPath::try_from(&b"folder"[..]).unwrap();
+        assert!(is_directory(&path, 0o040755 << 16));
+    }
+
+    #[test]
+    fn plain_file() {
+        let path = ZipPath::try_from(&b"folder/file.txt"[..]).unwrap();
+        assert!(!is_directory(&path, 0x20 | (0o100644 << 16)));
+    }
+}
@@ -1,4 +1,14 @@
 #![no_std]
+mod attributes;
+mod builder;
+mod consistency;
+mod directory;
+mod structure;
 mod zip_entry;
 
+pub use attributes::*;
+pub use builder::*;
+pub use consistency::*;
+pub use directory::*;
+pub use structure::*;
 pub use zip_entry::*;
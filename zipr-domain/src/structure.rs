@@ -0,0 +1,405 @@
+use core::fmt::Display;
+
+/// A range of bytes in the archive, the end is exclusive
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Extent {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Extent {
+    pub fn new(start: u64, end: u64) -> Self {
+        Extent { start, end }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn overlaps(&self, other: &Extent) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// The bytes used by an entry, from the start of the local header
+/// to the end of the data (and data descriptor if there is one)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EntryExtent {
+    /// The position of the entry in the central directory
+    pub index: usize,
+    pub extent: Extent,
+}
+
+/// Where the records that aren't entries are
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ArchiveLayout {
+    pub file_size: u64,
+    pub central_directory: Extent,
+    pub end_of_central_directory: Extent,
+}
+
+/// Something unusual about where entries are in the archive
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StructuralIssue {
+    /// Bytes before the first entry, eg a self extracting stub
+    PrependedData {
+        size: u64,
+    },
+    /// Bytes that no entry or record uses
+    Gap(Extent),
+    /// Two entries share bytes. Used by zip bombs and quines
+    Overlap {
+        first: usize,
+        second: usize,
+        extent: Extent,
+    },
+    /// The entry uses bytes of the central directory or end record
+    IntoCentralDirectory {
+        index: usize,
+    },
+    PastEndOfFile {
+        index: usize,
+    },
+    /// The local header of the entry couldn't be found or parsed,
+    /// so where its bytes are isn't known
+    UnreadableLocalHeader {
+        index: usize,
+    },
+    /// Bytes after the end of central directory record
+    TrailingData {
+        size: u64,
+    },
+}
+
+impl StructuralIssue {
+    /// Whether the issue means entries can't be trusted,
+    /// rather than the archive having unused bytes
+    pub fn is_critical(&self) -> bool {
+        matches!(
+            self,
+            StructuralIssue::Overlap { .. }
+                | StructuralIssue::IntoCentralDirectory { .. }
+                | StructuralIssue::PastEndOfFile { .. }
+                | StructuralIssue::UnreadableLocalHeader { .. }
+        )
+    }
+}
+
+impl Display for StructuralIssue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StructuralIssue::PrependedData { size } => {
+                write!(f, "0x{:x} bytes before the first entry", size)
+            }
+            StructuralIssue::Gap(extent) => {
+                write!(f, "unused bytes at 0x{:x}-0x{:x}", extent.start, extent.end)
+            }
+            StructuralIssue::Overlap {
+                first,
+                second,
+                extent,
+            } => write!(
+                f,
+                "entries {} and {} overlap at 0x{:x}-0x{:x}",
+                first, second, extent.start, extent.end
+            ),
+            StructuralIssue::IntoCentralDirectory { index } => {
+                write!(f, "entry {} overlaps the central directory", index)
+            }
+            StructuralIssue::PastEndOfFile { index } => {
+                write!(f, "entry {} extends past the end of the file", index)
+            }
+            StructuralIssue::UnreadableLocalHeader { index } => {
+                write!(f, "the local header of entry {} can't be read", index)
+            }
+            StructuralIssue::TrailingData { size } => {
+                write!(f, "0x{:x} bytes after the end of central directory", size)
+            }
+        }
+    }
+}
+
+/// Which structural issues stop an archive being read
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum StructurePolicy {
+    /// Any issue is an error
+    Strict,
+    /// Only critical issues are errors, the rest are warnings.
+    /// See StructuralIssue::is_critical
+    #[default]
+    Lenient,
+}
+
+impl StructurePolicy {
+    /// Whether the policy treats the issue as an error
+    pub fn rejects(&self, issue: &StructuralIssue) -> bool {
+        match self {
+            StructurePolicy::Strict => true,
+            StructurePolicy::Lenient => issue.is_critical(),
+        }
+    }
+
+    /// The first issue the policy doesn't allow
+    pub fn check(
+        &self,
+        layout: &ArchiveLayout,
+        entries: &mut [EntryExtent],
+    ) -> Result<(), StructuralIssue> {
+        structural_issues(layout, entries)
+            .find(|x| self.rejects(x))
+            .map_or(Ok(()), Err)
+    }
+}
+
+/// What is using the furthest byte seen so far
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Covered {
+    Nothing,
+    Entry(usize),
+    Record,
+}
+
+enum Upcoming {
+    Entry(EntryExtent),
+    Record(Extent),
+    End,
+}
+
+const ENTRY_CHECKS: usize = 4;
+
+/// Lazily walks the entries and records in file order looking for issues
+pub struct StructuralIssues<'a> {
+    layout: ArchiveLayout,
+    entries: &'a [EntryExtent],
+    position: usize,
+    record: usize,
+    check: usize,
+    covered: u64,
+    owner: Covered,
+}
+
+/// Finds the issues with where the entries are.
+/// The entries are sorted by their position in the file
+pub fn structural_issues<'a>(
+    layout: &ArchiveLayout,
+    entries: &'a mut [EntryExtent],
+) -> StructuralIssues<'a> {
+    entries.sort_unstable_by_key(|x| (x.extent.start, x.index));
+    StructuralIssues {
+        layout: *layout,
+        entries,
+        position: 0,
+        record: 0,
+        check: 0,
+        covered: 0,
+        owner: Covered::Nothing,
+    }
+}
+
+impl StructuralIssues<'_> {
+    fn upcoming(&self) -> Upcoming {
+        let records = [
+            self.layout.central_directory,
+            self.layout.end_of_central_directory,
+        ];
+        let entry = self.entries.get(self.position).copied();
+        let record = records.get(self.record).copied();
+        match (entry, record) {
+            (Some(entry), Some(record)) if entry.extent.start < record.start => {
+                Upcoming::Entry(entry)
+            }
+            (_, Some(record)) => Upcoming::Record(record),
+            (Some(entry), None) => Upcoming::Entry(entry),
+            (None, None) => Upcoming::End,
+        }
+    }
+
+    fn cover(&mut self, extent: Extent, owner: Covered) {
+        if extent.end > self.covered {
+            self.covered = extent.end;
+            self.owner = owner;
+        } else if self.owner == Covered::Nothing {
+            self.owner = owner;
+        }
+    }
+
+    fn unused(&self, end: u64) -> Option<StructuralIssue> {
+        let extent = Extent::new(self.covered, end);
+        match self.owner {
+            _ if extent.is_empty() => None,
+            Covered::Nothing => Some(StructuralIssue::PrependedData { size: extent.len() }),
+            _ => Some(StructuralIssue::Gap(extent)),
+        }
+    }
+
+    fn compare(&self, entry: &EntryExtent, check: usize) -> Option<StructuralIssue> {
+        let (index, extent) = (entry.index, entry.extent);
+        let layout = &self.layout;
+        match (check, self.owner) {
+            (0, _) => self.unused(extent.start),
+            (1, Covered::Entry(first)) if extent.start < self.covered => {
+                Some(StructuralIssue::Overlap {
+                    first,
+                    second: index,
+                    extent: Extent::new(extent.start, extent.end.min(self.covered)),
+                })
+            }
+            (2, _)
+                if extent.overlaps(&layout.central_directory)
+                    || extent.overlaps(&layout.end_of_central_directory) =>
+            {
+                Some(StructuralIssue::IntoCentralDirectory { index })
+            }
+            (3, _) if extent.end > layout.file_size => {
+                Some(StructuralIssue::PastEndOfFile { index })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for StructuralIssues<'_> {
+    type Item = StructuralIssue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let check = self.check;
+            self.check += 1;
+            let issue = match self.upcoming() {
+                Upcoming::Entry(entry) if check < ENTRY_CHECKS => self.compare(&entry, check),
+                Upcoming::Entry(entry) => {
+                    self.cover(entry.extent, Covered::Entry(entry.index));
+                    self.position += 1;
+                    self.check = 0;
+                    None
+                }
+                Upcoming::Record(extent) if check == 0 => self.unused(extent.start),
+                Upcoming::Record(extent) => {
+                    self.cover(extent, Covered::Record);
+                    self.record += 1;
+                    self.check = 0;
+                    None
+                }
+                Upcoming::End if check == 0 && self.layout.file_size > self.covered => {
+                    Some(StructuralIssue::TrailingData {
+                        size: self.layout.file_size - self.covered,
+                    })
+                }
+                Upcoming::End => {
+                    self.check = 1;
+                    return None;
+                }
+            };
+            if issue.is_some() {
+                return issue;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(file_size: u64, directory: u64) -> ArchiveLayout {
+        ArchiveLayout {
+            file_size,
+            central_directory: Extent::new(directory, file_size - 22),
+            end_of_central_directory: Extent::new(file_size - 22, file_size),
+        }
+    }
+
+    fn entry(index: usize, start: u64, end: u64) -> EntryExtent {
+        EntryExtent {
+            index,
+            extent: Extent::new(start, end),
+        }
+    }
+
+    #[test]
+    fn contiguous() {
+        let mut entries = [entry(0, 0, 40), entry(1, 40, 100)];
+        let layout = layout(200, 100);
+        assert_eq!(0, structural_issues(&layout, &mut entries).count());
+    }
+
+    #[test]
+    fn overlapping_entries() {
+        let mut entries = [entry(0, 0, 100), entry(1, 0, 100), entry(2, 50, 100)];
+        let layout = layout(200, 100);
+        let mut issues = structural_issues(&layout, &mut entries);
+        assert_eq!(
+            Some(StructuralIssue::Overlap {
+                first: 0,
+                second: 1,
+                extent: Extent::new(0, 100)
+            }),
+            issues.next()
+        );
+        assert_eq!(
+            Some(StructuralIssue::Overlap {
+                first: 0,
+                second: 2,
+                extent: Extent::new(50, 100)
+            }),
+            issues.next()
+        );
+        assert_eq!(None, issues.next());
+        assert!(StructurePolicy::Lenient
+            .check(&layout, &mut entries)
+            .is_err());
+    }
+
+    #[test]
+    fn prepended_gap_and_trailing() {
+        let mut entries = [entry(1, 60, 100), entry(0, 10, 50)];
+        let mut layout = layout(200, 100);
+        layout.end_of_central_directory = Extent::new(178, 192);
+        let mut issues = structural_issues(&layout, &mut entries);
+        assert_eq!(
+            Some(StructuralIssue::PrependedData { size: 10 }),
+            issues.next()
+        );
+        assert_eq!(
+            Some(StructuralIssue::Gap(Extent::new(50, 60))),
+            issues.next()
+        );
+        assert_eq!(
+            Some(StructuralIssue::TrailingData { size: 8 }),
+            issues.next()
+        );
+        assert_eq!(None, issues.next());
+        assert_eq!(
+            Ok(()),
+            StructurePolicy::Lenient.check(&layout, &mut entries)
+        );
+        assert_eq!(
+            Err(StructuralIssue::PrependedData { size: 10 }),
+            StructurePolicy::Strict.check(&layout, &mut entries)
+        );
+    }
+
+    #[test]
+    fn into_central_directory_and_past_end() {
+        let mut entries = [entry(0, 0, 120), entry(1, 150, 300)];
+        let layout = layout(200, 100);
+        let mut issues = structural_issues(&layout, &mut entries);
+        assert_eq!(
+            Some(StructuralIssue::IntoCentralDirectory { index: 0 }),
+            issues.next()
+        );
+        assert_eq!(
+            Some(StructuralIssue::IntoCentralDirectory { index: 1 }),
+            issues.next()
+        );
+        assert_eq!(
+            Some(StructuralIssue::PastEndOfFile { index: 1 }),
+            issues.next()
+        );
+        assert_eq!(None, issues.next());
+    }
+}
@@ -38,13 +38,33 @@ pub fn make_zip_entry<'a>(
     }
 }
 
+/// Creates a zip entry from just the local file entry,
+/// for when the central directory isn't available, eg when streaming.
+/// The attributes and comment are only in the central directory,
+/// so are left empty, and the version made by is the version needed
+pub fn local_zip_entry<'a>(local: &LocalFileEntry<'a>) -> ZipEntry<'a> {
+    ZipEntry {
+        version_made_by: local.version_needed,
+        version_needed: local.version_needed,
+        general_purpose: local.general_purpose,
+        file_modification_time: local.file_modification_time,
+        file_modification_date: local.file_modification_date,
+        internal_file_attributes: 0,
+        external_file_attributes: 0,
+        file_name: local.file_name,
+        extra_field: local.extra_field,
+        comment: Default::default(),
+        compressed_data: local.compressed_data,
+    }
+}
+
 /// Given a zip entry, convert it into
 /// the local file and central directory entries.
 /// Note we need to know where the localfile entry will be written as the offset from
 /// the beginning of the file
 pub fn zip_entry_to_files<'a>(
     relative_offset: u32,
-    entry: &'a ZipEntry<'a>,
+    entry: &ZipEntry<'a>,
 ) -> (LocalFileEntry<'a>, CentralDirectoryEntry<'a>) {
     let version_made_by = entry.version_made_by;
     let version_needed = entry.version_needed;
@@ -84,6 +104,7 @@ pub fn zip_entry_to_files<'a>(
         uncompressed_size,
         internal_file_attributes,
         external_file_attributes,
+        disk_number_start: 0,
         relative_offset,
         file_name,
         extra_field,
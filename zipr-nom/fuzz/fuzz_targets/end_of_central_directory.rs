@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::error::ParseFailure;
+use zipr_nom::find_end_of_central_directory;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = find_end_of_central_directory::<ParseFailure>(data);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use zipr_domain::ConsistencyPolicy;
+use zipr_nom::iter::{iterate_central_directory, zip_entry_iter};
+
+fuzz_target!(|data: &[u8]| {
+    iterate_central_directory(data).for_each(drop);
+    zip_entry_iter(data).for_each(drop);
+    zip_entry_iter(data)
+        .with_policy(ConsistencyPolicy::Strict)
+        .for_each(drop);
+});
@@ -0,0 +1,17 @@
+#![no_main]
+//! Each record parser on its own, given arbitrary bytes
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::{data::*, error::ParseFailure};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_directory_header::<ParseFailure>(data);
+    let _ = parse_local_file::<ParseFailure>(data);
+    let _ = parse_end_of_central_directory::<ParseFailure>(data);
+    let _ = parse_extra_field::<ParseFailure>(data);
+    let _ = parse_extended_timestamp::<ParseFailure>(data);
+    let _ = parse_ntfs::<ParseFailure>(data);
+    let _ = parse_version::<ParseFailure>(data);
+    let _ = parse_compression_method::<ParseFailure>(data);
+    let _ = parse_data_descriptor::<ParseFailure>(data);
+    let _ = parse_zip_path::<ParseFailure>(data);
+});
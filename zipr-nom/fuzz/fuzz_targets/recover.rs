@@ -0,0 +1,8 @@
+#![no_main]
+//! Scans for local headers, as when the central directory is lost
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::recover::recovery_scan;
+
+fuzz_target!(|data: &[u8]| {
+    for _ in recovery_scan(data) {}
+});
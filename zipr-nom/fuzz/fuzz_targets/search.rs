@@ -0,0 +1,11 @@
+#![no_main]
+//! The parsers that follow offsets through the whole file
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::error::ParseFailure;
+use zipr_nom::{find_central_directory_entries, find_local_file_entries, parse_zip_entries};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = find_central_directory_entries::<ParseFailure>(data);
+    let _ = find_local_file_entries::<ParseFailure>(data);
+    let _ = parse_zip_entries::<ParseFailure>(data);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+//! Reads entries forwards, as a pipe would give them
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::{error::ParseFailure, stream::parse_streaming_entry};
+
+fuzz_target!(|data: &[u8]| {
+    let mut input = data;
+    while let Ok((rest, Some(_))) = parse_streaming_entry::<ParseFailure>(input) {
+        input = rest;
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use zipr_nom::error::ParseFailure;
+use zipr_nom::{archive_layout, find_structural_issues};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = archive_layout::<ParseFailure>(data);
+    let _ = find_structural_issues::<ParseFailure>(data);
+});
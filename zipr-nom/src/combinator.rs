@@ -0,0 +1,353 @@
+//! Combinators for formats built on zip, eg EPUB, OOXML, JAR and ODF
+//!
+//! Each takes the full archive and applies a child parser to the
+//! decompressed bytes of an entry. The archive isn't consumed, so
+//! these can be combined, eg with tuple, to check several entries.
+//!
+//! The decompressed bytes only live for the call, so the child's output
+//! can't borrow them. Closures and functions like
+//! `fn mimetype<'b>(input: &'b [u8]) -> IResult<&'b [u8], (), ParseFailure<'b>>`
+//! work, as they take any lifetime. Failures in the child are placed at
+//! the entry's compressed data, keeping the child's field
+use alloc::vec::Vec;
+
+use nom::{error::ErrorKind, Err, IResult};
+use zipr_compression::{DecompressError, DecompressStream};
+use zipr_data::{
+    borrowed::{file::CompressedData, AsSymbols, ZipEntry, ZipPath},
+    CompressionMethod,
+};
+use zipr_domain::make_zip_entry;
+
+use crate::{
+    central_directory_entries,
+    error::{ParseFailure, ZipParseError, CENTRAL_DIRECTORY_HEADER, LOCAL_FILE_HEADER},
+    find_end_of_central_directory, local_file_entries, segmented_local_entry, Segments,
+};
+
+/// How much is decompressed at a time
+const CHUNK_SIZE: usize = 4 * 1024;
+
+fn named(path: &ZipPath<'_>, name: &str) -> bool {
+    path.to_utf8().eq(name.chars())
+}
+
+/// A failure in a header field, located at the entry's name
+fn header_error<'a, E: ZipParseError<'a>>(
+    record: &'static str,
+    path: &ZipPath<'a>,
+    field: &'static str,
+    kind: ErrorKind,
+) -> Err<E> {
+    let at = path.as_ref().as_bytes();
+    let error = E::add_context(at, field, E::from_error_kind(at, kind));
+    Err::Error(E::add_context(at, record, error))
+}
+
+/// Expands the data, growing as it goes rather than trusting the
+/// uncompressed size. Fails as soon as more than that is produced
+fn expand(data: CompressedData<'_>) -> Result<Vec<u8>, DecompressError> {
+    let mut stream = DecompressStream::new(data);
+    let mut bytes = Vec::new();
+    loop {
+        let start = bytes.len();
+        bytes.resize(start + CHUNK_SIZE, 0);
+        let read = stream.read(&mut bytes[start..])?;
+        bytes.truncate(start + read);
+        if read == 0 {
+            return Ok(bytes);
+        }
+    }
+}
+
+/// Moves a failure in the decompressed bytes to the compressed data
+fn from_child<'a, E: ZipParseError<'a>>(data: &'a [u8], failure: ParseFailure<'_>) -> E {
+    let error = E::from_error_kind(data, failure.kind);
+    let error = match failure.field {
+        Some(field) => E::add_context(data, field, error),
+        None => error,
+    };
+    let error = E::add_context(data, "decompressed data", error);
+    E::add_context(data, LOCAL_FILE_HEADER, error)
+}
+
+/// Applies the parser to the entry's decompressed bytes.
+/// Corrupt data, or a crc32 or size that doesn't match, fails with Verify
+pub fn decompressed<'a, O, E, F>(mut parser: F) -> impl FnMut(&ZipEntry<'a>) -> Result<O, Err<E>>
+where
+    E: ZipParseError<'a>,
+    F: for<'b> FnMut(&'b [u8]) -> IResult<&'b [u8], O, ParseFailure<'b>>,
+{
+    move |entry: &ZipEntry<'a>| {
+        let data = entry.compressed_data.bytes();
+        let bytes = expand(entry.compressed_data).map_err(|_| {
+            let error = E::from_error_kind(data, ErrorKind::Verify);
+            let error = E::add_context(data, "compressed data", error);
+            Err::Error(E::add_context(data, LOCAL_FILE_HEADER, error))
+        })?;
+        match parser(&bytes) {
+            Ok((_, output)) => Ok(output),
+            Err(Err::Error(e)) => Err(Err::Error(from_child(data, e))),
+            Err(Err::Failure(e)) => Err(Err::Failure(from_child(data, e))),
+            // The bytes are all there, so the child can't have more
+            Err(Err::Incomplete(_)) => {
+                let error = E::from_error_kind(data, ErrorKind::Complete);
+                Err(Err::Error(E::add_context(data, "decompressed data", error)))
+            }
+        }
+    }
+}
+
+/// Applies the parser to the entry with the name.
+/// Fails with Verify, in the file name, if there isn't one
+pub fn entry_named<'a, O, E, F>(
+    name: &'a str,
+    parser: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
+where
+    E: ZipParseError<'a>,
+    F: for<'b> FnMut(&'b [u8]) -> IResult<&'b [u8], O, ParseFailure<'b>>,
+{
+    let mut contents = decompressed(parser);
+    move |input: &'a [u8]| {
+        let (_, directories) = central_directory_entries::<E>(input)?;
+        let (_, end) = find_end_of_central_directory::<E>(input)?;
+        let segments = Segments::single(input, &end);
+        for directory in directories {
+            let directory = directory?;
+            if named(&directory.file_name, name) {
+                let (_, local) = segmented_local_entry::<E>(input, segments, &directory)?;
+                let output = contents(&make_zip_entry(&directory, &local))?;
+                return Ok((input, output));
+            }
+        }
+        let error = E::add_context(
+            input,
+            "file name",
+            E::from_error_kind(input, ErrorKind::Verify),
+        );
+        Err(Err::Error(E::add_context(
+            input,
+            CENTRAL_DIRECTORY_HEADER,
+            error,
+        )))
+    }
+}
+
+/// Applies the parser to each entry the predicate accepts,
+/// in central directory order
+pub fn entries_matching<'a, O, E, P, F>(
+    mut predicate: P,
+    parser: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>, E>
+where
+    E: ZipParseError<'a>,
+    P: FnMut(&ZipEntry<'a>) -> bool,
+    F: for<'b> FnMut(&'b [u8]) -> IResult<&'b [u8], O, ParseFailure<'b>>,
+{
+    let mut contents = decompressed(parser);
+    move |input: &'a [u8]| {
+        let (_, entries) = local_file_entries::<E>(input)?;
+        let mut outputs = Vec::new();
+        for joined in entries {
+            let (directory, local) = joined?;
+            let entry = make_zip_entry(&directory, &local);
+            if predicate(&entry) {
+                outputs.push(contents(&entry)?);
+            }
+        }
+        Ok((input, outputs))
+    }
+}
+
+/// Applies the parser to the first entry in the file, which must have
+/// the name and compression method. Eg an EPUB starts with a stored mimetype.
+/// Fails with Tag, in the field that doesn't match, and with Eof when
+/// no entry starts the file
+pub fn first_entry_must_be<'a, O, E, F>(
+    name: &'a str,
+    method: CompressionMethod,
+    parser: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
+where
+    E: ZipParseError<'a>,
+    F: for<'b> FnMut(&'b [u8]) -> IResult<&'b [u8], O, ParseFailure<'b>>,
+{
+    let mut contents = decompressed(parser);
+    move |input: &'a [u8]| {
+        let (_, directories) = central_directory_entries::<E>(input)?;
+        let (_, end) = find_end_of_central_directory::<E>(input)?;
+        let segments = Segments::single(input, &end);
+        // The central directory needn't be in the same order as the entries
+        let mut first = None;
+        for directory in directories {
+            let directory = directory?;
+            if directory.disk_number_start == 0 && directory.relative_offset == 0 {
+                first = Some(directory);
+                break;
+            }
+        }
+        let directory = match first {
+            Some(directory) => directory,
+            None => return Err(Err::Error(E::from_error_kind(input, ErrorKind::Eof))),
+        };
+
+        let path = &directory.file_name;
+        let field = "compression method";
+        if !named(path, name) {
+            let record = CENTRAL_DIRECTORY_HEADER;
+            return Err(header_error(record, path, "file name", ErrorKind::Tag));
+        }
+        if directory.compression_method != method {
+            let record = CENTRAL_DIRECTORY_HEADER;
+            return Err(header_error(record, path, field, ErrorKind::Tag));
+        }
+        let (_, local) = segmented_local_entry::<E>(input, segments, &directory)?;
+        if local.compressed_data.compression_method() != method {
+            let record = LOCAL_FILE_HEADER;
+            return Err(header_error(
+                record,
+                &local.file_name,
+                field,
+                ErrorKind::Tag,
+            ));
+        }
+        let output = contents(&make_zip_entry(&directory, &local))?;
+        Ok((input, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::{
+        bytes::complete::tag,
+        combinator::{rest, value},
+        error::{context, Error},
+        multi::many1,
+        sequence::tuple,
+        Finish,
+    };
+
+    use super::*;
+    use crate::error::{Record, ZipError};
+
+    fn mimetype<'b>(input: &'b [u8]) -> IResult<&'b [u8], (), ParseFailure<'b>> {
+        value((), context("mimetype", tag("world")))(input)
+    }
+
+    #[test]
+    fn epub_like() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let mut parser = tuple((
+            first_entry_must_be("hello.txt", CompressionMethod::Stored, mimetype),
+            entry_named("moredata.txt", |x| rest(x).map(|(r, x)| (r, x.to_vec()))),
+        ));
+        let (_, (_, more)) = parser(input)
+            .finish()
+            .map_err(|e: Error<_>| e.code)
+            .unwrap();
+
+        assert_eq!(b"nom", &more[..]);
+    }
+
+    #[test]
+    fn entries_matching_deflate() {
+        let input = include_bytes!("../../assets/hello_world_deflate.zip");
+        let mut parser = entries_matching(
+            |x| x.compressed_data.compression_method() == CompressionMethod::Deflate,
+            |x| many1(tag("world"))(x).map(|(r, x)| (r, x.len())),
+        );
+        let (_, counts) = parser(input)
+            .finish()
+            .map_err(|e: Error<_>| e.code)
+            .unwrap();
+
+        assert_eq!(alloc::vec![43], counts);
+    }
+
+    #[test]
+    fn missing_entry() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let result = entry_named::<_, ParseFailure, _>("missing.txt", mimetype)(input).finish();
+
+        assert_eq!(ErrorKind::Verify, result.map(|_| ()).unwrap_err().kind);
+    }
+
+    #[test]
+    fn first_entry_wrong_method() {
+        let input = include_bytes!("../../assets/hello_world_deflate.zip");
+        let mut parser = first_entry_must_be("hello.txt", CompressionMethod::Stored, mimetype);
+        let failure = parser(input).finish().map(|_| ()).unwrap_err();
+        let error = ZipError::new(input, Record::CentralDirectoryHeader(0), failure);
+
+        assert_eq!(Some("compression method"), error.field);
+        assert_eq!(ErrorKind::Tag, error.kind);
+    }
+
+    #[test]
+    fn first_entry_in_the_file() {
+        let mut input = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        // The directory lists hello.txt first, but moredata.txt now starts the file
+        input[0x83..0x87].copy_from_slice(&0x2cu32.to_le_bytes());
+        input[0xde..0xe2].copy_from_slice(&0u32.to_le_bytes());
+        let mut parser = first_entry_must_be::<_, ParseFailure, _>(
+            "hello.txt",
+            CompressionMethod::Stored,
+            mimetype,
+        );
+        let failure = parser(&input).finish().map(|_| ()).unwrap_err();
+
+        assert_eq!(Some("file name"), failure.field);
+        assert_eq!(ErrorKind::Tag, failure.kind);
+    }
+
+    #[test]
+    fn first_entry_local_method() {
+        let mut input = include_bytes!("../../assets/hello_world_deflate.zip").to_vec();
+        input[8] = 0;
+        let mut parser = first_entry_must_be("hello.txt", CompressionMethod::Deflate, mimetype);
+        let failure = parser(&input).finish().map(|_| ()).unwrap_err();
+        let error = ZipError::new(&input, Record::LocalFileHeader(0), failure);
+
+        assert_eq!(Some("compression method"), error.field);
+        assert_eq!(Record::LocalFileHeader(0), error.record);
+        // At the name in the local header
+        assert_eq!(30, error.offset);
+    }
+
+    #[test]
+    fn decompressed_past_size() {
+        let input = include_bytes!("../../assets/hello_world_deflate.zip");
+        let (directory, local) = local_file_entries::<Error<_>>(input)
+            .unwrap()
+            .1
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut entry = make_zip_entry(&directory, &local);
+        // Declares 5 bytes, but inflates to 215
+        let data = entry.compressed_data;
+        entry.compressed_data = CompressedData::create_unchecked(
+            5,
+            data.compression_method(),
+            data.crc32(),
+            data.bytes(),
+        );
+        let result = decompressed::<_, Error<_>, _>(|x| rest(x).map(|(r, x)| (r, x.len())))(&entry);
+
+        assert!(matches!(result, Err(Err::Error(e)) if e.code == ErrorKind::Verify));
+    }
+
+    #[test]
+    fn child_failure_located_in_entry() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let mut parser = entry_named("moredata.txt", mimetype);
+        let failure = parser(input).finish().map(|_| ()).unwrap_err();
+        let error = ZipError::new(input, Record::LocalFileHeader(1), failure);
+
+        // The data of the second entry, after its header and name
+        assert_eq!(44 + 30 + 12, error.offset);
+        assert_eq!(Some("mimetype"), error.field);
+        assert_eq!(ErrorKind::Tag, error.kind);
+    }
+}
@@ -1,6 +1,6 @@
 use nom::{
     bytes::complete::tag, bytes::complete::take, combinator::map, combinator::map_parser,
-    number::complete::le_u16, number::complete::le_u32, IResult,
+    error::context, number::complete::le_u16, number::complete::le_u32, IResult,
 };
 use zipr_data::{
     borrowed::file::CentralDirectoryEntry, constants::CENTRAL_DIRECTORY_HEADER_SIGNATURE, DosDate,
@@ -11,33 +11,59 @@ use super::{
     compression_method::parse_compression_method, cp437_char::parse_cp437_chars,
     extra_field::parse_extra_field, parse_version, zip_path::parse_zip_path,
 };
+use crate::error::{ZipParseError, CENTRAL_DIRECTORY_HEADER};
 
 /// Parses a single directory header
-pub fn parse_directory_header(input: &[u8]) -> IResult<&[u8], CentralDirectoryEntry> {
-    let (input, _) = tag(CENTRAL_DIRECTORY_HEADER_SIGNATURE)(input)?;
-    let (input, version_made_by) = parse_version(input)?;
-    let (input, version_needed) = parse_version(input)?;
-    let (input, general_purpose) = le_u16(input)?;
-    let (input, compression_method) = map_parser(take(2u16), parse_compression_method)(input)?;
-    let (input, file_modification_time) = map(le_u16, DosTime::from_u16_unchecked)(input)?;
-    let (input, file_modification_date) = map(le_u16, DosDate::from_u16_unchecked)(input)?;
-    let (input, crc32) = le_u32(input)?;
-    let (input, compressed_size) = le_u32(input)?;
-    let (input, uncompressed_size) = le_u32(input)?;
-    let (input, file_name_length) = le_u16(input)?;
-    let (input, extra_field_length) = le_u16(input)?;
-    let (input, comment_length) = le_u16(input)?;
-    // Assume single zip for now
-    let (input, _disk_file_starts) = tag([0, 0])(input)?;
-    let (input, internal_file_attributes) = le_u16(input)?;
-    let (input, external_file_attributes) = le_u32(input)?;
-    let (input, relative_offset) = le_u32(input)?;
-
-    let (input, file_name) = map_parser(take(file_name_length), parse_zip_path)(input)?;
-
-    let (input, extra_field) = map_parser(take(extra_field_length), parse_extra_field)(input)?;
-
-    let (input, comment) = map_parser(take(comment_length), parse_cp437_chars)(input)?;
+pub fn parse_directory_header<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], CentralDirectoryEntry<'a>, E> {
+    context(CENTRAL_DIRECTORY_HEADER, directory_header)(input)
+}
+
+fn directory_header<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], CentralDirectoryEntry<'a>, E> {
+    let (input, _) = context("signature", tag(CENTRAL_DIRECTORY_HEADER_SIGNATURE))(input)?;
+    let (input, version_made_by) = context("version made by", parse_version)(input)?;
+    let (input, version_needed) = context("version needed", parse_version)(input)?;
+    let (input, general_purpose) = context("general purpose flags", le_u16)(input)?;
+    let (input, compression_method) = context(
+        "compression method",
+        map_parser(take(2u16), parse_compression_method),
+    )(input)?;
+    let (input, file_modification_time) = context(
+        "modification time",
+        map(le_u16, DosTime::from_u16_unchecked),
+    )(input)?;
+    let (input, file_modification_date) = context(
+        "modification date",
+        map(le_u16, DosDate::from_u16_unchecked),
+    )(input)?;
+    let (input, crc32) = context("crc32", le_u32)(input)?;
+    let (input, compressed_size) = context("compressed size", le_u32)(input)?;
+    let (input, uncompressed_size) = context("uncompressed size", le_u32)(input)?;
+    let (input, file_name_length) = context("file name length", le_u16)(input)?;
+    let (input, extra_field_length) = context("extra field length", le_u16)(input)?;
+    let (input, comment_length) = context("comment length", le_u16)(input)?;
+    let (input, disk_number_start) = context("disk number start", le_u16)(input)?;
+    let (input, internal_file_attributes) = context("internal attributes", le_u16)(input)?;
+    let (input, external_file_attributes) = context("external attributes", le_u32)(input)?;
+    let (input, relative_offset) = context("relative offset", le_u32)(input)?;
+
+    let (input, file_name) = context(
+        "file name",
+        map_parser(take(file_name_length), parse_zip_path),
+    )(input)?;
+
+    let (input, extra_field) = context(
+        "extra field",
+        map_parser(take(extra_field_length), parse_extra_field),
+    )(input)?;
+
+    let (input, comment) = context(
+        "comment",
+        map_parser(take(comment_length), parse_cp437_chars),
+    )(input)?;
     let result = CentralDirectoryEntry {
         version_made_by,
         version_needed,
@@ -50,6 +76,7 @@ pub fn parse_directory_header(input: &[u8]) -> IResult<&[u8], CentralDirectoryEn
         uncompressed_size,
         internal_file_attributes,
         external_file_attributes,
+        disk_number_start,
         relative_offset,
         file_name,
         extra_field,
@@ -61,6 +88,7 @@ pub fn parse_directory_header(input: &[u8]) -> IResult<&[u8], CentralDirectoryEn
 #[cfg(test)]
 mod tests {
     use core::convert::{TryFrom, TryInto};
+    use nom::error::Error;
 
     use zipr_data::{
         borrowed::{
@@ -76,7 +104,7 @@ mod tests {
     fn hello_world_store() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = &hello[0x2c..0x87];
-        let result = parse_directory_header(data);
+        let result = parse_directory_header::<Error<&[u8]>>(data);
         let expected = CentralDirectoryEntry {
             version_made_by: Version {
                 host: HostCompatibility::MSDOS,
@@ -108,6 +136,7 @@ mod tests {
             }),
             compression_method: CompressionMethod::Stored,
             general_purpose: 0,
+            disk_number_start: 0,
             relative_offset: 0,
         };
 
@@ -118,7 +147,7 @@ mod tests {
     fn hello_world_deflate() {
         let hello = include_bytes!("../../../assets/hello_world_deflate.zip");
         let data = &hello[0x3d..0x3d + 91];
-        let result = parse_directory_header(data);
+        let result = parse_directory_header::<Error<&[u8]>>(data);
         let expected = CentralDirectoryEntry {
             version_made_by: Version {
                 host: HostCompatibility::MSDOS,
@@ -150,6 +179,7 @@ mod tests {
             }),
             compression_method: CompressionMethod::Deflate,
             general_purpose: 0,
+            disk_number_start: 0,
             relative_offset: 0,
         };
         assert_eq!(Ok((&[] as &[u8], expected)), result);
@@ -159,7 +189,7 @@ mod tests {
     fn two_file_store() {
         let hello = include_bytes!("../../../assets/two_files_store.zip");
         let data = &hello[0xb4..(0x59 + 185)];
-        let result = parse_directory_header(data);
+        let result = parse_directory_header::<Error<&[u8]>>(data);
 
         let (input, result) = result.unwrap();
 
@@ -5,9 +5,13 @@ use zipr_data::{
     CompressionMethod,
 };
 
+use crate::error::ZipParseError;
+
 /// Uses alternatives to try and pass the extra field.
 /// Will return one of the datatypes, or fail
-pub fn parse_compression_method(input: &[u8]) -> IResult<&[u8], CompressionMethod> {
+pub fn parse_compression_method<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], CompressionMethod, E> {
     let (input, method) = alt((
         map(tag(COMPRESSION_STORED), |_| CompressionMethod::Stored),
         map(tag(COMPRESSION_DEFLATE), |_| CompressionMethod::Deflate),
@@ -19,11 +23,12 @@ pub fn parse_compression_method(input: &[u8]) -> IResult<&[u8], CompressionMetho
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nom::error::Error;
 
     #[test]
     fn compression_stored() {
         let input: &[u8] = &[0x00, 0x00];
-        let (rem, result) = parse_compression_method(input).unwrap();
+        let (rem, result) = parse_compression_method::<Error<&[u8]>>(input).unwrap();
         assert_eq!(&[] as &[u8], rem);
         assert_eq!(CompressionMethod::Stored, result);
     }
@@ -31,7 +36,7 @@ mod tests {
     #[test]
     fn compression_deflate() {
         let input: &[u8] = &[0x08, 0x00];
-        let (rem, result) = parse_compression_method(input).unwrap();
+        let (rem, result) = parse_compression_method::<Error<&[u8]>>(input).unwrap();
         assert_eq!(&[] as &[u8], rem);
         assert_eq!(CompressionMethod::Deflate, result);
     }
@@ -4,8 +4,12 @@ use nom::{
 };
 use zipr_data::borrowed::OEM437Str;
 
+use crate::error::ZipParseError;
+
 /// Parses the entire input as a asciichar
-pub fn parse_cp437_chars<'a>(input: &'a [u8]) -> IResult<&[u8], OEM437Str<'a>> {
+pub fn parse_cp437_chars<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], OEM437Str<'a>, E> {
     let (rem, chars) = map(rest, OEM437Str::from)(input)?;
     let (rem, _) = eof(rem)?;
     Ok((rem, chars))
@@ -0,0 +1,51 @@
+use nom::{
+    bytes::complete::tag, combinator::opt, error::context, number::complete::le_u32, IResult,
+};
+use zipr_data::{borrowed::file::DataDescriptor, constants::DATA_DESCRIPTOR_SIGNATURE};
+
+use crate::error::{ZipParseError, DATA_DESCRIPTOR};
+
+/// Parses a data descriptor, the signature is optional
+pub fn parse_data_descriptor<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], DataDescriptor, E> {
+    context(DATA_DESCRIPTOR, data_descriptor)(input)
+}
+
+fn data_descriptor<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], DataDescriptor, E> {
+    let (input, _) = context("signature", opt(tag(DATA_DESCRIPTOR_SIGNATURE)))(input)?;
+    let (input, crc32) = context("crc32", le_u32)(input)?;
+    let (input, compressed_size) = context("compressed size", le_u32)(input)?;
+    let (input, uncompressed_size) = context("uncompressed size", le_u32)(input)?;
+    let result = DataDescriptor {
+        crc32,
+        compressed_size,
+        uncompressed_size,
+    };
+    Ok((input, result))
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn data_descriptor_zip() {
+        let input = include_bytes!("../../../assets/data_descriptor.zip");
+        let expected = DataDescriptor {
+            crc32: 0x3610a686,
+            compressed_size: 5,
+            uncompressed_size: 5,
+        };
+
+        let result = parse_data_descriptor::<Error<&[u8]>>(&input[0x2c..0x3c]);
+        assert_eq!(Ok((&[] as &[u8], expected)), result);
+        // Without the signature
+        let result = parse_data_descriptor::<Error<&[u8]>>(&input[0x30..0x3c]);
+        assert_eq!(Ok((&[] as &[u8], expected)), result);
+    }
+}
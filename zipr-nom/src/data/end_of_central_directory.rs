@@ -6,29 +6,43 @@ use nom::{
     bytes::complete::tag,
     bytes::complete::take,
     combinator::{eof, map_parser},
+    error::context,
     number::complete::le_u16,
     number::complete::le_u32,
     IResult,
 };
 
 use super::cp437_char::parse_cp437_chars;
+use crate::error::{ZipParseError, END_OF_CENTRAL_DIRECTORY};
 
 /// Parses the end of central directory record exactly
 /// Fails if its not present
-pub fn parse_end_of_central_directory(input: &[u8]) -> IResult<&[u8], EndOfCentralDirectory> {
-    let (input, _) = tag(END_OF_CENTRAL_DIRECTORY_HEADER)(input)?;
-    //For now only support a single zip file
-    let (input, _number_of_this_disk) = tag([0, 0])(input)?;
-    let (input, _directory_start_disk) = tag([0, 0])(input)?;
-    // don't use this, but should equal total number of records
-    let (input, _records_on_this_disk) = le_u16(input)?;
-    let (input, total_number_records) = le_u16(input)?;
-    let (input, size_of_directory) = le_u32(input)?;
-    let (input, offset_start_directory) = le_u32(input)?;
-    let (input, comment_length) = le_u16(input)?;
-    let (input, comment) = map_parser(take(comment_length), parse_cp437_chars)(input)?;
-    let (input, _eof) = eof(input)?;
+pub fn parse_end_of_central_directory<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], EndOfCentralDirectory<'a>, E> {
+    context(END_OF_CENTRAL_DIRECTORY, end_of_central_directory)(input)
+}
+
+fn end_of_central_directory<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], EndOfCentralDirectory<'a>, E> {
+    let (input, _) = context("signature", tag(END_OF_CENTRAL_DIRECTORY_HEADER))(input)?;
+    let (input, number_of_this_disk) = context("disk number", le_u16)(input)?;
+    let (input, directory_start_disk) = context("directory disk number", le_u16)(input)?;
+    let (input, records_on_this_disk) = context("records on this disk", le_u16)(input)?;
+    let (input, total_number_records) = context("total records", le_u16)(input)?;
+    let (input, size_of_directory) = context("directory size", le_u32)(input)?;
+    let (input, offset_start_directory) = context("directory offset", le_u32)(input)?;
+    let (input, comment_length) = context("comment length", le_u16)(input)?;
+    let (input, comment) = context(
+        "comment",
+        map_parser(take(comment_length), parse_cp437_chars),
+    )(input)?;
+    let (input, _eof) = context("comment length", eof)(input)?;
     let result = EndOfCentralDirectory {
+        number_of_this_disk,
+        directory_start_disk,
+        records_on_this_disk,
         total_number_records,
         size_of_directory,
         offset_start_directory,
@@ -40,6 +54,7 @@ pub fn parse_end_of_central_directory(input: &[u8]) -> IResult<&[u8], EndOfCentr
 
 #[cfg(test)]
 mod tests {
+    use nom::error::Error;
     use zipr_data::{
         borrowed::file::EndOfCentralDirectory, constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE,
     };
@@ -53,7 +68,7 @@ mod tests {
 
     #[test]
     fn minimal() {
-        let result = parse_end_of_central_directory(&MINIMAL);
+        let result = parse_end_of_central_directory::<Error<&[u8]>>(&MINIMAL);
         let expected = {
             let remaining: &[u8] = &[];
             let directory = EndOfCentralDirectory {
@@ -71,8 +86,9 @@ mod tests {
             let len = hello.len();
             &hello[len - END_OF_CENTRAL_DIRECTORY_MIN_SIZE..len]
         };
-        let result = parse_end_of_central_directory(header);
+        let result = parse_end_of_central_directory::<Error<&[u8]>>(header);
         let expected = EndOfCentralDirectory {
+            records_on_this_disk: 1,
             total_number_records: 1,
             size_of_directory: 91,
             offset_start_directory: 0x2C,
@@ -89,8 +105,9 @@ mod tests {
             let len = hello.len();
             &hello[len - END_OF_CENTRAL_DIRECTORY_MIN_SIZE..len]
         };
-        let result = parse_end_of_central_directory(header);
+        let result = parse_end_of_central_directory::<Error<&[u8]>>(header);
         let expected = EndOfCentralDirectory {
+            records_on_this_disk: 2,
             total_number_records: 2,
             size_of_directory: 185,
             offset_start_directory: 0x59,
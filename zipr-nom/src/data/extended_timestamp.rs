@@ -0,0 +1,101 @@
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::{map_parser, opt},
+    error::context,
+    number::complete::{le_i32, le_u16, le_u8},
+    IResult,
+};
+use zipr_data::{
+    borrowed::extra_field::extended_timestamp::ExtendedTimestamp,
+    constants::EXTRA_FIELD_EXTENDED_TIMESTAMP_HEADER,
+};
+
+use crate::error::{ZipParseError, EXTENDED_TIMESTAMP_EXTRA_FIELD};
+
+/// Reads the time if the flag says it is present
+/// The central directory may set flags without including the time,
+/// so a missing time is not an error
+fn time<'a, E: ZipParseError<'a>>(
+    flags: u8,
+    bit: u8,
+) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], Option<i32>, E> {
+    move |input: &'a [u8]| {
+        if flags & bit != 0 {
+            opt(le_i32)(input)
+        } else {
+            Ok((input, None))
+        }
+    }
+}
+
+fn parse_times<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ExtendedTimestamp, E> {
+    let (input, flags) = context("flags", le_u8)(input)?;
+    let (input, modify) = context("modify time", time(flags, 0x1))(input)?;
+    let (input, access) = context("access time", time(flags, 0x2))(input)?;
+    let (input, create) = context("create time", time(flags, 0x4))(input)?;
+    let result = ExtendedTimestamp {
+        flags,
+        modify,
+        access,
+        create,
+    };
+    Ok((input, result))
+}
+
+pub fn parse_extended_timestamp<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ExtendedTimestamp, E> {
+    context(EXTENDED_TIMESTAMP_EXTRA_FIELD, extended_timestamp)(input)
+}
+
+fn extended_timestamp<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ExtendedTimestamp, E> {
+    let (input, _) = context("header id", tag(EXTRA_FIELD_EXTENDED_TIMESTAMP_HEADER))(input)?;
+    let (input, size) = context("size", le_u16)(input)?;
+    let (input, result) = map_parser(take(size), parse_times)(input)?;
+    Ok((input, result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::error::Error;
+
+    #[test]
+    fn local_all_times() {
+        let input: &[u8] = &[
+            0x55, 0x54, 0x0d, 0x00, 0x07, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0x00, 0x00, 0x00,
+        ];
+        let expected = ExtendedTimestamp {
+            flags: 7,
+            modify: Some(1),
+            access: Some(2),
+            create: Some(3),
+        };
+
+        assert_eq!(
+            Ok((&[] as &[u8], expected)),
+            parse_extended_timestamp::<Error<&[u8]>>(input)
+        );
+    }
+
+    #[test]
+    fn central_modify_only() {
+        let input: &[u8] = &[0x55, 0x54, 0x05, 0x00, 0x03, 0x10, 0x00, 0x00, 0x00];
+        let expected = ExtendedTimestamp {
+            flags: 3,
+            modify: Some(16),
+            access: None,
+            create: None,
+        };
+
+        assert_eq!(
+            Ok((&[] as &[u8], expected)),
+            parse_extended_timestamp::<Error<&[u8]>>(input)
+        );
+    }
+}
@@ -1,27 +1,72 @@
-use nom::{branch::alt, bytes::complete::take, combinator::map, IResult};
+use nom::{
+    branch::alt,
+    bytes::complete::take,
+    combinator::{all_consuming, map},
+    error::Error,
+    number::complete::le_u16,
+    IResult,
+};
 use zipr_data::borrowed::extra_field::ExtraField;
 
-use super::ntfs::parse_ntfs;
+use super::{extended_timestamp::parse_extended_timestamp, ntfs::parse_ntfs};
+use crate::error::ZipParseError;
 
 /// Uses alternatives to try and pass the extra field.
-/// Will return one of the datatypes, or fail
-pub fn parse_extra_field(input: &[u8]) -> IResult<&[u8], ExtraField<'_>> {
-    let ntfs = map(parse_ntfs, ExtraField::NTFS);
+/// A known type is only used if it is the whole field, otherwise
+/// the bytes are kept as unknown so no other subfields are lost
+pub fn parse_extra_field<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ExtraField<'a>, E> {
+    let ntfs = map(all_consuming(parse_ntfs), ExtraField::NTFS);
+    let extended_timestamp = map(
+        all_consuming(parse_extended_timestamp),
+        ExtraField::ExtendedTimestamp,
+    );
     let unknown = {
         let len = input.len();
         map(take(len), ExtraField::Unknown)
     };
-    let (input, result) = alt((ntfs, unknown))(input)?;
+    let (input, result) = alt((ntfs, extended_timestamp, unknown))(input)?;
 
     Ok((input, result))
 }
 
+/// Splits an extra field into its subfields, parsing each as a single extra field.
+/// Stops at the first subfield whose size runs past the end
+pub fn extra_subfields(input: &[u8]) -> ExtraSubfields<'_> {
+    ExtraSubfields { input }
+}
+
+/// See extra_subfields
+#[derive(Debug, Clone)]
+pub struct ExtraSubfields<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Iterator for ExtraSubfields<'a> {
+    type Item = ExtraField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, size) = le_u16::<_, Error<&[u8]>>(self.input.get(2..)?).ok()?;
+        let end = 4 + size as usize;
+        if self.input.len() < end {
+            return None;
+        }
+        let (subfield, rem) = self.input.split_at(end);
+        self.input = rem;
+        parse_extra_field::<Error<&[u8]>>(subfield)
+            .ok()
+            .map(|(_, field)| field)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use nom::error::Error;
 
     use core::convert::TryInto;
 
-    use zipr_data::borrowed::extra_field::ntfs::NTFS;
+    use zipr_data::borrowed::extra_field::{extended_timestamp::ExtendedTimestamp, ntfs::NTFS};
 
     use super::*;
 
@@ -29,7 +74,7 @@ mod tests {
     fn hello_world_store() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = &hello[0x63..0x87];
-        let result = parse_extra_field(data);
+        let result = parse_extra_field::<Error<&[u8]>>(data);
         let expected = {
             let ntfs = NTFS {
                 atime: 132514708162669827.try_into().unwrap(),
@@ -41,4 +86,37 @@ mod tests {
 
         assert_eq!(Ok((&[] as &[u8], expected)), result);
     }
+
+    /// Info-ZIP writes the extended timestamp followed by the unix uid/gid
+    const TIMESTAMP_AND_UNIX: [u8; 24] = [
+        0x55, 0x54, 0x05, 0x00, 0x03, 0x47, 0xcc, 0xd5, 0x6a, 0x75, 0x78, 0x0b, 0x00, 0x01, 0x04,
+        0xe8, 0x03, 0x00, 0x00, 0x04, 0xe8, 0x03, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn keeps_subfields_after_known_type() {
+        let result = parse_extra_field::<Error<&[u8]>>(&TIMESTAMP_AND_UNIX);
+        let expected = ExtraField::Unknown(&TIMESTAMP_AND_UNIX);
+        assert_eq!(Ok((&[] as &[u8], expected)), result);
+    }
+
+    #[test]
+    fn subfields() {
+        let timestamp = ExtendedTimestamp {
+            flags: 3,
+            modify: Some(0x6ad5cc47),
+            access: None,
+            create: None,
+        };
+        let expected = [
+            ExtraField::ExtendedTimestamp(timestamp),
+            ExtraField::Unknown(&TIMESTAMP_AND_UNIX[9..]),
+        ];
+        assert!(extra_subfields(&TIMESTAMP_AND_UNIX).eq(expected));
+    }
+
+    #[test]
+    fn subfields_stop_when_truncated() {
+        assert_eq!(1, extra_subfields(&TIMESTAMP_AND_UNIX[..20]).count());
+    }
 }
@@ -1,11 +1,14 @@
 use nom::{
     bytes::complete::tag, bytes::complete::take, combinator::map, combinator::map_parser,
-    number::complete::le_u16, number::complete::le_u32, IResult,
+    error::context, number::complete::le_u16, number::complete::le_u32, IResult,
 };
 
 use zipr_data::{
-    borrowed::{file::CompressedData, file::LocalFileEntry},
-    constants::LOCAL_FILE_HEADER_SIGNATURE,
+    borrowed::{
+        file::CompressedData,
+        file::{CentralDirectoryEntry, LocalFileEntry},
+    },
+    constants::{GENERAL_PURPOSE_DATA_DESCRIPTOR, LOCAL_FILE_HEADER_SIGNATURE},
     DosDate, DosTime,
 };
 
@@ -13,28 +16,100 @@ use super::{
     compression_method::parse_compression_method, extra_field::parse_extra_field, parse_version,
     zip_path::parse_zip_path,
 };
+use crate::error::{ZipParseError, LOCAL_FILE_HEADER};
 
-pub fn parse_local_file(input: &[u8]) -> IResult<&[u8], LocalFileEntry> {
-    let (input, _) = tag(LOCAL_FILE_HEADER_SIGNATURE)(input)?;
-    let (input, version_needed) = parse_version(input)?;
-    let (input, general_purpose) = le_u16(input)?;
-    let (input, compression_method) = parse_compression_method(input)?;
-    let (input, file_modification_time) = map(le_u16, DosTime::from_u16_unchecked)(input)?;
-    let (input, file_modification_date) = map(le_u16, DosDate::from_u16_unchecked)(input)?;
-    let (input, crc32) = le_u32(input)?;
-    let (input, compressed_size) = le_u32(input)?;
-    let (input, uncompressed_size) = le_u32(input)?;
-    let (input, file_name_length) = le_u16(input)?;
-    let (input, extra_field_length) = le_u16(input)?;
+/// Parses a local file header, and the compressed data after it
+pub fn parse_local_file<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
+    context(LOCAL_FILE_HEADER, local_file)(input)
+}
+
+/// Parses the local file the directory entry points to.
+/// When the local header left the crc and sizes for a data descriptor,
+/// the directory entry's are used instead, so the data can be read
+pub fn parse_local_file_for<'a, E: ZipParseError<'a>>(
+    directory: &CentralDirectoryEntry<'_>,
+) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
+    let (crc32, compressed_size, uncompressed_size) = (
+        directory.crc32,
+        directory.compressed_size,
+        directory.uncompressed_size,
+    );
+    move |input: &'a [u8]| {
+        let (input, mut entry) = parse_local_file(input)?;
+        let deferred = entry.general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0;
+        if !deferred || !entry.compressed_data.bytes().is_empty() {
+            return Ok((input, entry));
+        }
+        let (input, bytes) = context(
+            LOCAL_FILE_HEADER,
+            context("compressed data", take(compressed_size)),
+        )(input)?;
+        let method = entry.compressed_data.compression_method();
+        entry.compressed_data =
+            CompressedData::create_unchecked(uncompressed_size, method, crc32, bytes);
+        Ok((input, entry))
+    }
+}
 
-    let (input, file_name) = map_parser(take(file_name_length), parse_zip_path)(input)?;
+/// Parses a local file header without the compressed data after it.
+/// The entry's data is left empty, the compressed size is given alongside
+pub fn parse_local_header<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], (LocalFileEntry<'a>, u32), E> {
+    context(LOCAL_FILE_HEADER, local_header)(input)
+}
+
+fn local_file<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
+    let (input, (mut entry, compressed_size)) = local_header(input)?;
+    let (input, bytes) = context("compressed data", take(compressed_size))(input)?;
+    let data = entry.compressed_data;
+    entry.compressed_data = CompressedData::create_unchecked(
+        data.uncompressed_size(),
+        data.compression_method(),
+        data.crc32(),
+        bytes,
+    );
+    Ok((input, entry))
+}
 
-    let (input, extra_field) = map_parser(take(extra_field_length), parse_extra_field)(input)?;
+fn local_header<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], (LocalFileEntry<'a>, u32), E> {
+    let (input, _) = context("signature", tag(LOCAL_FILE_HEADER_SIGNATURE))(input)?;
+    let (input, version_needed) = context("version needed", parse_version)(input)?;
+    let (input, general_purpose) = context("general purpose flags", le_u16)(input)?;
+    let (input, compression_method) =
+        context("compression method", parse_compression_method)(input)?;
+    let (input, file_modification_time) = context(
+        "modification time",
+        map(le_u16, DosTime::from_u16_unchecked),
+    )(input)?;
+    let (input, file_modification_date) = context(
+        "modification date",
+        map(le_u16, DosDate::from_u16_unchecked),
+    )(input)?;
+    let (input, crc32) = context("crc32", le_u32)(input)?;
+    let (input, compressed_size) = context("compressed size", le_u32)(input)?;
+    let (input, uncompressed_size) = context("uncompressed size", le_u32)(input)?;
+    let (input, file_name_length) = context("file name length", le_u16)(input)?;
+    let (input, extra_field_length) = context("extra field length", le_u16)(input)?;
 
-    let (input, bytes) = take(compressed_size)(input)?;
+    let (input, file_name) = context(
+        "file name",
+        map_parser(take(file_name_length), parse_zip_path),
+    )(input)?;
+
+    let (input, extra_field) = context(
+        "extra field",
+        map_parser(take(extra_field_length), parse_extra_field),
+    )(input)?;
 
     let compressed_data =
-        CompressedData::create_unchecked(uncompressed_size, compression_method, crc32, bytes);
+        CompressedData::create_unchecked(uncompressed_size, compression_method, crc32, &[]);
     let result = LocalFileEntry {
         version_needed,
         general_purpose,
@@ -44,12 +119,13 @@ pub fn parse_local_file(input: &[u8]) -> IResult<&[u8], LocalFileEntry> {
         extra_field,
         compressed_data,
     };
-    Ok((input, result))
+    Ok((input, (result, compressed_size)))
 }
 
 #[cfg(test)]
 mod tests {
     use core::convert::{TryFrom, TryInto};
+    use nom::error::Error;
     use zipr_data::{
         borrowed::{extra_field::ExtraField, OEM437Str, ZipPath},
         CompressionMethod, DosDate, DosTime, HostCompatibility, Version, ZipSpecification,
@@ -60,7 +136,7 @@ mod tests {
     fn hello_world_store() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = &hello[0..0x2c];
-        let result = parse_local_file(data);
+        let result = parse_local_file::<Error<&[u8]>>(data);
         let compression_method = CompressionMethod::Stored;
         let uncompressed_size = 5;
         let crc32 = 980881731;
@@ -86,4 +162,16 @@ mod tests {
 
         assert_eq!(Ok((&[] as &[u8], expected)), result);
     }
+
+    #[test]
+    fn header_without_data() {
+        let hello = include_bytes!("../../../assets/hello_world_store.zip");
+        let (rest, (entry, compressed_size)) =
+            parse_local_header::<Error<&[u8]>>(&hello[0..0x2c]).unwrap();
+
+        assert_eq!(b"world", rest);
+        assert_eq!(5, compressed_size);
+        assert_eq!(5, entry.compressed_data.uncompressed_size());
+        assert!(entry.compressed_data.bytes().is_empty());
+    }
 }
@@ -6,7 +6,9 @@
 mod central_directory;
 mod compression_method;
 mod cp437_char;
+mod data_descriptor;
 mod end_of_central_directory;
+mod extended_timestamp;
 mod extra_field;
 mod local_file;
 mod ntfs;
@@ -15,9 +17,11 @@ mod zip_path;
 
 pub use central_directory::parse_directory_header;
 pub use compression_method::parse_compression_method;
+pub use data_descriptor::parse_data_descriptor;
 pub use end_of_central_directory::parse_end_of_central_directory;
-pub use extra_field::parse_extra_field;
-pub use local_file::parse_local_file;
+pub use extended_timestamp::parse_extended_timestamp;
+pub use extra_field::{extra_subfields, parse_extra_field, ExtraSubfields};
+pub use local_file::{parse_local_file, parse_local_file_for, parse_local_header};
 pub use ntfs::parse_ntfs;
 pub use version::parse_version;
 pub use zip_path::parse_zip_path;
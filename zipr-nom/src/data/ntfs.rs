@@ -1,25 +1,35 @@
-use core::convert::TryInto;
+use core::convert::TryFrom;
 
 use nom::{
-    bytes::complete::tag, combinator::map_res, number::complete::le_u32, number::complete::le_u64,
-    IResult,
+    bytes::complete::tag, combinator::map_opt, error::context, number::complete::le_u32,
+    number::complete::le_u64, IResult,
 };
 use zipr_data::{
     borrowed::extra_field::ntfs::NTFS, constants::EXTRA_FIELD_NTFS_HEADER, WinTimestamp,
 };
 
-pub fn parse_ntfs(input: &[u8]) -> IResult<&[u8], NTFS> {
-    let (input, _) = tag(EXTRA_FIELD_NTFS_HEADER)(input)?;
+use crate::error::{ZipParseError, NTFS_EXTRA_FIELD};
+
+fn timestamp<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], WinTimestamp, E> {
+    map_opt(le_u64, |x| WinTimestamp::try_from(x).ok())(input)
+}
+
+pub fn parse_ntfs<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], NTFS, E> {
+    context(NTFS_EXTRA_FIELD, ntfs)(input)
+}
+
+fn ntfs<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], NTFS, E> {
+    let (input, _) = context("header id", tag(EXTRA_FIELD_NTFS_HEADER))(input)?;
     // tsize is static currently
-    let (input, _tsize) = tag([0x20, 0x00])(input)?;
-    let (input, _reserved) = le_u32(input)?;
+    let (input, _tsize) = context("size", tag([0x20, 0x00]))(input)?;
+    let (input, _reserved) = context("reserved", le_u32)(input)?;
 
-    let (input, _tag1) = tag([0x1, 0x0])(input)?;
-    let (input, _size1) = tag([0x18, 0])(input)?;
+    let (input, _tag1) = context("attribute tag", tag([0x1, 0x0]))(input)?;
+    let (input, _size1) = context("attribute size", tag([0x18, 0]))(input)?;
 
-    let (input, mtime) = map_res(le_u64, TryInto::<WinTimestamp>::try_into)(input)?;
-    let (input, atime) = map_res(le_u64, TryInto::<WinTimestamp>::try_into)(input)?;
-    let (input, ctime) = map_res(le_u64, TryInto::<WinTimestamp>::try_into)(input)?;
+    let (input, mtime) = context("mtime", timestamp)(input)?;
+    let (input, atime) = context("atime", timestamp)(input)?;
+    let (input, ctime) = context("ctime", timestamp)(input)?;
 
     let result = NTFS {
         mtime,
@@ -32,6 +42,7 @@ pub fn parse_ntfs(input: &[u8]) -> IResult<&[u8], NTFS> {
 
 #[cfg(test)]
 mod tests {
+    use nom::error::Error;
 
     use core::convert::TryInto;
 
@@ -41,7 +52,7 @@ mod tests {
     fn hello_world_store() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = &hello[0x63..0x87];
-        let result = parse_ntfs(data);
+        let result = parse_ntfs::<Error<&[u8]>>(data);
         let expected = NTFS {
             atime: 132514708162669827.try_into().unwrap(),
             mtime: 132514707831351075.try_into().unwrap(),
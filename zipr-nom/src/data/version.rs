@@ -1,8 +1,10 @@
-use core::convert::TryInto;
+use core::convert::TryFrom;
 
-use nom::{number::complete::le_u8, IResult};
+use nom::{combinator::map_opt, number::complete::le_u8, IResult};
 use zipr_data::{HostCompatibility, Version, ZipSpecification};
 
+use crate::error::ZipParseError;
+
 fn hostcompat(input: u8) -> HostCompatibility {
     match input {
         0 => HostCompatibility::MSDOS,
@@ -28,14 +30,11 @@ fn hostcompat(input: u8) -> HostCompatibility {
         x => HostCompatibility::Other(x),
     }
 }
-/// Uses alternatives to try and pass the extra field.
-/// Will return one of the datatypes, or fail
-pub fn parse_version(input: &[u8]) -> IResult<&[u8], Version> {
-    let (input, version) = le_u8(input)?;
+/// Parses the version, and the host it was made on.
+/// Fails if the version is below 1.0
+pub fn parse_version<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], Version, E> {
+    let (input, spec) = map_opt(le_u8, |x| ZipSpecification::try_from(x).ok())(input)?;
     let (input, host) = le_u8(input)?;
-    let major = (version / 10).try_into().unwrap();
-    let minor = (version % 10).try_into().unwrap();
-    let spec = ZipSpecification { major, minor };
     let host = hostcompat(host);
     let version = Version { host, spec };
 
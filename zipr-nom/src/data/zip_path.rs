@@ -1,23 +1,21 @@
-use core::convert::TryFrom;
-
 use nom::{
     bytes::complete::take,
-    combinator::{map_parser, map_res},
+    combinator::{map, map_parser},
     IResult,
 };
-use zipr_data::borrowed::{OEM437Str, ZipPath, ZipPathError};
+use zipr_data::borrowed::ZipPath;
 
 use super::cp437_char::parse_cp437_chars;
+use crate::error::ZipParseError;
 
-pub fn parse_zip_path<'a>(input: &'a [u8]) -> IResult<&[u8], ZipPath<'a>> {
-    let to_path = |input: OEM437Str<'a>| -> Result<ZipPath<'a>, ZipPathError> {
-        let result = ZipPath::try_from(input)?;
-        Ok(result)
-    };
-
+/// Names are kept as found, even if they break the zip path rules,
+/// so archives from older tools still parse. See ZipPath::validate
+pub fn parse_zip_path<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ZipPath<'a>, E> {
     let len = input.len();
     let asciistr = map_parser(take(len), parse_cp437_chars);
-    let mut parser = map_res(asciistr, to_path);
+    let mut parser = map(asciistr, ZipPath::from_unchecked);
     let (input, file_name) = parser(input)?;
     Ok((input, file_name))
 }
@@ -0,0 +1,246 @@
+//! Errors for the parsers
+//!
+//! The parsers are generic over the nom error type, so any error
+//! implementing ParseError and ContextError can be used, eg VerboseError.
+//! Each field is wrapped in a nom context with its name, and each
+//! record with one of the record contexts below.
+//!
+//! ParseFailure is a cheap error that keeps the innermost failure,
+//! and ZipError places it in the file, for reporting
+use core::fmt::Display;
+
+#[cfg(feature = "alloc")]
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::{
+    error::{ContextError, ErrorKind, ParseError},
+    Offset,
+};
+
+pub const END_OF_CENTRAL_DIRECTORY: &str = "end of central directory";
+pub const CENTRAL_DIRECTORY_HEADER: &str = "central directory header";
+pub const LOCAL_FILE_HEADER: &str = "local file header";
+pub const NTFS_EXTRA_FIELD: &str = "ntfs extra field";
+pub const EXTENDED_TIMESTAMP_EXTRA_FIELD: &str = "extended timestamp extra field";
+pub const DATA_DESCRIPTOR: &str = "data descriptor";
+
+const RECORDS: [&str; 6] = [
+    END_OF_CENTRAL_DIRECTORY,
+    CENTRAL_DIRECTORY_HEADER,
+    LOCAL_FILE_HEADER,
+    NTFS_EXTRA_FIELD,
+    EXTENDED_TIMESTAMP_EXTRA_FIELD,
+    DATA_DESCRIPTOR,
+];
+
+/// The error types the parsers can produce
+pub trait ZipParseError<'a>: ParseError<&'a [u8]> + ContextError<&'a [u8]> {}
+
+impl<'a, T> ZipParseError<'a> for T where T: ParseError<&'a [u8]> + ContextError<&'a [u8]> {}
+
+/// The innermost failure, with the field and record it was in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ParseFailure<'a> {
+    /// The input where parsing failed
+    pub input: &'a [u8],
+    pub kind: ErrorKind,
+    pub field: Option<&'static str>,
+    pub record: Option<&'static str>,
+}
+
+impl<'a> ParseError<&'a [u8]> for ParseFailure<'a> {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        ParseFailure {
+            input,
+            kind,
+            field: None,
+            record: None,
+        }
+    }
+
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> ContextError<&'a [u8]> for ParseFailure<'a> {
+    fn add_context(_: &'a [u8], context: &'static str, mut other: Self) -> Self {
+        if RECORDS.contains(&context) {
+            other.record = other.record.or(Some(context));
+        } else {
+            other.field = other.field.or(Some(context));
+        }
+        other
+    }
+}
+
+/// The record that failed to parse
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Record {
+    EndOfCentralDirectory,
+    /// The nth header in the central directory
+    CentralDirectoryHeader(usize),
+    /// The local header for the nth entry
+    LocalFileHeader(usize),
+    /// An extra field, by its header id
+    ExtraField(u16),
+    /// The data descriptor for the nth entry
+    DataDescriptor(usize),
+    /// Parsed on its own, so the position in the archive isn't known
+    Unknown(&'static str),
+}
+
+impl Record {
+    /// Extra fields and data descriptors are parsed along with a header,
+    /// so are more specific if the failure was in one
+    fn within(self, context: Option<&'static str>) -> Self {
+        match (context, self) {
+            (Some(NTFS_EXTRA_FIELD), _) => Record::ExtraField(0x000a),
+            (Some(EXTENDED_TIMESTAMP_EXTRA_FIELD), _) => Record::ExtraField(0x5455),
+            (Some(DATA_DESCRIPTOR), Record::LocalFileHeader(index)) => {
+                Record::DataDescriptor(index)
+            }
+            _ => self,
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Record::EndOfCentralDirectory => f.write_str(END_OF_CENTRAL_DIRECTORY),
+            Record::CentralDirectoryHeader(index) => {
+                write!(f, "{} {}", CENTRAL_DIRECTORY_HEADER, index)
+            }
+            Record::LocalFileHeader(index) => {
+                write!(f, "{} for entry {}", LOCAL_FILE_HEADER, index)
+            }
+            Record::ExtraField(id) => write!(f, "extra field 0x{:04x}", id),
+            Record::DataDescriptor(index) => write!(f, "{} for entry {}", DATA_DESCRIPTOR, index),
+            Record::Unknown(context) => f.write_str(context),
+        }
+    }
+}
+
+/// A parse failure placed in the file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ZipError {
+    /// From the start of the file
+    pub offset: u64,
+    pub record: Record,
+    pub field: Option<&'static str>,
+    pub kind: ErrorKind,
+}
+
+impl ZipError {
+    /// Locates the failure, which must be within full_file
+    pub fn new(full_file: &[u8], record: Record, failure: ParseFailure<'_>) -> Self {
+        ZipError {
+            offset: full_file.offset(failure.input) as u64,
+            record: record.within(failure.record),
+            field: failure.field,
+            kind: failure.kind,
+        }
+    }
+
+    /// Locates the innermost failure of a verbose error
+    #[cfg(feature = "alloc")]
+    pub fn from_verbose(full_file: &[u8], record: Record, error: VerboseError<&[u8]>) -> Self {
+        let mut failure = match error.errors.first() {
+            Some((input, VerboseErrorKind::Nom(kind))) => {
+                ParseFailure::from_error_kind(input, *kind)
+            }
+            Some((input, _)) => ParseFailure::from_error_kind(input, ErrorKind::Fail),
+            None => ParseFailure::from_error_kind(full_file, ErrorKind::Fail),
+        };
+        for (input, kind) in error.errors.iter() {
+            if let VerboseErrorKind::Context(context) = kind {
+                failure = ParseFailure::add_context(input, context, failure);
+            }
+        }
+        ZipError::new(full_file, record, failure)
+    }
+
+    /// Moves the failure along, for when full_file
+    /// started part way into the archive
+    pub fn offset_by(mut self, start: u64) -> Self {
+        self.offset = self.offset.saturating_add(start);
+        self
+    }
+
+    /// For a failure outside of any record, eg an offset past the end
+    pub fn out_of_range(offset: u64, record: Record) -> Self {
+        ZipError {
+            offset,
+            record,
+            field: None,
+            kind: ErrorKind::Eof,
+        }
+    }
+}
+
+impl Display for ZipError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let reason = match self.kind {
+            ErrorKind::Eof | ErrorKind::Complete => "ends early",
+            ErrorKind::Tag => "doesn't match",
+            _ => "is invalid",
+        };
+        match self.field {
+            Some(field) => write!(
+                f,
+                "{} at 0x{:x}: {} {} ({:?})",
+                self.record, self.offset, field, reason, self.kind
+            ),
+            None => write!(
+                f,
+                "{} at 0x{:x} {} ({:?})",
+                self.record, self.offset, reason, self.kind
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::{error::context, number::complete::le_u32, Finish, IResult};
+
+    use super::*;
+
+    fn record<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], u32, E> {
+        context(LOCAL_FILE_HEADER, context("crc32", le_u32))(input)
+    }
+
+    #[test]
+    fn failure_keeps_field_and_record() {
+        let input = [1, 2, 3];
+        let failure = record::<ParseFailure>(&input[1..]).finish().unwrap_err();
+        let error = ZipError::new(&input, Record::LocalFileHeader(2), failure);
+
+        assert_eq!(1, error.offset);
+        assert_eq!(Some("crc32"), error.field);
+        assert_eq!(
+            "local file header for entry 2 at 0x1: crc32 ends early (Eof)",
+            alloc::format!("{}", error)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn from_verbose() {
+        let input = [1, 2, 3];
+        let verbose = record::<VerboseError<&[u8]>>(&input[1..])
+            .finish()
+            .unwrap_err();
+        let error = ZipError::from_verbose(&input, Record::CentralDirectoryHeader(0), verbose);
+
+        assert_eq!(
+            ZipError {
+                offset: 1,
+                record: Record::CentralDirectoryHeader(0),
+                field: Some("crc32"),
+                kind: ErrorKind::Eof
+            },
+            error
+        );
+    }
+}
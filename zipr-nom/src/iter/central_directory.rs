@@ -1,21 +1,43 @@
-use core::{cmp::min, iter::Iterator};
+use core::{fmt::Display, iter::Iterator};
 use nom::Finish;
-use zipr_data::{
-    borrowed::file::CentralDirectoryEntry,
-    constants::{self},
+
+use crate::{
+    error::{ParseFailure, Record, ZipError},
+    prefix_length,
+    slice::range,
 };
+use zipr_data::borrowed::file::CentralDirectoryEntry;
 
 #[derive(Debug)]
 pub enum CentralDirectoryIteratorError {
-    NoEndOfDirectory(
-        nom::error::Error<[u8; constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE as usize]>,
-    ),
-    InvalidDirectoryEntry(
-        nom::error::Error<[u8; constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as usize]>,
-    ),
+    NoEndOfDirectory(ZipError),
+    InvalidDirectoryEntry(ZipError),
+    /// The end of central directory points outside of the file
+    DirectoryOutOfRange {
+        offset: u32,
+        size: u32,
+    },
+}
+
+impl Display for CentralDirectoryIteratorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CentralDirectoryIteratorError::NoEndOfDirectory(e)
+            | CentralDirectoryIteratorError::InvalidDirectoryEntry(e) => write!(f, "{}", e),
+            CentralDirectoryIteratorError::DirectoryOutOfRange { offset, size } => write!(
+                f,
+                "central directory at 0x{:x} (0x{:x} bytes) is outside the file",
+                offset, size
+            ),
+        }
+    }
 }
+
 pub struct CentralDirectoryIterator<'a> {
+    file: &'a [u8],
     unprocessed: &'a [u8],
+    prefix: usize,
+    index: usize,
     state: State,
 }
 
@@ -24,47 +46,30 @@ enum State {
     Errored,
     Entries,
 }
-pub fn iterate_central_directory(file: &[u8]) -> CentralDirectoryIterator {
+pub fn iterate_central_directory(file: &[u8]) -> CentralDirectoryIterator<'_> {
     let state = State::Initialized;
     CentralDirectoryIterator {
+        file,
         unprocessed: file,
+        prefix: 0,
+        index: 0,
         state,
     }
 }
-fn copy_slice_safe(a: &mut [u8], b: &[u8]) {
-    let length = min(a.len(), b.len());
-    let dest = &mut a[..length];
-    let src = &b[..length];
-    dest.copy_from_slice(src);
-}
-
-fn invalid_entry(error: nom::error::Error<&'_ [u8]>) -> CentralDirectoryIteratorError {
-    let mut dest: [u8; constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as usize] =
-        [0; constants::CENTRAL_DIRECTORY_HEAD_MIN_LENGTH as usize];
-    copy_slice_safe(&mut dest, error.input);
-    let error = nom::error::Error::new(dest, error.code);
-    CentralDirectoryIteratorError::InvalidDirectoryEntry(error)
-}
-
-fn invalid_eocd(error: nom::error::Error<&'_ [u8]>) -> CentralDirectoryIteratorError {
-    let mut dest: [u8; constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE as usize] =
-        [0; constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE as usize];
-    copy_slice_safe(&mut dest, error.input);
-    let error = nom::error::Error::new(dest, error.code);
-    CentralDirectoryIteratorError::NoEndOfDirectory(error)
-}
 
 fn next_entry<'a>(
     it: &mut CentralDirectoryIterator<'a>,
 ) -> Result<CentralDirectoryEntry<'a>, CentralDirectoryIteratorError> {
-    match crate::data::parse_directory_header(it.unprocessed).finish() {
+    match crate::data::parse_directory_header::<ParseFailure>(it.unprocessed).finish() {
         Err(e) => {
             it.state = State::Errored;
-            let error = invalid_entry(e);
-            Err(error)
+            let record = Record::CentralDirectoryHeader(it.index);
+            let error = ZipError::new(it.file, record, e);
+            Err(CentralDirectoryIteratorError::InvalidDirectoryEntry(error))
         }
         Ok((rem, dir)) => {
             it.unprocessed = rem;
+            it.index += 1;
             Ok(dir)
         }
     }
@@ -72,30 +77,49 @@ fn next_entry<'a>(
 
 fn from_initialize<'a>(
     it: &mut CentralDirectoryIterator<'a>,
-) -> Result<CentralDirectoryEntry<'a>, CentralDirectoryIteratorError> {
-    match crate::search::find_end_of_central_directory(it.unprocessed).finish() {
+) -> Option<Result<CentralDirectoryEntry<'a>, CentralDirectoryIteratorError>> {
+    let eocd = match crate::search::find_end_of_central_directory::<ParseFailure>(it.file).finish()
+    {
         Err(e) => {
             it.state = State::Errored;
-            let error = invalid_eocd(e);
-            Err(error)
+            let error = ZipError::new(it.file, Record::EndOfCentralDirectory, e);
+            return Some(Err(CentralDirectoryIteratorError::NoEndOfDirectory(error)));
         }
-        Ok((_, eocd)) => {
-            let start = eocd.offset_start_directory as usize;
-            let end = start + eocd.size_of_directory as usize;
-            it.unprocessed = &it.unprocessed[start..end];
+        Ok((_, eocd)) => eocd,
+    };
+    it.prefix = prefix_length(it.file, &eocd);
+    let (offset, size) = (eocd.offset_start_directory, eocd.size_of_directory);
+    match range(it.file, it.prefix + offset as usize, size as usize) {
+        None => {
+            it.state = State::Errored;
+            Some(Err(CentralDirectoryIteratorError::DirectoryOutOfRange {
+                offset,
+                size,
+            }))
+        }
+        Some(directory) => {
+            it.unprocessed = directory;
             it.state = State::Entries;
-            next_entry(it)
+            it.next()
         }
     }
 }
 
+impl<'a> CentralDirectoryIterator<'a> {
+    /// The bytes before the archive, see prefix_length.
+    /// Only known once the first entry has been read
+    pub fn prefix(&self) -> &'a [u8] {
+        &self.file[..self.prefix]
+    }
+}
+
 impl<'a> Iterator for CentralDirectoryIterator<'a> {
     type Item = Result<CentralDirectoryEntry<'a>, CentralDirectoryIteratorError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.state {
             State::Errored => None,
-            State::Initialized => Some(from_initialize(self)),
+            State::Initialized => from_initialize(self),
             State::Entries => {
                 if self.unprocessed.is_empty() {
                     None
@@ -106,3 +130,58 @@ impl<'a> Iterator for CentralDirectoryIterator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zipr_data::constants;
+
+    use super::*;
+
+    #[test]
+    fn directory_out_of_range() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        let eocd = input.len() - constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE;
+        input[eocd + 16..eocd + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut entries = iterate_central_directory(&input);
+
+        assert!(matches!(
+            entries.next(),
+            Some(Err(CentralDirectoryIteratorError::DirectoryOutOfRange {
+                offset: u32::MAX,
+                size: 91
+            }))
+        ));
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn too_short() {
+        let input = include_bytes!("../../../assets/hello_world_store.zip");
+        let mut entries = iterate_central_directory(&input[..10]);
+
+        assert!(matches!(
+            entries.next(),
+            Some(Err(CentralDirectoryIteratorError::NoEndOfDirectory(_)))
+        ));
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn invalid_entry_located() {
+        let mut input = include_bytes!("../../../assets/two_files_store.zip").to_vec();
+        // The signature of the second header
+        let second = 0x59 + 46 + 9 + 36;
+        input[second] = 0;
+        let mut entries = iterate_central_directory(&input);
+
+        assert!(entries.next().unwrap().is_ok());
+        match entries.next() {
+            Some(Err(CentralDirectoryIteratorError::InvalidDirectoryEntry(e))) => {
+                assert_eq!(second as u64, e.offset);
+                assert_eq!(Record::CentralDirectoryHeader(1), e.record);
+                assert_eq!(Some("signature"), e.field);
+            }
+            _ => panic!("expected an invalid entry"),
+        }
+    }
+}
@@ -1,41 +1,54 @@
-use core::cmp::min;
+use core::fmt::Display;
 
 use super::CentralDirectoryIteratorError;
-use crate::data::parse_local_file;
-use nom::Finish;
-use zipr_data::{
-    borrowed::{file::CentralDirectoryEntry, ZipEntry},
-    constants,
+use crate::{
+    data::parse_local_file_for,
+    error::{ParseFailure, Record, ZipError},
+    slice::from_offset,
 };
-use zipr_domain::make_zip_entry;
+use nom::Finish;
+use zipr_data::borrowed::{file::CentralDirectoryEntry, ZipEntry};
+use zipr_domain::{make_zip_entry, ConsistencyPolicy, Discrepancy};
 
 /// An iterator for zip entries in a set of bytes
 /// Will load them lazily, so you can only pull out as much as you need
 pub struct ZipEntryIterator<'a> {
     file: &'a [u8],
     directories: super::CentralDirectoryIterator<'a>,
+    index: usize,
+    policy: ConsistencyPolicy,
 }
 
 ///Enum for the ways we can fail to load zip file entries
 #[derive(Debug)]
 pub enum ZipEntryIteratorError {
     DirectoryError(CentralDirectoryIteratorError),
-    LocalFileError(nom::error::Error<[u8; constants::LOCAL_FILE_MIN_LENGTH as usize]>),
-}
-
-fn copy_slice_safe(a: &mut [u8], b: &[u8]) {
-    let length = min(a.len(), b.len());
-    let dest = &mut a[..length];
-    let src = &b[..length];
-    dest.copy_from_slice(src);
+    LocalFileError(ZipError),
+    /// A central directory entry points outside of the file
+    LocalFileOutOfRange {
+        offset: u32,
+    },
+    /// The local file doesn't agree with its central directory entry
+    Inconsistent {
+        offset: u32,
+        discrepancy: Discrepancy,
+    },
 }
 
-fn invalid_entry(error: nom::error::Error<&'_ [u8]>) -> ZipEntryIteratorError {
-    let mut dest: [u8; constants::LOCAL_FILE_MIN_LENGTH as usize] =
-        [0; constants::LOCAL_FILE_MIN_LENGTH as usize];
-    copy_slice_safe(&mut dest, error.input);
-    let error = nom::error::Error::new(dest, error.code);
-    ZipEntryIteratorError::LocalFileError(error)
+impl Display for ZipEntryIteratorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ZipEntryIteratorError::DirectoryError(e) => write!(f, "{}", e),
+            ZipEntryIteratorError::LocalFileError(e) => write!(f, "{}", e),
+            ZipEntryIteratorError::LocalFileOutOfRange { offset } => {
+                write!(f, "local file header at 0x{:x} is outside the file", offset)
+            }
+            ZipEntryIteratorError::Inconsistent {
+                offset,
+                discrepancy,
+            } => write!(f, "local file header at 0x{:x}: {}", offset, discrepancy),
+        }
+    }
 }
 
 /// Creates an iterator over the zip entries
@@ -46,18 +59,49 @@ pub fn zip_entry_iter(input: &[u8]) -> ZipEntryIterator<'_> {
     ZipEntryIterator {
         file: input,
         directories,
+        index: 0,
+        policy: ConsistencyPolicy::default(),
     }
 }
 
+impl<'a> ZipEntryIterator<'a> {
+    /// Sets how strictly local files must match the central directory
+    pub fn with_policy(mut self, policy: ConsistencyPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The bytes before the archive, eg a self extracting stub.
+    /// Only known once the first entry has been read
+    pub fn prefix(&self) -> &'a [u8] {
+        self.directories.prefix()
+    }
+}
+
+/// The archive is the input without its prefix, which offsets are from
 fn next_entry<'a>(
     input: &'a [u8],
+    archive: &'a [u8],
+    index: usize,
     d: Result<CentralDirectoryEntry<'a>, CentralDirectoryIteratorError>,
+    policy: ConsistencyPolicy,
 ) -> Result<ZipEntry<'a>, ZipEntryIteratorError> {
     let directory = d.map_err(ZipEntryIteratorError::DirectoryError)?;
-    let start = directory.relative_offset as usize;
-    let end = input.len();
-    let input = &input[start..end];
-    let (_, entry) = parse_local_file(input).finish().map_err(invalid_entry)?;
+    let offset = directory.relative_offset;
+    let local = from_offset(archive, offset as usize)
+        .ok_or(ZipEntryIteratorError::LocalFileOutOfRange { offset })?;
+    let (_, entry) = parse_local_file_for::<ParseFailure>(&directory)(local)
+        .finish()
+        .map_err(|e| {
+            let error = ZipError::new(input, Record::LocalFileHeader(index), e);
+            ZipEntryIteratorError::LocalFileError(error)
+        })?;
+    policy.check(&directory, &entry).map_err(|discrepancy| {
+        ZipEntryIteratorError::Inconsistent {
+            offset: directory.relative_offset,
+            discrepancy,
+        }
+    })?;
     let zip = make_zip_entry(&directory, &entry);
     Ok(zip)
 }
@@ -66,9 +110,75 @@ impl<'a> core::iter::Iterator for ZipEntryIterator<'a> {
     type Item = Result<ZipEntry<'a>, ZipEntryIteratorError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let directories = &mut self.directories;
-        let file = self.file;
-        let next = directories.map(|e| next_entry(file, e)).next()?;
+        let directory = self.directories.next()?;
+        let (file, index, policy) = (self.file, self.index, self.policy);
+        let archive = &file[self.directories.prefix().len()..];
+        let next = next_entry(file, archive, index, directory, policy);
+        self.index += 1;
         Some(next)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zipr_data::constants;
+
+    use super::*;
+
+    #[test]
+    fn local_file_out_of_range() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        // The relative offset of the only central directory entry
+        input[0x2c + 42..0x2c + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut entries = zip_entry_iter(&input);
+
+        assert!(matches!(
+            entries.next(),
+            Some(Err(ZipEntryIteratorError::LocalFileOutOfRange {
+                offset: u32::MAX
+            }))
+        ));
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn prefixed_archive() {
+        let mut input = b"#!/bin/sh\n".to_vec();
+        input.extend_from_slice(include_bytes!("../../../assets/two_files_store.zip"));
+        let mut entries = zip_entry_iter(&input);
+
+        let first = entries.next().unwrap().unwrap();
+        assert_eq!(b"world", first.compressed_data.bytes());
+        assert!(entries.next().unwrap().is_ok());
+        assert!(entries.next().is_none());
+        assert_eq!(b"#!/bin/sh\n", entries.prefix());
+    }
+
+    #[test]
+    fn invalid_local_file_located() {
+        let mut input = include_bytes!("../../../assets/two_files_store.zip").to_vec();
+        // The crc32 of the second local file, now cut short by the directory
+        let second = input
+            .windows(4)
+            .rposition(|x| x == constants::LOCAL_FILE_HEADER_SIGNATURE)
+            .unwrap();
+        input[second + 18..second + 22].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut entries = zip_entry_iter(&input);
+
+        assert!(entries.next().unwrap().is_ok());
+        match entries.next() {
+            Some(Err(ZipEntryIteratorError::LocalFileError(e))) => {
+                assert_eq!(Record::LocalFileHeader(1), e.record);
+                assert_eq!(Some("compressed data"), e.field);
+                assert_eq!(
+                    alloc::format!(
+                        "local file header for entry 1 at 0x{:x}: compressed data ends early (Eof)",
+                        e.offset
+                    ),
+                    alloc::format!("{}", e)
+                );
+            }
+            _ => panic!("expected an invalid local file"),
+        }
+    }
+}
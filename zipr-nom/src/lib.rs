@@ -1,7 +1,19 @@
 #![no_std]
+//! Parsers for zip files, using nom
+//!
+//! The parsers and iterators don't allocate. The helpers that collect
+//! into a Vec, and recovery, need the alloc feature (on by default)
+#[cfg(any(feature = "alloc", test))]
 extern crate alloc;
+#[cfg(feature = "alloc")]
+pub mod combinator;
 pub mod data;
+pub mod error;
 pub mod iter;
+#[cfg(feature = "alloc")]
+pub mod recover;
 mod search;
+mod slice;
+pub mod stream;
 
 pub use search::*;
@@ -0,0 +1,294 @@
+//! Recovering entries from an archive whose central directory is lost,
+//! eg a truncated download or a partially overwritten file
+//!
+//! Local headers are searched for from the start of the input. A candidate
+//! is only accepted once its data decompresses and matches its crc32 and
+//! size, so signatures that happen to appear in other data are skipped
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use memchr::memmem;
+use nom::{error::context, Err};
+use zipr_compression::{DecompressError, DecompressStream};
+use zipr_data::{
+    borrowed::file::LocalFileEntry,
+    constants::{
+        CENTRAL_DIRECTORY_HEADER_SIGNATURE, END_OF_CENTRAL_DIRECTORY_HEADER,
+        LOCAL_FILE_HEADER_SIGNATURE, SPANNING_SIGNATURE,
+    },
+};
+use zipr_domain::Extent;
+
+use crate::{
+    error::{ParseFailure, Record, ZipError, LOCAL_FILE_HEADER},
+    stream::local_file,
+};
+
+const BUFFER_SIZE: usize = 1024;
+
+/// Why a local header signature wasn't recovered as an entry
+#[derive(Debug)]
+pub enum Rejection {
+    /// The header isn't valid
+    Header(ZipError),
+    /// The header or data runs past the end of the input
+    Truncated,
+    /// The data doesn't decompress, or doesn't match its crc32 and size
+    Data(DecompressError),
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Rejection::Header(e) => write!(f, "{}", e),
+            Rejection::Truncated => f.write_str("the entry runs past the end of the file"),
+            Rejection::Data(e) => write!(f, "corrupt data ({:?})", e),
+        }
+    }
+}
+
+/// Something found while scanning that couldn't be recovered
+#[derive(Debug)]
+pub enum Damage {
+    /// A local header signature that wasn't the start of an entry
+    Rejected { offset: u64, rejection: Rejection },
+    /// Bytes that aren't part of any recovered entry
+    Unreadable(Extent),
+}
+
+impl Display for Damage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Damage::Rejected { offset, rejection } => {
+                write!(f, "skipped local header at 0x{:x}: {}", offset, rejection)
+            }
+            Damage::Unreadable(extent) => {
+                write!(
+                    f,
+                    "unreadable bytes at 0x{:x}-0x{:x}",
+                    extent.start, extent.end
+                )
+            }
+        }
+    }
+}
+
+/// An entry recovered from its local header
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RecoveredEntry<'a> {
+    /// Where the local header starts
+    pub offset: u64,
+    pub entry: LocalFileEntry<'a>,
+}
+
+/// The entries that could be recovered, and what couldn't be
+#[derive(Debug, Default)]
+pub struct Recovery<'a> {
+    pub entries: Vec<RecoveredEntry<'a>>,
+    pub damage: Vec<Damage>,
+}
+
+/// Lazily scans the input for entries, in file order
+pub struct RecoveryScanner<'a> {
+    input: &'a [u8],
+    /// Where the search for the next signature starts
+    position: usize,
+    /// The end of the last recovered entry
+    recovered_to: usize,
+    /// Found after unreadable bytes, so given on the next call
+    pending: Option<RecoveredEntry<'a>>,
+    done: bool,
+}
+
+/// Scans the input for local headers, ignoring the central directory.
+/// Each item is a recovered entry, or damage found on the way
+pub fn recovery_scan(input: &[u8]) -> RecoveryScanner<'_> {
+    // The first segment of a split archive starts with a signature, not damage
+    let start = match input.starts_with(&SPANNING_SIGNATURE) {
+        true => SPANNING_SIGNATURE.len(),
+        false => 0,
+    };
+    RecoveryScanner {
+        input,
+        position: start,
+        recovered_to: start,
+        pending: None,
+        done: false,
+    }
+}
+
+/// Recovers every entry it can from the input, see recovery_scan
+pub fn recover(input: &[u8]) -> Recovery<'_> {
+    let mut recovery = Recovery::default();
+    for result in recovery_scan(input) {
+        match result {
+            Ok(entry) => recovery.entries.push(entry),
+            Err(damage) => recovery.damage.push(damage),
+        }
+    }
+    recovery
+}
+
+/// Decompresses the data, checking its crc32 and size
+fn verify(entry: &LocalFileEntry<'_>) -> Result<(), DecompressError> {
+    let mut stream = DecompressStream::new(entry.compressed_data);
+    let mut buffer = [0u8; BUFFER_SIZE];
+    while stream.read(&mut buffer)? != 0 {}
+    Ok(())
+}
+
+impl<'a> RecoveryScanner<'a> {
+    /// The entry starting at the position, and its length
+    fn candidate(&self, start: usize) -> Result<(usize, LocalFileEntry<'a>), Rejection> {
+        let input = &self.input[start..];
+        let (rest, entry) = match context(LOCAL_FILE_HEADER, local_file::<ParseFailure>)(input) {
+            Ok(result) => result,
+            Err(Err::Incomplete(_)) => return Err(Rejection::Truncated),
+            Err(Err::Error(e) | Err::Failure(e)) => {
+                let record = Record::Unknown(LOCAL_FILE_HEADER);
+                return Err(Rejection::Header(ZipError::new(self.input, record, e)));
+            }
+        };
+        verify(&entry).map_err(Rejection::Data)?;
+        Ok((input.len() - rest.len(), entry))
+    }
+
+    /// The bytes since the last recovered entry, if there are any
+    fn unreadable(&self, end: usize) -> Option<Damage> {
+        let extent = Extent::new(self.recovered_to as u64, end as u64);
+        match extent.is_empty() {
+            true => None,
+            false => Some(Damage::Unreadable(extent)),
+        }
+    }
+
+    /// Whether the rest of the input is the central directory,
+    /// which isn't needed but isn't damage either
+    fn at_directory(&self) -> bool {
+        let rest = &self.input[self.recovered_to..];
+        rest.starts_with(&CENTRAL_DIRECTORY_HEADER_SIGNATURE)
+            || rest.starts_with(&END_OF_CENTRAL_DIRECTORY_HEADER)
+    }
+}
+
+impl<'a> Iterator for RecoveryScanner<'a> {
+    type Item = Result<RecoveredEntry<'a>, Damage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.pending.take() {
+            return Some(Ok(entry));
+        }
+        if self.done {
+            return None;
+        }
+        let signature = memmem::find(&self.input[self.position..], &LOCAL_FILE_HEADER_SIGNATURE);
+        let start = match signature {
+            Some(found) => self.position + found,
+            None => {
+                self.done = true;
+                if self.at_directory() {
+                    return None;
+                }
+                return self.unreadable(self.input.len()).map(Err);
+            }
+        };
+        match self.candidate(start) {
+            Err(rejection) => {
+                self.position = start + LOCAL_FILE_HEADER_SIGNATURE.len();
+                Some(Err(Damage::Rejected {
+                    offset: start as u64,
+                    rejection,
+                }))
+            }
+            Ok((length, entry)) => {
+                let recovered = RecoveredEntry {
+                    offset: start as u64,
+                    entry,
+                };
+                let unreadable = self.unreadable(start);
+                self.position = start + length;
+                self.recovered_to = self.position;
+                match unreadable {
+                    Some(damage) => {
+                        self.pending = Some(recovered);
+                        Some(Err(damage))
+                    }
+                    None => Some(Ok(recovered)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intact_archive() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let recovery = recover(input);
+
+        assert_eq!(2, recovery.entries.len());
+        assert_eq!(44, recovery.entries[1].offset);
+        assert!(recovery.damage.is_empty(), "{:?}", recovery.damage);
+    }
+
+    #[test]
+    fn split_segments_joined() {
+        let mut input = include_bytes!("../../assets/split.z01").to_vec();
+        input.extend_from_slice(include_bytes!("../../assets/split.zip"));
+        let recovery = recover(&input);
+
+        assert_eq!(3, recovery.entries.len());
+        assert_eq!(4, recovery.entries[0].offset);
+        assert!(recovery.damage.is_empty(), "{:?}", recovery.damage);
+    }
+
+    #[test]
+    fn truncated_directory() {
+        let input = include_bytes!("../../assets/data_descriptor.zip");
+        // Cut part way into the second entry's data
+        let recovery = recover(&input[..0x70]);
+
+        assert_eq!(1, recovery.entries.len());
+        assert_eq!(b"hello", recovery.entries[0].entry.compressed_data.bytes());
+        assert!(matches!(
+            recovery.damage[..],
+            [
+                Damage::Rejected {
+                    rejection: Rejection::Truncated,
+                    ..
+                },
+                Damage::Unreadable(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn garbage_and_corrupt_data_skipped() {
+        let mut input = b"garbagePK\x03\x04".to_vec();
+        input.extend_from_slice(include_bytes!("../../assets/two_files_store.zip"));
+        // The first entry's data
+        input[11 + 39] ^= 0xff;
+        let recovery = recover(&input);
+
+        assert_eq!(1, recovery.entries.len());
+        assert_eq!(11 + 44, recovery.entries[0].offset);
+        assert!(matches!(
+            recovery.damage[..],
+            [
+                // Read as a header, its sizes run past the end
+                Damage::Rejected {
+                    offset: 7,
+                    rejection: Rejection::Truncated
+                },
+                Damage::Rejected {
+                    offset: 11,
+                    rejection: Rejection::Data(DecompressError::InvalidCrc(..))
+                },
+                Damage::Unreadable(Extent { start: 0, end: 55 })
+            ]
+        ));
+    }
+}
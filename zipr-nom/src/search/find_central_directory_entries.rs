@@ -1,40 +1,98 @@
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
-use nom::{
-    combinator::{eof, iterator},
-    IResult,
-};
+use core::marker::PhantomData;
+use nom::IResult;
 use zipr_data::borrowed::file::CentralDirectoryEntry;
 
-use crate::data::parse_directory_header;
+use crate::{
+    data::parse_directory_header,
+    error::ZipParseError,
+    slice::{out_of_range, range},
+};
+
+use super::{find_end_of_central_directory::find_end_of_central_directory, Segments};
+
+/// Lazily parses the headers of a central directory,
+/// stopping after the first that fails
+pub struct CentralDirectoryEntries<'a, E> {
+    unprocessed: &'a [u8],
+    errored: bool,
+    error: PhantomData<E>,
+}
+
+impl<'a, E> CentralDirectoryEntries<'a, E> {
+    /// Iterates over the headers in the directory's bytes
+    pub fn new(directory: &'a [u8]) -> Self {
+        CentralDirectoryEntries {
+            unprocessed: directory,
+            errored: false,
+            error: PhantomData,
+        }
+    }
+}
+
+impl<'a, E: ZipParseError<'a>> Iterator for CentralDirectoryEntries<'a, E> {
+    type Item = Result<CentralDirectoryEntry<'a>, nom::Err<E>>;
 
-use super::find_end_of_central_directory::find_end_of_central_directory;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored || self.unprocessed.is_empty() {
+            return None;
+        }
+        match parse_directory_header::<E>(self.unprocessed) {
+            Ok((rest, entry)) => {
+                self.unprocessed = rest;
+                Some(Ok(entry))
+            }
+            Err(e) => {
+                self.errored = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Given the full file, lazily walks the central directory entries
+/// Fails with Eof if the directory is outside of the file.
+/// Any prefix before the archive is skipped, see prefix_length
+pub fn central_directory_entries<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], CentralDirectoryEntries<'a, E>, E> {
+    let (_, end) = find_end_of_central_directory::<E>(input)?;
+    segmented_central_directory_entries(input, Segments::single(input, &end))
+}
 
-fn parse_directory_entries<'a>(
+/// Given every disk of a split archive, lazily walks the central
+/// directory entries. Fails with Eof if the directory starts on a
+/// disk that isn't in the input, or is outside of it
+pub fn segmented_central_directory_entries<'a, E: ZipParseError<'a>>(
     input: &'a [u8],
-) -> IResult<&'a [u8], Vec<CentralDirectoryEntry<'a>>> {
-    let mut it = iterator(input, parse_directory_header);
-    let result = it.collect::<Vec<CentralDirectoryEntry<'a>>>();
-    let (input, _) = it.finish()?;
-    let (input, _eof) = eof(input)?;
-    Ok((input, result))
+    segments: Segments<'_>,
+) -> IResult<&'a [u8], CentralDirectoryEntries<'a, E>, E> {
+    let (_, end) = find_end_of_central_directory::<E>(input)?;
+    let start = segments
+        .position(end.directory_start_disk, end.offset_start_directory)
+        .ok_or_else(|| out_of_range(input))?;
+    let size = end.size_of_directory as usize;
+    let directory = range(input, start, size).ok_or_else(|| out_of_range(input))?;
+    Ok((&[], CentralDirectoryEntries::new(directory)))
 }
 
-/// Given the full file, find the central directory entries
-pub fn find_central_directory_entries(
-    input: &[u8],
-) -> IResult<&[u8], Vec<CentralDirectoryEntry<'_>>> {
-    let (_, end) = find_end_of_central_directory(input)?;
-    let start = end.offset_start_directory as usize;
-    let end = start + end.size_of_directory as usize;
-    let input = &input[start..end];
-    let (input, entries) = parse_directory_entries(input)?;
+/// Given the full file, find the central directory entries.
+/// See central_directory_entries
+#[cfg(feature = "alloc")]
+pub fn find_central_directory_entries<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Vec<CentralDirectoryEntry<'a>>, E> {
+    let (input, entries) = central_directory_entries::<E>(input)?;
+    let entries = entries.collect::<Result<Vec<_>, _>>()?;
     Ok((input, entries))
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
     use core::convert::TryFrom;
-    use nom::Finish;
+    use nom::{error::Error, Finish};
     use zipr_data::borrowed::{OEM437Str, ZipPath};
 
     use super::*;
@@ -42,11 +100,10 @@ mod tests {
     fn hello_world_store_as_entries() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = &hello[0x2c..0x87];
-        let result = parse_directory_entries(data).finish();
-
-        let (rem, result) = result.unwrap();
+        let result = CentralDirectoryEntries::<Error<_>>::new(data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
 
-        assert_eq!(0, rem.len());
         assert_eq!(1, result.len());
         assert_eq!(
             ZipPath::try_from(OEM437Str::from(b"hello.txt")).unwrap(),
@@ -54,15 +111,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn directory_out_of_range() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        let eocd = input.len() - 22;
+        input[eocd + 16..eocd + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = central_directory_entries::<Error<_>>(&input).finish();
+
+        assert_eq!(
+            nom::error::ErrorKind::Eof,
+            result.map(|_| ()).unwrap_err().code
+        );
+    }
+
     #[test]
     fn two_files_store_as_entries() {
         let hello = include_bytes!("../../../assets/two_files_store.zip");
         let data = &hello[0x59..(0x59 + 185)];
-        let result = parse_directory_entries(data).finish();
-
-        let (rem, result) = result.unwrap();
+        let result = CentralDirectoryEntries::<Error<_>>::new(data)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
 
-        assert_eq!(0, rem.len());
         assert_eq!(2, result.len());
         assert_eq!(
             ZipPath::try_from(OEM437Str::from(b"hello.txt")).unwrap(),
@@ -73,4 +142,19 @@ mod tests {
             result[1].file_name
         );
     }
+
+    #[test]
+    fn last_split_segment_listed() {
+        // The directory is on the last disk, so can be read without the others
+        let input = include_bytes!("../../../assets/split.zip");
+        let (_, entries) = central_directory_entries::<Error<_>>(input)
+            .finish()
+            .unwrap();
+        let disks = entries
+            .map(|x| x.map(|x| x.disk_number_start))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(alloc::vec![0, 0, 1], disks);
+    }
 }
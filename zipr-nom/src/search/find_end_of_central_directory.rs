@@ -1,39 +1,78 @@
-use crate::data::parse_end_of_central_directory;
+use super::prefix_length;
+use crate::{
+    data::{parse_directory_header, parse_end_of_central_directory},
+    error::{ParseFailure, ZipParseError},
+    slice::range,
+};
+use memchr::memmem;
 use nom::IResult;
 use zipr_data::{
-    borrowed::file::EndOfCentralDirectory, constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE,
+    borrowed::file::EndOfCentralDirectory,
+    constants::{END_OF_CENTRAL_DIRECTORY_HEADER, END_OF_CENTRAL_DIRECTORY_MAX_SIZE},
 };
 
-/// Like parse eocd, but walks backwards in the slice trying to find
-/// where the end of central directory record is
-pub fn find_end_of_central_directory(input: &[u8]) -> IResult<&[u8], EndOfCentralDirectory> {
-    let length = input.len();
-    let minimal = length - END_OF_CENTRAL_DIRECTORY_MIN_SIZE + 1;
-
-    let start = &input[0..minimal];
-
-    for (index, _) in start.iter().rev().enumerate() {
-        let input = &input[index..length];
-        if let Ok(result) = parse_end_of_central_directory(input) {
-            return Ok(result);
+/// Whether the whole central directory the record points to parses
+fn directory_parses(input: &[u8], eocd: &EndOfCentralDirectory<'_>) -> bool {
+    let start = prefix_length(input, eocd) + eocd.offset_start_directory as usize;
+    let size = eocd.size_of_directory as usize;
+    let mut directory = match range(input, start, size) {
+        Some(directory) => directory,
+        None => return false,
+    };
+    while !directory.is_empty() {
+        match parse_directory_header::<ParseFailure>(directory) {
+            Ok((rem, _)) => directory = rem,
+            Err(_) => return false,
         }
     }
+    true
+}
 
-    parse_end_of_central_directory(input)
+/// Like parse eocd, but searches backwards from the end of the slice
+/// for where the end of central directory record is.
+///
+/// Only the last 65,557 bytes are searched, as the comment can't be longer.
+/// A candidate's comment must reach exactly to the end. If the comment
+/// holds something that looks like a record, the candidate whose central
+/// directory parses is used. Failing that, the last candidate
+pub fn find_end_of_central_directory<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], EndOfCentralDirectory<'a>, E> {
+    let window_start = input
+        .len()
+        .saturating_sub(END_OF_CENTRAL_DIRECTORY_MAX_SIZE);
+    let window = &input[window_start..];
+
+    let mut candidates = memmem::rfind_iter(window, &END_OF_CENTRAL_DIRECTORY_HEADER)
+        .filter_map(|index| parse_end_of_central_directory::<ParseFailure>(&window[index..]).ok());
+    let last = match candidates.next() {
+        Some(last) => last,
+        // Report why the record doesn't parse
+        None => return parse_end_of_central_directory(window),
+    };
+    if directory_parses(input, &last.1) {
+        return Ok(last);
+    }
+    Ok(candidates
+        .find(|(_, eocd)| directory_parses(input, eocd))
+        .unwrap_or(last))
 }
 
 #[cfg(test)]
 mod tests {
 
-    use zipr_data::borrowed::OEM437Str;
+    use zipr_data::{borrowed::OEM437Str, constants::END_OF_CENTRAL_DIRECTORY_MIN_SIZE};
+
+    use nom::error::Error;
 
     use super::*;
 
     #[test]
     fn hello_world_store_without_position() {
         let input = include_bytes!("../../../assets/hello_world_store.zip");
-        let result = find_end_of_central_directory(input);
+        let result = find_end_of_central_directory::<Error<_>>(input);
         let expected = EndOfCentralDirectory {
+            records_on_this_disk: 1,
             total_number_records: 1,
             size_of_directory: 91,
             offset_start_directory: 44,
@@ -43,18 +82,73 @@ mod tests {
         assert_eq!(Ok((&[] as &[u8], expected)), result);
     }
 
+    #[test]
+    fn shorter_than_record() {
+        let input = include_bytes!("../../../assets/hello_world_store.zip");
+        for length in 0..END_OF_CENTRAL_DIRECTORY_MIN_SIZE {
+            assert!(find_end_of_central_directory::<Error<_>>(&input[..length]).is_err());
+        }
+    }
+
     #[test]
     fn hello_world_store_with_comment() {
         let input = include_bytes!("../../../assets/hello_world_store_with_comment.zip");
         let comment = OEM437Str::from(b"tricky");
-        let result = find_end_of_central_directory(input);
+        let result = find_end_of_central_directory::<Error<_>>(input);
         let expected = EndOfCentralDirectory {
+            records_on_this_disk: 1,
             total_number_records: 1,
             size_of_directory: 91,
             offset_start_directory: 44,
             comment,
+            ..Default::default()
         };
 
         assert_eq!(Ok((&[] as &[u8], expected)), result);
     }
+
+    #[test]
+    fn record_in_comment_skipped() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        // A comment holding a record, pointing at a directory that doesn't exist
+        let mut fake = [0u8; END_OF_CENTRAL_DIRECTORY_MIN_SIZE];
+        fake[..4].copy_from_slice(&END_OF_CENTRAL_DIRECTORY_HEADER);
+        fake[16..20].copy_from_slice(&1000u32.to_le_bytes());
+        let comment_length = input.len() - 2;
+        input[comment_length..].copy_from_slice(&(fake.len() as u16).to_le_bytes());
+        input.extend_from_slice(&fake);
+
+        let (_, result) = find_end_of_central_directory::<Error<_>>(&input).unwrap();
+
+        assert_eq!(44, result.offset_start_directory);
+        assert_eq!(&fake[..], result.comment.as_ref());
+    }
+
+    #[test]
+    fn unparseable_directory_falls_back_to_last() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        let eocd = input.len() - END_OF_CENTRAL_DIRECTORY_MIN_SIZE;
+        input[eocd + 16..eocd + 20].copy_from_slice(&1000u32.to_le_bytes());
+
+        let (_, result) = find_end_of_central_directory::<Error<_>>(&input).unwrap();
+
+        assert_eq!(1000, result.offset_start_directory);
+    }
+
+    #[test]
+    fn outside_of_comment_range() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        input.extend_from_slice(&[0; END_OF_CENTRAL_DIRECTORY_MAX_SIZE]);
+
+        assert!(find_end_of_central_directory::<Error<_>>(&input).is_err());
+    }
+
+    #[test]
+    fn comment_must_reach_end() {
+        let mut input =
+            include_bytes!("../../../assets/hello_world_store_with_comment.zip").to_vec();
+        input.push(0);
+
+        assert!(find_end_of_central_directory::<Error<_>>(&input).is_err());
+    }
 }
@@ -1,36 +1,110 @@
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 use nom::IResult;
 use zipr_data::borrowed::file::{CentralDirectoryEntry, LocalFileEntry};
 
-use crate::data::parse_local_file;
+use crate::{
+    data::parse_local_file_for,
+    error::ZipParseError,
+    slice::{from_offset, out_of_range},
+};
 
-use super::find_central_directory_entries;
+use super::{
+    find_end_of_central_directory, segmented_central_directory_entries, CentralDirectoryEntries,
+    Segments,
+};
 
-pub fn local_entry<'a>(
-    full_file: &'a [u8],
+/// The local file the directory entry points to. The archive
+/// is the full file without its prefix, which offsets are from.
+/// Fails with Eof if that is outside of the file.
+/// The disk isn't checked, see segmented_local_entry
+pub fn local_entry<'a, E: ZipParseError<'a>>(
+    archive: &'a [u8],
     directory: &CentralDirectoryEntry,
-) -> IResult<&'a [u8], LocalFileEntry<'a>> {
+) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
     let start = directory.relative_offset as usize;
-    let end = full_file.len();
-    let local_bytes = &full_file[start..end];
-    let (rem, entry) = parse_local_file(local_bytes)?;
+    let local_bytes = from_offset(archive, start).ok_or_else(|| out_of_range(archive))?;
+    let (rem, entry) = parse_local_file_for(directory)(local_bytes)?;
     Ok((rem, entry))
 }
 
+/// The local file the directory entry points to, on any of the disks.
+/// Fails with Eof if its disk isn't in the input, or it is outside of it
+pub fn segmented_local_entry<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+    segments: Segments<'_>,
+    directory: &CentralDirectoryEntry,
+) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
+    let start = segments
+        .position(directory.disk_number_start, directory.relative_offset)
+        .ok_or_else(|| out_of_range(input))?;
+    let local_bytes = from_offset(input, start).ok_or_else(|| out_of_range(input))?;
+    let (rem, entry) = parse_local_file_for(directory)(local_bytes)?;
+    Ok((rem, entry))
+}
+
+/// Lazily joins each central directory entry with its local file,
+/// stopping after the first that fails
+pub struct LocalFileEntries<'a, E> {
+    input: &'a [u8],
+    segments: Segments<'a>,
+    directories: CentralDirectoryEntries<'a, E>,
+    errored: bool,
+}
+
+impl<'a, E: ZipParseError<'a>> Iterator for LocalFileEntries<'a, E> {
+    type Item = Result<(CentralDirectoryEntry<'a>, LocalFileEntry<'a>), nom::Err<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored {
+            return None;
+        }
+        let joined = self.directories.next()?.and_then(|directory| {
+            let (_, local) = segmented_local_entry::<E>(self.input, self.segments, &directory)?;
+            Ok((directory, local))
+        });
+        self.errored = joined.is_err();
+        Some(joined)
+    }
+}
+
+/// Given the full file, lazily finds the local file of each
+/// central directory entry. See central_directory_entries
+pub fn local_file_entries<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], LocalFileEntries<'a, E>, E> {
+    let (_, end) = find_end_of_central_directory::<E>(input)?;
+    segmented_local_file_entries(input, Segments::single(input, &end))
+}
+
+/// Given every disk of a split archive, lazily finds the local file
+/// of each central directory entry. See segmented_central_directory_entries
+pub fn segmented_local_file_entries<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+    segments: Segments<'a>,
+) -> IResult<&'a [u8], LocalFileEntries<'a, E>, E> {
+    let (_, directories) = segmented_central_directory_entries::<E>(input, segments)?;
+    let entries = LocalFileEntries {
+        input,
+        segments,
+        directories,
+        errored: false,
+    };
+    Ok((&[], entries))
+}
+
 /// Given the full file. Finds all the local file entries
 /// Note this uses the central directory header to find the locations.
 /// So both must be valid/non-corrupt    
-pub fn find_local_file_entries(input: &[u8]) -> IResult<&[u8], Vec<LocalFileEntry<'_>>> {
-    let (_, directories) = find_central_directory_entries(input)?;
-
-    // There should be a way to nicely do this with iterators, but trouble finding
-    // sequence (Vec<IResult> -> IResult<Vec<_>>) for nom.
-    let mut local = Vec::with_capacity(directories.len());
-    for directory in directories.iter() {
-        let (_, file) = local_entry(input, directory)?;
-        local.push(file);
-    }
-    Ok((&[], local))
+#[cfg(feature = "alloc")]
+pub fn find_local_file_entries<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Vec<LocalFileEntry<'a>>, E> {
+    let (input, entries) = local_file_entries::<E>(input)?;
+    let local = entries
+        .map(|x| x.map(|(_, local)| local))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((input, local))
 }
 
 #[cfg(test)]
@@ -38,30 +112,58 @@ mod tests {
     use core::convert::TryFrom;
 
     use super::*;
-    use nom::Finish;
+    use nom::{error::Error, Finish};
     use zipr_data::borrowed::{OEM437Str, ZipPath};
 
     #[test]
     fn hello_world_store_as_entries() {
         let hello = include_bytes!("../../../assets/hello_world_store.zip");
         let data = hello;
-        let result = find_local_file_entries(data).finish();
-
-        let (rem, result) = result.unwrap();
+        let (_, mut entries) = local_file_entries::<Error<_>>(data).finish().unwrap();
+        let (directory, local) = entries.next().unwrap().unwrap();
 
-        assert_eq!(0, rem.len());
-        assert_eq!(1, result.len());
         assert_eq!(
             ZipPath::try_from(OEM437Str::from(b"hello.txt")).unwrap(),
-            result[0].file_name
+            local.file_name
         );
+        assert_eq!(directory.file_name, local.file_name);
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn local_file_out_of_range() {
+        let mut input = include_bytes!("../../../assets/hello_world_store.zip").to_vec();
+        // The relative offset of the only central directory entry
+        input[0x2c + 42..0x2c + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        let (_, mut entries) = local_file_entries::<Error<_>>(&input).finish().unwrap();
+
+        match entries.next() {
+            Some(Err(nom::Err::Error(e))) => assert_eq!(nom::error::ErrorKind::Eof, e.code),
+            _ => panic!("expected the local file to be out of range"),
+        }
+        assert!(entries.next().is_none());
+    }
+
+    #[test]
+    fn data_descriptor_sizes_from_directory() {
+        let input = include_bytes!("../../../assets/data_descriptor.zip");
+        let (_, mut entries) = local_file_entries::<Error<_>>(input).finish().unwrap();
+        let (_, hello) = entries.next().unwrap().unwrap();
+        let (directory, more) = entries.next().unwrap().unwrap();
+
+        assert_eq!(b"hello", hello.compressed_data.bytes());
+        assert_eq!(0x3610a686, hello.compressed_data.crc32());
+        assert_eq!(128, more.compressed_data.uncompressed_size());
+        assert_eq!(directory.crc32, more.compressed_data.crc32());
+        assert!(entries.next().is_none());
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn two_files_store_as_entries() {
         let hello = include_bytes!("../../../assets/two_files_store.zip");
         let data = hello;
-        let result = find_local_file_entries(data).finish();
+        let result = find_local_file_entries::<Error<_>>(data).finish();
 
         let (rem, result) = result.unwrap();
 
@@ -76,4 +178,33 @@ mod tests {
             result[1].file_name
         );
     }
+
+    #[test]
+    fn last_split_segment_partial() {
+        let input = include_bytes!("../../../assets/split.zip");
+        let (_, mut entries) = local_file_entries::<Error<_>>(input).finish().unwrap();
+
+        // The first entry is on the missing disk
+        match entries.next() {
+            Some(Err(nom::Err::Error(e))) => assert_eq!(nom::error::ErrorKind::Eof, e.code),
+            _ => panic!("expected the first disk to be missing"),
+        }
+    }
+
+    #[test]
+    fn split_segments_joined() {
+        let first = include_bytes!("../../../assets/split.z01");
+        let last = include_bytes!("../../../assets/split.zip");
+        let input = [&first[..], &last[..]].concat();
+        let lengths = [first.len(), last.len()];
+        let (_, entries) =
+            segmented_local_file_entries::<Error<_>>(&input, Segments::Joined(&lengths))
+                .finish()
+                .unwrap();
+        let entries = entries.collect::<Result<alloc::vec::Vec<_>, _>>().unwrap();
+
+        assert_eq!(3, entries.len());
+        assert_eq!(90000, entries[1].1.compressed_data.bytes().len());
+        assert_eq!(b"last\n", entries[2].1.compressed_data.bytes());
+    }
 }
@@ -1,12 +1,31 @@
+//! Parsers that try to find items inside the zip file
+//! This is required to enter the zip file.. as they require you to search
+//! backwards, but other utilities for trying to find data are useful too
+//! Note in this context we are parsing the 'full' file to the parsers
 mod find_central_directory_entries;
-///! Parsers that try to find items inside the zip file
-///! This is required to enter the zip file.. as they require you to search
-///! backwards, but other utilities for trying to find data are useful too
-///! Note in this context we are parsing the 'full' file to the parsers
 mod find_end_of_central_directory;
 mod find_local_file_entries;
+mod prefix;
+mod segments;
+mod structure;
+#[cfg(feature = "alloc")]
 mod zip_entries;
+#[cfg(feature = "alloc")]
 pub use find_central_directory_entries::find_central_directory_entries;
+pub use find_central_directory_entries::{
+    central_directory_entries, segmented_central_directory_entries, CentralDirectoryEntries,
+};
 pub use find_end_of_central_directory::find_end_of_central_directory;
+#[cfg(feature = "alloc")]
 pub use find_local_file_entries::find_local_file_entries;
+pub use find_local_file_entries::{
+    local_entry, local_file_entries, segmented_local_entry, segmented_local_file_entries,
+    LocalFileEntries,
+};
+pub use prefix::{find_prefix, prefix_length};
+pub use segments::Segments;
+#[cfg(feature = "alloc")]
+pub use structure::find_structural_issues;
+pub use structure::{archive_layout, entry_extent};
+#[cfg(feature = "alloc")]
 pub use zip_entries::parse_zip_entries;
@@ -0,0 +1,89 @@
+use nom::IResult;
+use zipr_data::{
+    borrowed::file::EndOfCentralDirectory,
+    constants::{CENTRAL_DIRECTORY_HEADER_SIGNATURE, END_OF_CENTRAL_DIRECTORY_MIN_SIZE},
+};
+
+use super::find_end_of_central_directory;
+use crate::{error::ZipParseError, slice::from_offset};
+
+/// The number of bytes before the archive, eg a self extracting stub.
+/// Offsets in the archive are from its start, so this is added to them.
+///
+/// The central directory should be right before the end record, so where it
+/// actually is less where the record says it is, is the length of the prefix.
+/// Stubs that had the offsets adjusted for them, eg by zip -A, have no prefix
+pub fn prefix_length(input: &[u8], eocd: &EndOfCentralDirectory<'_>) -> usize {
+    let record_length = END_OF_CENTRAL_DIRECTORY_MIN_SIZE + eocd.comment.len();
+    let prefix = input
+        .len()
+        .saturating_sub(record_length)
+        .saturating_sub(eocd.size_of_directory as usize)
+        .saturating_sub(eocd.offset_start_directory as usize);
+    // Something between the directory and record would look like a prefix,
+    // so only trust it if the directory is actually there
+    let directory = (eocd.offset_start_directory as usize)
+        .checked_add(prefix)
+        .and_then(|start| from_offset(input, start))
+        .unwrap_or_default();
+    if eocd.size_of_directory == 0 || directory.starts_with(&CENTRAL_DIRECTORY_HEADER_SIGNATURE) {
+        prefix
+    } else {
+        0
+    }
+}
+
+/// Given the full file, finds the bytes before the archive.
+/// These are empty for most archives, see prefix_length
+pub fn find_prefix<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8], E> {
+    let (_, eocd) = find_end_of_central_directory::<E>(input)?;
+    let prefix = prefix_length(input, &eocd);
+    Ok((&input[prefix..], &input[..prefix]))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::convert::TryInto;
+    use nom::error::Error;
+
+    use super::*;
+
+    fn prefixed(stub: &[u8]) -> Vec<u8> {
+        let archive = include_bytes!("../../../assets/two_files_store.zip");
+        let mut input = stub.to_vec();
+        input.extend_from_slice(archive);
+        input
+    }
+
+    #[test]
+    fn two_files_store_no_prefix() {
+        let input = include_bytes!("../../../assets/two_files_store.zip");
+        let (rest, prefix) = find_prefix::<Error<&[u8]>>(input).unwrap();
+
+        assert!(prefix.is_empty());
+        assert_eq!(input.len(), rest.len());
+    }
+
+    #[test]
+    fn shell_script_stub() {
+        let stub = b"#!/bin/sh\nexec unzip \"$0\"\n";
+        let input = prefixed(stub);
+        let (_, prefix) = find_prefix::<Error<&[u8]>>(&input).unwrap();
+
+        assert_eq!(&stub[..], prefix);
+    }
+
+    #[test]
+    fn adjusted_offsets_no_prefix() {
+        let stub = b"stub";
+        let mut input = prefixed(stub);
+        // As zip -A would, move the directory offset past the stub
+        let eocd = input.len() - END_OF_CENTRAL_DIRECTORY_MIN_SIZE;
+        let offset = u32::from_le_bytes(input[eocd + 16..eocd + 20].try_into().unwrap());
+        input[eocd + 16..eocd + 20].copy_from_slice(&(offset + 4).to_le_bytes());
+        let (_, prefix) = find_prefix::<Error<&[u8]>>(&input).unwrap();
+
+        assert!(prefix.is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use zipr_data::borrowed::file::EndOfCentralDirectory;
+
+use super::prefix_length;
+
+/// Where the disks of an archive are in the input.
+/// Offsets in a split or spanned archive are from the start of the
+/// disk the record is on, eg the .z01 file, rather than the archive
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Segments<'s> {
+    /// A file holding a single disk, after any prefix. Only entries on
+    /// this disk can be read, eg from the last file of a split archive
+    Single { disk: u16, prefix: usize },
+    /// Every disk joined in order, with the length of each
+    Joined(&'s [usize]),
+}
+
+impl Segments<'_> {
+    /// The disk the end record is on, in a file of its own.
+    /// See prefix_length
+    pub fn single(input: &[u8], eocd: &EndOfCentralDirectory<'_>) -> Self {
+        // The prefix is found from the directory, so it has to be on this disk
+        let prefix = match eocd.directory_start_disk == eocd.number_of_this_disk {
+            true => prefix_length(input, eocd),
+            false => 0,
+        };
+        Segments::Single {
+            disk: eocd.number_of_this_disk,
+            prefix,
+        }
+    }
+
+    /// Whether the disk is in the input
+    pub fn has_disk(&self, disk: u16) -> bool {
+        match self {
+            Segments::Single { disk: this, .. } => disk == *this,
+            Segments::Joined(lengths) => (disk as usize) < lengths.len(),
+        }
+    }
+
+    /// The bytes before the first disk, see prefix_length
+    pub fn prefix(&self) -> usize {
+        match self {
+            Segments::Single { prefix, .. } => *prefix,
+            Segments::Joined(_) => 0,
+        }
+    }
+
+    /// Where the offset on the disk is in the input.
+    /// None if the disk isn't in the input, or the offset is past its end
+    pub fn position(&self, disk: u16, offset: u32) -> Option<usize> {
+        if !self.has_disk(disk) {
+            return None;
+        }
+        let offset = offset as usize;
+        match self {
+            Segments::Single { prefix, .. } => prefix.checked_add(offset),
+            Segments::Joined(lengths) => {
+                let (before, rest) = lengths.split_at(disk as usize);
+                if offset > rest[0] {
+                    return None;
+                }
+                before
+                    .iter()
+                    .try_fold(offset, |position, length| position.checked_add(*length))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::error::Error;
+
+    use super::*;
+    use crate::find_end_of_central_directory;
+
+    const SEGMENTS: [usize; 2] = [65536, 24787];
+
+    #[test]
+    fn two_files_store_single() {
+        let input = include_bytes!("../../../assets/two_files_store.zip");
+        let (_, eocd) = find_end_of_central_directory::<Error<_>>(input).unwrap();
+        let segments = Segments::single(input, &eocd);
+
+        assert_eq!(Segments::Single { disk: 0, prefix: 0 }, segments);
+        assert_eq!(Some(44), segments.position(0, 44));
+        assert_eq!(None, segments.position(1, 44));
+    }
+
+    #[test]
+    fn last_split_segment_single() {
+        let input = include_bytes!("../../../assets/split.zip");
+        let (_, eocd) = find_end_of_central_directory::<Error<_>>(input).unwrap();
+        let segments = Segments::single(input, &eocd);
+
+        assert_eq!(1, eocd.number_of_this_disk);
+        assert_eq!(Segments::Single { disk: 1, prefix: 0 }, segments);
+        assert!(!segments.has_disk(0));
+    }
+
+    #[test]
+    fn joined_positions() {
+        let segments = Segments::Joined(&SEGMENTS);
+
+        assert_eq!(Some(4), segments.position(0, 4));
+        assert_eq!(Some(65536 + 10), segments.position(1, 10));
+        assert_eq!(None, segments.position(0, 65537));
+        assert_eq!(None, segments.position(2, 0));
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use nom::{bytes::complete::tag, combinator::opt, IResult, Offset};
+use zipr_data::{
+    borrowed::file::CentralDirectoryEntry,
+    constants::{
+        DATA_DESCRIPTOR_LENGTH, DATA_DESCRIPTOR_SIGNATURE, END_OF_CENTRAL_DIRECTORY_MIN_SIZE,
+        GENERAL_PURPOSE_DATA_DESCRIPTOR,
+    },
+};
+#[cfg(feature = "alloc")]
+use zipr_domain::{structural_issues, StructuralIssue};
+use zipr_domain::{ArchiveLayout, EntryExtent, Extent};
+
+#[cfg(feature = "alloc")]
+use super::find_central_directory_entries;
+use super::{find_end_of_central_directory, prefix_length, Segments};
+use crate::{
+    data::parse_local_file,
+    error::ZipParseError,
+    slice::{from_offset, out_of_range},
+};
+
+/// The bytes used by the entry, from its local header to the end of
+/// its data descriptor. The data size comes from the central directory,
+/// as the local header may not have it. The segments give where the
+/// offset is in the file, eg after a prefix, see Segments.
+/// Fails if the local header can't be read, see StructuralIssue::UnreadableLocalHeader
+pub fn entry_extent<'a, E: ZipParseError<'a>>(
+    full_file: &'a [u8],
+    segments: Segments<'_>,
+    index: usize,
+    directory: &CentralDirectoryEntry,
+) -> IResult<&'a [u8], EntryExtent, E> {
+    let start = segments
+        .position(directory.disk_number_start, directory.relative_offset)
+        .ok_or_else(|| out_of_range(full_file))?;
+    let local_bytes = from_offset(full_file, start).ok_or_else(|| out_of_range(full_file))?;
+    let (_, local) = parse_local_file::<E>(local_bytes)?;
+    let data_start = full_file.offset(local.compressed_data.bytes()) as u64;
+    let mut end = data_start + directory.compressed_size as u64;
+    if directory.general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0 {
+        let descriptor = from_offset(full_file, end as usize).unwrap_or_default();
+        let (_, signature) = opt(tag::<_, _, E>(DATA_DESCRIPTOR_SIGNATURE))(descriptor)?;
+        let signature = signature.map_or(0, <[u8]>::len) as u64;
+        end += signature + DATA_DESCRIPTOR_LENGTH as u64;
+    }
+    let extent = Extent::new(start as u64, end);
+    Ok((&[], EntryExtent { index, extent }))
+}
+
+/// Where the central directory and end record are in the full file
+pub fn archive_layout<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], ArchiveLayout, E> {
+    let (_, end) = find_end_of_central_directory::<E>(input)?;
+    let file_size = input.len() as u64;
+    let record_size = (END_OF_CENTRAL_DIRECTORY_MIN_SIZE + end.comment.len()) as u64;
+    let eocd_start = file_size.saturating_sub(record_size);
+    let directory_start = (prefix_length(input, &end) + end.offset_start_directory as usize) as u64;
+    let layout = ArchiveLayout {
+        file_size,
+        central_directory: Extent::new(
+            directory_start,
+            directory_start + end.size_of_directory as u64,
+        ),
+        end_of_central_directory: Extent::new(eocd_start, file_size),
+    };
+    Ok((&[], layout))
+}
+
+/// Given the full file. Finds overlapping entries, gaps
+/// and other unusual structure. See StructurePolicy to decide
+/// which of these are errors. Entries whose local header can't be
+/// read are reported, and left out of the rest of the checks
+#[cfg(feature = "alloc")]
+pub fn find_structural_issues<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Vec<StructuralIssue>, E> {
+    let (_, layout) = archive_layout::<E>(input)?;
+    let (_, directories) = find_central_directory_entries::<E>(input)?;
+    let (_, end) = find_end_of_central_directory::<E>(input)?;
+    let segments = Segments::single(input, &end);
+    let mut extents = Vec::with_capacity(directories.len());
+    let mut issues = Vec::new();
+    for (index, directory) in directories.iter().enumerate() {
+        match entry_extent::<E>(input, segments, index, directory) {
+            Ok((_, extent)) => extents.push(extent),
+            Err(_) => issues.push(StructuralIssue::UnreadableLocalHeader { index }),
+        }
+    }
+    issues.extend(structural_issues(&layout, &mut extents));
+    Ok((&[], issues))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use nom::{error::Error, Finish};
+    use zipr_data::constants::{CENTRAL_DIRECTORY_HEADER_SIGNATURE, LOCAL_FILE_HEADER_SIGNATURE};
+
+    use super::*;
+
+    #[test]
+    fn two_files_store_no_issues() {
+        let input = include_bytes!("../../../assets/two_files_store.zip");
+        let (_, issues) = find_structural_issues::<Error<_>>(input).finish().unwrap();
+
+        assert_eq!(Vec::<StructuralIssue>::new(), issues);
+    }
+
+    #[test]
+    fn two_files_store_overlapping() {
+        let mut input = include_bytes!("../../../assets/two_files_store.zip").to_vec();
+        // Point the second central directory entry at the first local file
+        let second = input
+            .windows(4)
+            .rposition(|x| x == CENTRAL_DIRECTORY_HEADER_SIGNATURE)
+            .unwrap();
+        input[second + 42..second + 46].copy_from_slice(&[0, 0, 0, 0]);
+
+        let (_, issues) = find_structural_issues::<Error<_>>(&input).finish().unwrap();
+
+        assert!(matches!(
+            issues[..],
+            [
+                StructuralIssue::Overlap {
+                    first: 0,
+                    second: 1,
+                    ..
+                },
+                StructuralIssue::Gap(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn unreadable_local_header() {
+        let mut input = include_bytes!("../../../assets/two_files_store.zip").to_vec();
+        let second = input
+            .windows(4)
+            .rposition(|x| x == LOCAL_FILE_HEADER_SIGNATURE)
+            .unwrap();
+        input[second..second + 4].copy_from_slice(&[0, 0, 0, 0]);
+
+        let (_, issues) = find_structural_issues::<Error<_>>(&input).finish().unwrap();
+
+        assert!(matches!(
+            issues[..],
+            [
+                StructuralIssue::UnreadableLocalHeader { index: 1 },
+                StructuralIssue::Gap(_)
+            ]
+        ));
+    }
+
+    #[test]
+    fn prefix_is_prepended_data() {
+        let mut input = b"stub".to_vec();
+        input.extend_from_slice(include_bytes!("../../../assets/two_files_store.zip"));
+
+        let (_, issues) = find_structural_issues::<Error<_>>(&input).finish().unwrap();
+
+        assert_eq!(
+            alloc::vec![StructuralIssue::PrependedData { size: 4 }],
+            issues
+        );
+    }
+}
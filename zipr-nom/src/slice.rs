@@ -0,0 +1,19 @@
+//! Bounds checked slicing for offsets and sizes read from the file.
+//! These are untrusted, so can point anywhere, or overflow
+use nom::error::{ErrorKind, ParseError};
+
+/// The bytes from start to the end of the input
+pub(crate) fn from_offset(input: &[u8], start: usize) -> Option<&[u8]> {
+    input.get(start..)
+}
+
+/// The length bytes at start
+pub(crate) fn range(input: &[u8], start: usize, length: usize) -> Option<&[u8]> {
+    let end = start.checked_add(length)?;
+    input.get(start..end)
+}
+
+/// The error for an offset outside of the input
+pub(crate) fn out_of_range<'a, E: ParseError<&'a [u8]>>(input: &'a [u8]) -> nom::Err<E> {
+    nom::Err::Error(E::from_error_kind(input, ErrorKind::Eof))
+}
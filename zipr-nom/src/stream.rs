@@ -0,0 +1,296 @@
+//! Parsers for reading an archive forwards, as it arrives
+//!
+//! These use nom's streaming combinators, so give Incomplete when
+//! more input is needed rather than failing. The central directory
+//! isn't read, each entry comes from its local header
+use crc::{Crc, CRC_32_ISO_HDLC};
+use memchr::memmem;
+use nom::{
+    bytes::{complete, streaming::take},
+    combinator::peek,
+    error::context,
+    number::complete::{le_u16, le_u32},
+    Err, IResult, Needed,
+};
+use zipr_data::{
+    borrowed::file::{CompressedData, DataDescriptor, LocalFileEntry},
+    constants::{
+        CENTRAL_DIRECTORY_HEADER_SIGNATURE, DATA_DESCRIPTOR_LENGTH, DATA_DESCRIPTOR_SIGNATURE,
+        END_OF_CENTRAL_DIRECTORY_HEADER, GENERAL_PURPOSE_DATA_DESCRIPTOR,
+        LOCAL_FILE_HEADER_SIGNATURE, LOCAL_FILE_MIN_LENGTH, SPANNING_SIGNATURE,
+    },
+    CompressionMethod,
+};
+
+use crate::{
+    data::{parse_data_descriptor, parse_local_file, parse_local_header},
+    error::{ZipParseError, DATA_DESCRIPTOR, LOCAL_FILE_HEADER},
+};
+
+static CRC32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// Parses the next entry of an archive being read forwards.
+/// Gives None once the central directory is reached, as no entries follow it.
+/// The segments of a split archive can be read one after the other, the
+/// spanning signature at the start of the first is skipped
+pub fn parse_streaming_entry<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Option<LocalFileEntry<'a>>, E> {
+    let input = match next_local_file(input)? {
+        Some(input) => input,
+        None => return Ok((input, None)),
+    };
+    let (input, entry) = context(LOCAL_FILE_HEADER, local_file)(input)?;
+    Ok((input, Some(entry)))
+}
+
+/// A local header, and the size of the data after it when that is known
+pub type StreamedHeader<'a> = (LocalFileEntry<'a>, Option<u32>);
+
+/// Parses the next local header of an archive being read forwards, leaving
+/// its data to be read separately. The entry's data is empty, the size of
+/// the data after it is given alongside. That is None when the size is only
+/// in a data descriptor, then parse_streaming_entry finds where it ends.
+/// Gives None once the central directory is reached
+pub fn parse_streaming_header<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], Option<StreamedHeader<'a>>, E> {
+    let input = match next_local_file(input)? {
+        Some(input) => input,
+        None => return Ok((input, None)),
+    };
+    let (_, header) = take(LOCAL_FILE_MIN_LENGTH)(input)?;
+    let (_, (general_purpose, _, file_name_length, extra_field_length)) =
+        context(LOCAL_FILE_HEADER, lengths)(header)?;
+    let header_length =
+        LOCAL_FILE_MIN_LENGTH as usize + file_name_length as usize + extra_field_length as usize;
+    let (input, header) = take(header_length)(input)?;
+    let (_, (entry, compressed_size)) = parse_local_header(header)?;
+
+    let deferred = general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0;
+    let size = Some(compressed_size).filter(|&x| !deferred || x != 0);
+    Ok((input, Some((entry, size))))
+}
+
+/// Skips the spanning signature at the start of a split archive.
+/// None once the central directory is reached, as no entries follow it
+fn next_local_file<'a, E: ZipParseError<'a>>(input: &'a [u8]) -> Result<Option<&'a [u8]>, Err<E>> {
+    let (_, signature) = peek(take(4usize))(input)?;
+    if signature == CENTRAL_DIRECTORY_HEADER_SIGNATURE
+        || signature == END_OF_CENTRAL_DIRECTORY_HEADER
+    {
+        return Ok(None);
+    }
+    // Entries consume their data descriptors, so this is only at the start
+    match signature == SPANNING_SIGNATURE {
+        true => Ok(Some(&input[SPANNING_SIGNATURE.len()..])),
+        false => Ok(Some(input)),
+    }
+}
+
+/// The general purpose flags, compressed size, and the
+/// file name and extra field lengths of a local header
+fn lengths<'a, E: ZipParseError<'a>>(
+    header: &'a [u8],
+) -> IResult<&'a [u8], (u16, u32, u16, u16), E> {
+    let (input, _) = context("signature", complete::tag(LOCAL_FILE_HEADER_SIGNATURE))(header)?;
+    let (input, _version_needed) = complete::take(2usize)(input)?;
+    let (input, general_purpose) = le_u16(input)?;
+    let (input, _method_time_date_crc32) = complete::take(10usize)(input)?;
+    let (input, compressed_size) = le_u32(input)?;
+    let (input, _uncompressed_size) = le_u32(input)?;
+    let (input, file_name_length) = le_u16(input)?;
+    let (input, extra_field_length) = le_u16(input)?;
+    let result = (
+        general_purpose,
+        compressed_size,
+        file_name_length,
+        extra_field_length,
+    );
+    Ok((input, result))
+}
+
+/// Waits for the whole header, and data if its size is known,
+/// then parses them as usual
+pub(crate) fn local_file<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+) -> IResult<&'a [u8], LocalFileEntry<'a>, E> {
+    let (_, header) = take(LOCAL_FILE_MIN_LENGTH)(input)?;
+    let (_, (general_purpose, compressed_size, file_name_length, extra_field_length)) =
+        lengths(header)?;
+    let header_length =
+        LOCAL_FILE_MIN_LENGTH as usize + file_name_length as usize + extra_field_length as usize;
+
+    let deferred = general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0;
+    if !deferred || compressed_size != 0 {
+        let (input, bytes) = take(header_length + compressed_size as usize)(input)?;
+        let (_, entry) = parse_local_file(bytes)?;
+        return Ok((input, entry));
+    }
+
+    let (input, header) = take(header_length)(input)?;
+    let (_, mut entry) = parse_local_file(header)?;
+    let method = entry.compressed_data.compression_method();
+    let (input, (bytes, descriptor)) =
+        context(DATA_DESCRIPTOR, |x| data_with_descriptor(x, method))(input)?;
+    entry.compressed_data = CompressedData::create_unchecked(
+        descriptor.uncompressed_size,
+        method,
+        descriptor.crc32,
+        bytes,
+    );
+    Ok((input, entry))
+}
+
+/// Whether the descriptor is for the data before it.
+/// Only stored data can be checked against the crc32 without decompressing
+fn describes(data: &[u8], descriptor: &DataDescriptor, method: CompressionMethod) -> bool {
+    let size = data.len() as u64;
+    let compressed = descriptor.compressed_size as u64 == size;
+    match method {
+        CompressionMethod::Stored => {
+            compressed
+                && descriptor.uncompressed_size as u64 == size
+                && CRC32.checksum(data) == descriptor.crc32
+        }
+        CompressionMethod::Deflate => compressed,
+    }
+}
+
+/// Data of unknown length ends at the data descriptor whose compressed size
+/// matches the bytes before it. Descriptors without a signature are found
+/// by the signature of the record after them
+fn data_with_descriptor<'a, E: ZipParseError<'a>>(
+    input: &'a [u8],
+    method: CompressionMethod,
+) -> IResult<&'a [u8], (&'a [u8], DataDescriptor), E> {
+    let length = DATA_DESCRIPTOR_LENGTH as usize;
+    for position in memmem::find_iter(input, b"PK") {
+        let signature = &input[position..];
+        let (start, candidate) = if signature.starts_with(&DATA_DESCRIPTOR_SIGNATURE) {
+            let end = position + DATA_DESCRIPTOR_SIGNATURE.len() + length;
+            if end > input.len() {
+                return Err(Err::Incomplete(Needed::new(end - input.len())));
+            }
+            (position, &input[position..end])
+        } else if position >= length
+            && (signature.starts_with(&LOCAL_FILE_HEADER_SIGNATURE)
+                || signature.starts_with(&CENTRAL_DIRECTORY_HEADER_SIGNATURE))
+        {
+            (position - length, &input[position - length..position])
+        } else {
+            continue;
+        };
+        let data = &input[..start];
+        if let Ok((_, descriptor)) = parse_data_descriptor::<E>(candidate) {
+            if describes(data, &descriptor, method) {
+                let rest = &input[start + candidate.len()..];
+                return Ok((rest, (data, descriptor)));
+            }
+        }
+    }
+    Err(Err::Incomplete(Needed::Unknown))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use nom::error::Error;
+
+    use super::*;
+
+    fn entries(mut input: &[u8]) -> Vec<LocalFileEntry<'_>> {
+        let mut result = Vec::new();
+        while let (rest, Some(entry)) = parse_streaming_entry::<Error<&[u8]>>(input).unwrap() {
+            result.push(entry);
+            input = rest;
+        }
+        result
+    }
+
+    #[test]
+    fn data_descriptor_zip() {
+        let input = include_bytes!("../../assets/data_descriptor.zip");
+        let entries = entries(input);
+
+        assert_eq!(2, entries.len());
+        let hello = entries[0].compressed_data;
+        assert_eq!(b"hello", hello.bytes());
+        assert_eq!(0x3610a686, hello.crc32());
+        let more = entries[1].compressed_data;
+        assert_eq!(CompressionMethod::Deflate, more.compression_method());
+        assert_eq!(21, more.bytes().len());
+        assert_eq!(128, more.uncompressed_size());
+        assert_eq!(0xe8b8e0d1, more.crc32());
+    }
+
+    #[test]
+    fn without_descriptor_signature() {
+        let mut input = include_bytes!("../../assets/data_descriptor.zip").to_vec();
+        input.drain(0x2c..0x30);
+        let entries = entries(&input);
+
+        assert_eq!(2, entries.len());
+        assert_eq!(b"hello", entries[0].compressed_data.bytes());
+    }
+
+    #[test]
+    fn two_files_store() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let entries = entries(input);
+
+        assert_eq!(2, entries.len());
+        assert_eq!(b"world", entries[0].compressed_data.bytes());
+    }
+
+    #[test]
+    fn split_segments_in_order() {
+        let mut input = include_bytes!("../../assets/split.z01").to_vec();
+        input.extend_from_slice(include_bytes!("../../assets/split.zip"));
+        let entries = entries(&input);
+
+        assert_eq!(3, entries.len());
+        assert_eq!(b"first\n", entries[0].compressed_data.bytes());
+    }
+
+    #[test]
+    fn incomplete_until_entry_arrives() {
+        let input = include_bytes!("../../assets/data_descriptor.zip");
+        // The end of the first data descriptor
+        let end = 0x3c;
+        for length in 0..end {
+            let result = parse_streaming_entry::<Error<&[u8]>>(&input[..length]);
+            assert!(matches!(result, Err(Err::Incomplete(_))), "{}", length);
+        }
+        let (rest, entry) = parse_streaming_entry::<Error<&[u8]>>(&input[..end]).unwrap();
+        assert!(rest.is_empty());
+        assert!(entry.is_some());
+    }
+
+    #[test]
+    fn headers_with_sizes() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let (rest, header) = parse_streaming_header::<Error<&[u8]>>(input).unwrap();
+        let (entry, size) = header.unwrap();
+
+        assert_eq!(Some(5), size);
+        assert!(entry.compressed_data.bytes().is_empty());
+        assert_eq!(b"world", &rest[..5]);
+
+        let input = include_bytes!("../../assets/data_descriptor.zip");
+        let (_, header) = parse_streaming_header::<Error<&[u8]>>(input).unwrap();
+        assert_eq!(None, header.unwrap().1);
+
+        // Incomplete until the whole header arrives, not the data
+        let result = parse_streaming_header::<Error<&[u8]>>(&input[..0x20]);
+        assert!(matches!(result, Err(Err::Incomplete(_))));
+    }
+
+    #[test]
+    fn unexpected_signature() {
+        let input = include_bytes!("../../assets/two_files_store.zip");
+        let result = parse_streaming_entry::<Error<&[u8]>>(&input[1..]);
+
+        assert!(matches!(result, Err(Err::Error(_))));
+    }
+}
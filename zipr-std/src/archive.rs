@@ -0,0 +1,746 @@
+use std::{cmp::max, collections::HashMap, path::Path};
+
+use nom::{Finish, Offset};
+use zipr_data::{
+    borrowed::{
+        file::{CentralDirectoryEntry, EndOfCentralDirectory, LocalFileEntry},
+        AsSymbols, ZipEntry,
+    },
+    constants::{
+        DATA_DESCRIPTOR_LENGTH, DATA_DESCRIPTOR_SIGNATURE, END_OF_CENTRAL_DIRECTORY_MIN_SIZE,
+        GENERAL_PURPOSE_DATA_DESCRIPTOR, SPANNING_SIGNATURE,
+    },
+};
+use zipr_domain::{
+    make_zip_entry, structural_issues, ArchiveLayout, ConsistencyPolicy, EntryExtent, Extent,
+    StructuralIssue, StructurePolicy,
+};
+use zipr_nom::{
+    data::{
+        parse_directory_header, parse_end_of_central_directory, parse_local_file_for,
+        parse_local_header,
+    },
+    entry_extent,
+    error::{ParseFailure, Record, ZipError, LOCAL_FILE_HEADER},
+    find_end_of_central_directory, Segments,
+};
+
+use crate::{segment_paths, ArchiveError, EntryReader, MmapArchive};
+
+/// A zip archive over any set of bytes
+///
+/// The central directory is walked once on creation,
+/// recording where each entry is, and indexing the names.
+/// Entries are then parsed on demand, borrowing from the bytes
+pub struct ZipArchive<T> {
+    data: T,
+    /// The disks before the one the end record is on, empty unless split
+    earlier: Vec<T>,
+    prefix: usize,
+    /// The disk the end record is on
+    disk: u16,
+    /// The disk and position the central directory starts at
+    directory_start: (u16, usize),
+    directory_size: usize,
+    /// The central directory, if it runs onto the next disk
+    joined_directory: Option<Vec<u8>>,
+    end_of_directory: usize,
+    /// Where each entry is in the central directory
+    directory: Vec<usize>,
+    /// Entries whose data runs onto the next disk, joined.
+    /// By the disk and offset they start at
+    spanning: HashMap<(u16, u32), Vec<u8>>,
+    names: HashMap<String, usize>,
+    policy: ConsistencyPolicy,
+    structure_policy: StructurePolicy,
+}
+
+/// Where a local file is
+struct Location<'a> {
+    /// The disk, or the entry joined from the disks it is on
+    bytes: &'a [u8],
+    /// Where bytes starts on its disk
+    offset: u64,
+    /// Where the local file is in bytes
+    position: usize,
+    disk: u16,
+}
+
+impl ZipArchive<MmapArchive> {
+    /// Memory maps the file at path and indexes it.
+    /// If it is the last segment of a split archive, eg archive.zip after
+    /// archive.z01, the others are mapped too. Without them only the
+    /// entries on its own disk can be read
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let path = path.as_ref();
+        let map = MmapArchive::open(path)?;
+        let disk = find_end_of_central_directory::<ParseFailure>(map.bytes())
+            .map_or(0, |(_, eocd)| eocd.number_of_this_disk);
+        let segments = segment_paths(path, disk);
+        if segments.is_empty() || !segments.iter().all(|x| x.is_file()) {
+            return ZipArchive::new(map);
+        }
+        let mut disks = Vec::with_capacity(segments.len() + 1);
+        for segment in segments {
+            disks.push(MmapArchive::open(segment)?);
+        }
+        disks.push(map);
+        ZipArchive::from_disks(disks)
+    }
+}
+
+impl<T: AsRef<[u8]>> ZipArchive<T> {
+    /// Indexes the central directory of the bytes
+    pub fn new(data: T) -> Result<Self, ArchiveError> {
+        ZipArchive::from_disks(vec![data])
+    }
+
+    /// Indexes a split archive, given the bytes of every disk in order.
+    /// The last holds the end record. The disks aren't joined, only the
+    /// entries whose data runs from one disk onto the next are copied
+    pub fn from_disks(mut disks: Vec<T>) -> Result<Self, ArchiveError> {
+        let data = match disks.pop() {
+            Some(data) => data,
+            None => {
+                let error = ZipError::out_of_range(0, Record::EndOfCentralDirectory);
+                return Err(ArchiveError::NoEndOfDirectory(error));
+            }
+        };
+        let bytes = data.as_ref();
+        let (_, eocd) = find_end_of_central_directory::<ParseFailure>(bytes)
+            .finish()
+            .map_err(|e| {
+                let error = ZipError::new(bytes, Record::EndOfCentralDirectory, e);
+                ArchiveError::NoEndOfDirectory(error)
+            })?;
+        let end_of_directory = bytes.len() - END_OF_CENTRAL_DIRECTORY_MIN_SIZE - eocd.comment.len();
+
+        let disk = eocd.number_of_this_disk;
+        let split = !disks.is_empty();
+        if split && disks.len() < disk as usize {
+            let disk = disks.len() as u16;
+            return Err(ArchiveError::MissingDisk { disk });
+        }
+        disks.truncate(disk as usize);
+        let prefix = match split {
+            true => 0,
+            false => Segments::single(bytes, &eocd).prefix(),
+        };
+        let directory_disk = eocd.directory_start_disk;
+        let directory_offset = eocd.offset_start_directory;
+        let directory_size = eocd.size_of_directory as usize;
+        let capacity = eocd.total_number_records as usize;
+
+        let mut archive = ZipArchive {
+            data,
+            earlier: disks,
+            prefix,
+            disk,
+            directory_start: (directory_disk, 0),
+            directory_size,
+            joined_directory: None,
+            end_of_directory,
+            directory: Vec::new(),
+            spanning: HashMap::new(),
+            names: HashMap::new(),
+            policy: ConsistencyPolicy::default(),
+            structure_policy: StructurePolicy::default(),
+        };
+        archive.find_directory(directory_disk, directory_offset, directory_size)?;
+        archive.index_directory(capacity)?;
+        archive.spanning = archive.join_spanning()?;
+        Ok(archive)
+    }
+
+    /// Locates the central directory, joining it if it runs onto the next disk
+    fn find_directory(&mut self, disk: u16, offset: u32, size: usize) -> Result<(), ArchiveError> {
+        let (bytes, segments) = self.disk(disk).ok_or(ArchiveError::MissingDisk { disk })?;
+        let out_of_range = |offset: u64| ArchiveError::DirectoryOutOfRange {
+            offset,
+            size: size as u64,
+        };
+        let start = segments
+            .position(disk, offset)
+            .ok_or_else(|| out_of_range(self.prefix as u64 + offset as u64))?;
+        let fits = start
+            .checked_add(size)
+            .is_some_and(|end| end <= bytes.len());
+        if !fits {
+            let joined = match disk < self.disk {
+                true => self.read_across(disk, start, size),
+                false => Vec::new(),
+            };
+            if joined.len() != size {
+                return Err(out_of_range(start as u64));
+            }
+            self.joined_directory = Some(joined);
+        }
+        self.directory_start = (disk, start);
+        Ok(())
+    }
+
+    /// Records where each central directory entry is, and their names
+    fn index_directory(&mut self, capacity: usize) -> Result<(), ArchiveError> {
+        let bytes = self.directory_bytes();
+        let start = self.directory_start.1 as u64;
+        let mut unprocessed = bytes;
+        let mut directory = Vec::with_capacity(capacity);
+        let mut names = HashMap::with_capacity(capacity);
+        while !unprocessed.is_empty() {
+            let offset = bytes.len() - unprocessed.len();
+            let (rem, entry) = parse_directory_header::<ParseFailure>(unprocessed)
+                .finish()
+                .map_err(|e| {
+                    let record = Record::CentralDirectoryHeader(directory.len());
+                    let error = ZipError::new(bytes, record, e).offset_by(start);
+                    ArchiveError::InvalidDirectoryEntry(error)
+                })?;
+            // If a name is duplicated, the first entry wins
+            let name = entry.file_name.to_utf8().collect::<String>();
+            names.entry(name).or_insert(directory.len());
+            directory.push(offset);
+            unprocessed = rem;
+        }
+        self.directory = directory;
+        self.names = names;
+        Ok(())
+    }
+
+    /// Joins the entries whose data runs onto the next disk. Their
+    /// headers can't be split, so those that are are left to fail later
+    fn join_spanning(&self) -> Result<HashMap<(u16, u32), Vec<u8>>, ArchiveError> {
+        let mut spanning = HashMap::new();
+        for index in 0..self.len() {
+            let directory = self.central_directory_entry(index)?;
+            let disk = directory.disk_number_start;
+            let offset = directory.relative_offset;
+            // Nothing comes after the last disk
+            let (bytes, segments) = match self.disk(disk).filter(|_| disk < self.disk) {
+                Some(disk) => disk,
+                None => continue,
+            };
+            let local = match segments.position(disk, offset).and_then(|x| bytes.get(x..)) {
+                Some(local) => local,
+                None => continue,
+            };
+            let (rest, (_, compressed_size)) = match parse_local_header::<ParseFailure>(local) {
+                Ok(header) => header,
+                Err(_) => continue,
+            };
+            let mut length = local.offset(rest);
+            length += max(compressed_size, directory.compressed_size) as usize;
+            if directory.general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0 {
+                length += DATA_DESCRIPTOR_SIGNATURE.len() + DATA_DESCRIPTOR_LENGTH as usize;
+            }
+            if length > local.len() {
+                let start = bytes.len() - local.len();
+                spanning.insert((disk, offset), self.read_across(disk, start, length));
+            }
+        }
+        Ok(spanning)
+    }
+
+    /// The bytes of the disk, and where offsets on it are from
+    fn disk(&self, disk: u16) -> Option<(&[u8], Segments<'static>)> {
+        if disk == self.disk {
+            let prefix = self.prefix;
+            return Some((self.data.as_ref(), Segments::Single { disk, prefix }));
+        }
+        let bytes = self.earlier.get(disk as usize)?.as_ref();
+        Some((bytes, Segments::Single { disk, prefix: 0 }))
+    }
+
+    /// Where the disk would start if the disks were joined
+    fn disk_start(&self, disk: u16) -> u64 {
+        let earlier = self.earlier.iter().take(disk as usize);
+        earlier.map(|x| x.as_ref().len() as u64).sum()
+    }
+
+    /// Copies length bytes from the position on the disk, carrying
+    /// on through the disks after it. Shorter if they run out
+    fn read_across(&self, disk: u16, position: usize, length: usize) -> Vec<u8> {
+        let mut joined = Vec::new();
+        let mut position = position;
+        for (bytes, _) in (disk..=self.disk).map_while(|x| self.disk(x)) {
+            let piece = bytes.get(position..).unwrap_or_default();
+            let wanted = length - joined.len();
+            joined.extend_from_slice(&piece[..wanted.min(piece.len())]);
+            if joined.len() == length {
+                break;
+            }
+            position = 0;
+        }
+        joined
+    }
+
+    /// The central directory
+    fn directory_bytes(&self) -> &[u8] {
+        if let Some(joined) = &self.joined_directory {
+            return joined;
+        }
+        let (disk, start) = self.directory_start;
+        // Checked to be on the disk when it was found
+        let bytes = self.disk(disk).map_or(&[][..], |(bytes, _)| bytes);
+        &bytes[start..start + self.directory_size]
+    }
+
+    /// Sets how strictly local files must match the central directory.
+    /// The default is lenient, only rejecting critical discrepancies
+    pub fn with_policy(mut self, policy: ConsistencyPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets which structural issues check_structure rejects.
+    /// The default is lenient, only rejecting overlapping entries
+    /// and entries outside of the file data
+    pub fn with_structure_policy(mut self, policy: StructurePolicy) -> Self {
+        self.structure_policy = policy;
+        self
+    }
+
+    /// The number of entries in the archive
+    pub fn len(&self) -> usize {
+        self.directory.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directory.is_empty()
+    }
+
+    /// The bytes before the archive, eg a self extracting stub.
+    /// Offsets in the archive are from after these, see prefix_length
+    pub fn prefix(&self) -> &[u8] {
+        &self.data.as_ref()[..self.prefix]
+    }
+
+    /// Where the local file the central directory entry points to is
+    fn local_location(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+    ) -> Result<Location<'_>, ArchiveError> {
+        let disk = directory.disk_number_start;
+        let offset = directory.relative_offset;
+        if let Some(joined) = self.spanning.get(&(disk, offset)) {
+            let (_, segments) = self.disk(disk).ok_or(ArchiveError::MissingDisk { disk })?;
+            let offset = segments.position(disk, offset).unwrap_or_default() as u64;
+            return Ok(Location {
+                bytes: joined,
+                offset,
+                position: 0,
+                disk,
+            });
+        }
+        let (bytes, segments) = self.disk(disk).ok_or(ArchiveError::MissingDisk { disk })?;
+        let position =
+            segments
+                .position(disk, offset)
+                .ok_or(ArchiveError::LocalFileOutOfRange {
+                    offset: self.prefix as u64 + offset as u64,
+                })?;
+        if position > bytes.len() {
+            let offset = position as u64;
+            return Err(ArchiveError::LocalFileOutOfRange { offset });
+        }
+        Ok(Location {
+            bytes,
+            offset: 0,
+            position,
+            disk,
+        })
+    }
+
+    /// The end of central directory record, this holds the archive comment
+    pub fn end_of_central_directory(&self) -> EndOfCentralDirectory<'_> {
+        let bytes = &self.data.as_ref()[self.end_of_directory..];
+        // Validated on creation, so will parse again
+        let (_, eocd) = parse_end_of_central_directory::<ParseFailure>(bytes)
+            .finish()
+            .expect("end of central directory was validated on creation");
+        eocd
+    }
+
+    /// The position of the entry with the name
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    /// The central directory entry at the index
+    pub fn central_directory_entry(
+        &self,
+        index: usize,
+    ) -> Result<CentralDirectoryEntry<'_>, ArchiveError> {
+        let offset = *self
+            .directory
+            .get(index)
+            .ok_or(ArchiveError::FileNotFound)?;
+        let bytes = &self.directory_bytes()[offset..];
+        // Validated on creation, so will parse again
+        let (_, entry) = parse_directory_header::<ParseFailure>(bytes)
+            .finish()
+            .expect("central directory entry was validated on creation");
+        Ok(entry)
+    }
+
+    /// The local file the central directory entry points to.
+    /// This isn't checked against the directory entry
+    pub fn local_file_entry(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+    ) -> Result<LocalFileEntry<'_>, ArchiveError> {
+        self.local_file(directory, Record::Unknown(LOCAL_FILE_HEADER))
+    }
+
+    /// The local file, reporting failures as the record
+    pub(crate) fn local_file(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+        record: Record,
+    ) -> Result<LocalFileEntry<'_>, ArchiveError> {
+        let location = self.local_location(directory)?;
+        let local_bytes = &location.bytes[location.position..];
+        let (_, local) = parse_local_file_for::<ParseFailure>(directory)(local_bytes)
+            .finish()
+            .map_err(|e| {
+                let error = ZipError::new(location.bytes, record, e).offset_by(location.offset);
+                ArchiveError::InvalidLocalFile(error)
+            })?;
+        Ok(local)
+    }
+
+    /// The compressed data of the local file, sized by the central directory
+    /// as the local header may not have the size. Fails with where the data
+    /// starts on its disk if it runs past the end
+    pub(crate) fn directory_sized_data(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+        local: &LocalFileEntry<'_>,
+    ) -> Result<&[u8], u64> {
+        let location = self
+            .local_location(directory)
+            .map_err(|_| directory.relative_offset as u64)?;
+        let start = location.bytes.offset(local.compressed_data.bytes());
+        let size = directory.compressed_size as usize;
+        let data = location.bytes.get(start..).and_then(|x| x.get(..size));
+        data.ok_or(location.offset + start as u64)
+    }
+
+    /// The entry at the index, combining the central directory and local file
+    pub fn by_index(&self, index: usize) -> Result<ZipEntry<'_>, ArchiveError> {
+        let directory = self.central_directory_entry(index)?;
+        let local = self.local_file(&directory, Record::LocalFileHeader(index))?;
+        let location = self.local_location(&directory)?;
+        let offset = location.offset + location.position as u64;
+        self.policy
+            .check(&directory, &local)
+            .map_err(|discrepancy| ArchiveError::Inconsistent {
+                offset,
+                discrepancy,
+            })?;
+        Ok(make_zip_entry(&directory, &local))
+    }
+
+    /// Where every entry is, and any overlaps or unused bytes between them.
+    /// Reads every local header, but not the data. The disks of a split
+    /// archive are placed one after the other, and entries on disks that
+    /// weren't given are skipped. Entries whose local header can't be read
+    /// are reported, and left out of the rest of the checks
+    pub fn structural_issues(&self) -> Result<Vec<StructuralIssue>, ArchiveError> {
+        let mut extents = Vec::with_capacity(self.len());
+        let mut unreadable = Vec::new();
+        for index in 0..self.len() {
+            let directory = self.central_directory_entry(index)?;
+            if self.disk(directory.disk_number_start).is_none() {
+                continue;
+            }
+            match self.entry_extent(index, &directory) {
+                Some(extent) => extents.push(extent),
+                None => unreadable.push(StructuralIssue::UnreadableLocalHeader { index }),
+            }
+        }
+
+        let eocd = self.end_of_central_directory();
+        let file_size = self.disk_start(self.disk) + self.data.as_ref().len() as u64;
+        let (disk, start) = self.directory_start;
+        let directory_start = self.disk_start(disk) + start as u64;
+        let layout = ArchiveLayout {
+            file_size,
+            central_directory: Extent::new(
+                directory_start,
+                directory_start + eocd.size_of_directory as u64,
+            ),
+            end_of_central_directory: Extent::new(
+                self.disk_start(self.disk) + self.end_of_directory as u64,
+                file_size,
+            ),
+        };
+        // The first segment of a split archive starts with a signature
+        let first = self.earlier.first().map(AsRef::as_ref);
+        let spanning = match first.is_some_and(|x| x.starts_with(&SPANNING_SIGNATURE)) {
+            true => Some(StructuralIssue::PrependedData {
+                size: SPANNING_SIGNATURE.len() as u64,
+            }),
+            false => None,
+        };
+        let issues = structural_issues(&layout, &mut extents).filter(|x| Some(*x) != spanning);
+        unreadable.extend(issues);
+        Ok(unreadable)
+    }
+
+    /// Where the entry would be if the disks were joined
+    fn entry_extent(
+        &self,
+        index: usize,
+        directory: &CentralDirectoryEntry<'_>,
+    ) -> Option<EntryExtent> {
+        let location = self.local_location(directory).ok()?;
+        // Found from the start of the local file, wherever that is
+        let segments = Segments::Single {
+            disk: 0,
+            prefix: location.position,
+        };
+        let mut at = *directory;
+        at.disk_number_start = 0;
+        at.relative_offset = 0;
+        let (_, mut entry) =
+            entry_extent::<ParseFailure>(location.bytes, segments, index, &at).ok()?;
+        let start = self.disk_start(location.disk) + location.offset;
+        entry.extent = Extent::new(entry.extent.start + start, entry.extent.end + start);
+        Some(entry)
+    }
+
+    /// Applies the structure policy, giving back the issues it allows as warnings
+    pub fn check_structure(&self) -> Result<Vec<StructuralIssue>, ArchiveError> {
+        let policy = self.structure_policy;
+        let issues = self.structural_issues()?;
+        match issues.iter().find(|x| policy.rejects(x)) {
+            Some(issue) => Err(ArchiveError::Structure(*issue)),
+            None => Ok(issues),
+        }
+    }
+
+    /// The entry with the name. Uses the index, so doesn't scan the archive
+    pub fn by_name(&self, name: &str) -> Result<ZipEntry<'_>, ArchiveError> {
+        let index = self.index_of(name).ok_or(ArchiveError::FileNotFound)?;
+        self.by_index(index)
+    }
+
+    /// Lazily iterates all entries in central directory order
+    pub fn entries(&self) -> impl Iterator<Item = Result<ZipEntry<'_>, ArchiveError>> + '_ {
+        (0..self.len()).map(move |index| self.by_index(index))
+    }
+
+    /// Streams the uncompressed contents of the entry
+    pub fn open<'a>(&'a self, entry: &ZipEntry<'a>) -> EntryReader<'a> {
+        EntryReader::new(entry.compressed_data)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn bytes(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    pub(crate) fn policy(&self) -> ConsistencyPolicy {
+        self.policy
+    }
+
+    /// Gives back the bytes holding the end record.
+    /// The other disks of a split archive are dropped
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::EntryMetadata;
+
+    #[test]
+    fn two_files_store_by_name() {
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let entry = archive.by_name("moredata.txt").unwrap();
+
+        assert_eq!(2, archive.len());
+        assert_eq!(Some(1), archive.index_of("moredata.txt"));
+        assert_eq!("moredata.txt", entry.name());
+    }
+
+    #[test]
+    fn two_files_store_missing() {
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert!(matches!(
+            archive.by_name("missing.txt"),
+            Err(ArchiveError::FileNotFound)
+        ));
+        assert!(matches!(
+            archive.by_index(2),
+            Err(ArchiveError::FileNotFound)
+        ));
+    }
+
+    #[test]
+    fn hello_world_deflate_open() {
+        let bytes = include_bytes!("../../assets/hello_world_deflate.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let entry = archive.by_index(0).unwrap();
+        let mut result = String::new();
+        archive.open(&entry).read_to_string(&mut result).unwrap();
+
+        assert_eq!("world".repeat(43), result);
+    }
+
+    #[test]
+    fn data_descriptor_open() {
+        let bytes = include_bytes!("../../assets/data_descriptor.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let entry = archive.by_index(1).unwrap();
+        let mut result = String::new();
+        archive.open(&entry).read_to_string(&mut result).unwrap();
+
+        assert_eq!("more data, more data, more data\n".repeat(4), result);
+    }
+
+    #[test]
+    fn hello_world_store_with_comment() {
+        let bytes = include_bytes!("../../assets/hello_world_store_with_comment.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert_eq!(
+            b"tricky",
+            archive.end_of_central_directory().comment.as_ref()
+        );
+    }
+
+    #[test]
+    fn overlapping_entries_rejected() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        let second = bytes
+            .windows(4)
+            .rposition(|x| x == zipr_data::constants::CENTRAL_DIRECTORY_HEADER_SIGNATURE)
+            .unwrap();
+        bytes[second + 42..second + 46].copy_from_slice(&[0, 0, 0, 0]);
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert!(matches!(
+            archive.check_structure(),
+            Err(ArchiveError::Structure(StructuralIssue::Overlap { .. }))
+        ));
+    }
+
+    #[test]
+    fn unreadable_local_header_reported() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        let second = bytes
+            .windows(4)
+            .rposition(|x| x == zipr_data::constants::LOCAL_FILE_HEADER_SIGNATURE)
+            .unwrap();
+        bytes[second..second + 4].copy_from_slice(&[0, 0, 0, 0]);
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        let issues = archive.structural_issues().unwrap();
+        assert_eq!(
+            StructuralIssue::UnreadableLocalHeader { index: 1 },
+            issues[0]
+        );
+        assert!(matches!(
+            archive.check_structure(),
+            Err(ArchiveError::Structure(
+                StructuralIssue::UnreadableLocalHeader { index: 1 }
+            ))
+        ));
+        let passed: Vec<bool> = archive.test().map(|x| x.passed()).collect();
+        assert_eq!(vec![true, false], passed);
+    }
+
+    #[test]
+    fn gap_warns() {
+        let mut bytes = include_bytes!("../../assets/hello_world_store.zip").to_vec();
+        // Move the central directory along, leaving unused bytes before it
+        bytes.splice(0x2c..0x2c, *b"junk");
+        let eocd = bytes.len() - END_OF_CENTRAL_DIRECTORY_MIN_SIZE;
+        bytes[eocd + 16..eocd + 20].copy_from_slice(&0x30u32.to_le_bytes());
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert_eq!(
+            vec![StructuralIssue::Gap(Extent::new(0x2c, 0x30))],
+            archive.check_structure().unwrap()
+        );
+        assert!(matches!(
+            archive
+                .with_structure_policy(StructurePolicy::Strict)
+                .check_structure(),
+            Err(ArchiveError::Structure(_))
+        ));
+    }
+
+    #[test]
+    fn self_extracting_prefix() {
+        let stub = b"#!/bin/sh\nexec unzip \"$0\"\n";
+        let mut bytes = stub.to_vec();
+        bytes.extend_from_slice(include_bytes!("../../assets/two_files_store.zip"));
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert_eq!(&stub[..], archive.prefix());
+        let entry = archive.by_name("moredata.txt").unwrap();
+        assert_eq!(1, archive.index_of("moredata.txt").unwrap());
+        assert_eq!(entry, archive.by_index(1).unwrap());
+        assert!(archive.test().all(|x| x.passed()));
+        assert_eq!(
+            vec![StructuralIssue::PrependedData {
+                size: stub.len() as u64
+            }],
+            archive.check_structure().unwrap()
+        );
+    }
+
+    #[test]
+    fn split_segments_from_path() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/split.zip");
+        let archive = ZipArchive::from_path(path).unwrap();
+        let entry = archive.by_name("spanning.txt").unwrap();
+        let mut result = String::new();
+        archive.open(&entry).read_to_string(&mut result).unwrap();
+
+        // Only the entry running from the first disk onto the second is copied
+        assert_eq!(1, archive.spanning.len());
+        assert_eq!(90000, result.len());
+        assert!(archive.test().all(|x| x.passed()));
+        assert!(archive.check_structure().unwrap().is_empty());
+    }
+
+    #[test]
+    fn last_split_segment_partial() {
+        let bytes = include_bytes!("../../assets/split.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+
+        assert_eq!(3, archive.len());
+        assert!(archive.by_name("last.txt").is_ok());
+        assert!(matches!(
+            archive.by_name("first.txt"),
+            Err(ArchiveError::MissingDisk { disk: 0 })
+        ));
+        assert_eq!(1, archive.test().filter(|x| x.passed()).count());
+    }
+
+    #[test]
+    fn inconsistent_name_rejected() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        // The first local file name starts after the 30 byte header
+        bytes[30] = b'X';
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        assert!(matches!(
+            archive.by_index(0),
+            Err(ArchiveError::Inconsistent {
+                offset: 0,
+                discrepancy: zipr_domain::Discrepancy::FileName
+            })
+        ));
+        assert!(archive.by_index(1).is_ok());
+    }
+}
@@ -0,0 +1,73 @@
+use std::{fmt::Display, io};
+
+use zipr_domain::{Discrepancy, StructuralIssue};
+use zipr_nom::error::ZipError;
+
+/// Enum for the ways reading an archive can fail
+/// Offsets are from the start of the archive
+#[derive(Debug)]
+pub enum ArchiveError {
+    IO(io::Error),
+    NoEndOfDirectory(ZipError),
+    DirectoryOutOfRange {
+        offset: u64,
+        size: u64,
+    },
+    InvalidDirectoryEntry(ZipError),
+    LocalFileOutOfRange {
+        offset: u64,
+    },
+    InvalidLocalFile(ZipError),
+    Inconsistent {
+        offset: u64,
+        discrepancy: Discrepancy,
+    },
+    Structure(StructuralIssue),
+    FileNotFound,
+    /// A record is on a disk of a split archive that wasn't given
+    MissingDisk {
+        disk: u16,
+    },
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(e: io::Error) -> Self {
+        ArchiveError::IO(e)
+    }
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::IO(e) => write!(f, "{}", e),
+            ArchiveError::NoEndOfDirectory(e) => {
+                write!(f, "end of central directory not found, {}", e)
+            }
+            ArchiveError::DirectoryOutOfRange { offset, size } => write!(
+                f,
+                "central directory at 0x{:x} (0x{:x} bytes) is outside the file",
+                offset, size
+            ),
+            ArchiveError::InvalidDirectoryEntry(e) => write!(f, "{}", e),
+            ArchiveError::LocalFileOutOfRange { offset } => {
+                write!(f, "local file at 0x{:x} is outside the file", offset)
+            }
+            ArchiveError::InvalidLocalFile(e) => write!(f, "{}", e),
+            ArchiveError::Inconsistent {
+                offset,
+                discrepancy,
+            } => write!(
+                f,
+                "local file at 0x{:x} doesn't match the central directory, {}",
+                offset, discrepancy
+            ),
+            ArchiveError::Structure(issue) => write!(f, "{}", issue),
+            ArchiveError::FileNotFound => write!(f, "file not found in archive"),
+            ArchiveError::MissingDisk { disk } => {
+                write!(f, "disk {} of the split archive is missing", disk + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
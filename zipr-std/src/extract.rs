@@ -0,0 +1,615 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs::{FileTimes, OpenOptions},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use zipr_data::borrowed::{AsSymbols, ZipEntry};
+use zipr_domain::{is_symlink, unix_mode, IsDirectory};
+
+use crate::times::{entry_times, EntryTimes};
+
+/// The reasons an entry name is refused as a path
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnsafePathReason {
+    /// Starts with a slash, eg /etc/passwd or \\server\share
+    Absolute,
+    /// Starts with a drive, eg C:\Windows or C:relative
+    DriveLetter,
+    /// Contains a .. component
+    ParentDirectory,
+    /// Contains a nul character
+    Nul,
+    /// Nothing remains after normalizing
+    Empty,
+    /// Contains a ':', which Windows reads as a drive or a data stream.
+    /// Only refused on Windows
+    Colon,
+    /// A Windows device name such as CON or NUL, which doesn't name a file.
+    /// Only refused on Windows
+    ReservedName,
+}
+
+/// What to do when the output path already exists
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Overwrite {
+    /// Report a conflict and leave the existing file alone
+    #[default]
+    Never,
+    /// Replace existing files
+    Always,
+}
+
+/// Enum for the ways extracting an entry can fail
+#[derive(Debug)]
+pub enum ExtractError {
+    UnsafePath {
+        name: String,
+        reason: UnsafePathReason,
+    },
+    Conflict(PathBuf),
+    SymlinkInPath(PathBuf),
+    IO(io::Error),
+}
+
+impl From<io::Error> for ExtractError {
+    fn from(e: io::Error) -> Self {
+        ExtractError::IO(e)
+    }
+}
+
+impl Display for ExtractError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractError::UnsafePath { name, reason } => {
+                write!(f, "refusing unsafe path {:?} ({:?})", name, reason)
+            }
+            ExtractError::Conflict(path) => write!(f, "{} already exists", path.display()),
+            ExtractError::SymlinkInPath(path) => {
+                write!(f, "refusing to write through symlink {}", path.display())
+            }
+            ExtractError::IO(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+fn is_drive(component: &str) -> bool {
+    let mut chars = component.chars();
+    matches!(
+        (chars.next(), chars.next()),
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic()
+    )
+}
+
+/// Windows treats these as devices in any folder, and with any extension
+#[cfg(windows)]
+fn is_reserved(component: &str) -> bool {
+    const DEVICES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+    let stem = component.split('.').next().unwrap_or_default();
+    let stem = stem.trim_end_matches(' ').to_ascii_uppercase();
+    let numbered = |prefix: &str| {
+        stem.strip_prefix(prefix)
+            .is_some_and(|x| matches!(x.as_bytes(), [b'1'..=b'9']))
+    };
+    DEVICES.contains(&stem.as_str()) || numbered("COM") || numbered("LPT")
+}
+
+/// Refuses components that only mean something else on Windows
+#[cfg(windows)]
+fn check_platform(component: &str) -> Result<(), UnsafePathReason> {
+    if component.contains(':') {
+        Err(UnsafePathReason::Colon)
+    } else if is_reserved(component) {
+        Err(UnsafePathReason::ReservedName)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(windows))]
+fn check_platform(_: &str) -> Result<(), UnsafePathReason> {
+    Ok(())
+}
+
+/// Converts an entry name into a relative path that cannot escape
+/// the directory it is joined onto.
+///
+/// Backslashes are treated as separators, and empty or '.' components
+/// are dropped. Anything that could point outside is refused rather
+/// than rewritten, so a hostile archive can't collide with a real entry
+pub fn sanitize_path(name: &str) -> Result<PathBuf, UnsafePathReason> {
+    if name.contains('\0') {
+        return Err(UnsafePathReason::Nul);
+    }
+    if name.starts_with('/') || name.starts_with('\\') {
+        return Err(UnsafePathReason::Absolute);
+    }
+
+    let mut path = PathBuf::new();
+    for (index, component) in name.split(['/', '\\']).enumerate() {
+        match component {
+            "" | "." => continue,
+            ".." => return Err(UnsafePathReason::ParentDirectory),
+            x if index == 0 && is_drive(x) => return Err(UnsafePathReason::DriveLetter),
+            x => {
+                check_platform(x)?;
+                // Pushing anything but a plain name can replace the whole path
+                let mut parts = Path::new(x).components();
+                match (parts.next(), parts.next()) {
+                    (Some(Component::Normal(_)), None) => path.push(x),
+                    _ => return Err(UnsafePathReason::Absolute),
+                }
+            }
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        Err(UnsafePathReason::Empty)
+    } else {
+        Ok(path)
+    }
+}
+
+/// Writes entries below a root directory
+///
+/// Every entry name is sanitized before it touches the filesystem.
+/// Symlinks are never followed below the root, so an entry can't be
+/// redirected by a link created earlier in the same archive.
+///
+/// By default modification times, unix permissions and symlinks are
+/// restored. Each can be turned off. Directory metadata is applied
+/// in finish, once nothing more will be written inside them
+pub struct Extractor {
+    root: PathBuf,
+    overwrite: Overwrite,
+    restore_times: bool,
+    restore_permissions: bool,
+    restore_symlinks: bool,
+    written: HashSet<PathBuf>,
+    directories: Vec<(PathBuf, EntryTimes, Option<u32>)>,
+}
+
+/// The longest symlink target we will read from an entry
+const SYMLINK_MAX_LENGTH: u64 = 4096;
+
+/// Only the permission bits are restored, never setuid/setgid/sticky
+const PERMISSION_MASK: u32 = 0o777;
+
+impl Extractor {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Extractor {
+            root: root.into(),
+            overwrite: Overwrite::default(),
+            restore_times: true,
+            restore_permissions: true,
+            restore_symlinks: true,
+            written: HashSet::new(),
+            directories: Vec::new(),
+        }
+    }
+
+    /// Sets what to do when an output file already exists
+    pub fn overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Whether to set modified/accessed times from the entry.
+    /// NTFS times are preferred, then the extended timestamp, then dos
+    pub fn restore_times(mut self, restore: bool) -> Self {
+        self.restore_times = restore;
+        self
+    }
+
+    /// Whether to apply unix permissions from the external attributes
+    pub fn restore_permissions(mut self, restore: bool) -> Self {
+        self.restore_permissions = restore;
+        self
+    }
+
+    /// Whether to create symlinks for unix symlink entries.
+    /// When off, the link target is written as a regular file
+    pub fn restore_symlinks(mut self, restore: bool) -> Self {
+        self.restore_symlinks = restore;
+        self
+    }
+
+    /// The directory entries are extracted into
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The sanitized location the entry will be written to
+    pub fn target(&self, entry: &ZipEntry<'_>) -> Result<PathBuf, ExtractError> {
+        let name = entry.file_name.to_utf8().collect::<String>();
+        let relative = match sanitize_path(&name) {
+            Ok(relative) => relative,
+            Err(reason) => return Err(ExtractError::UnsafePath { name, reason }),
+        };
+        let target = self.root.join(relative);
+        if !target.starts_with(&self.root) {
+            let reason = UnsafePathReason::Absolute;
+            return Err(ExtractError::UnsafePath { name, reason });
+        }
+        Ok(target)
+    }
+
+    /// Fails if anything between the root and the target is a symlink
+    fn check_no_symlinks(&self, target: &Path) -> Result<(), ExtractError> {
+        let mut ancestors = target
+            .ancestors()
+            .skip(1)
+            .take_while(|x| *x != self.root.as_path());
+        ancestors.try_for_each(|ancestor| match ancestor.symlink_metadata() {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                Err(ExtractError::SymlinkInPath(ancestor.to_path_buf()))
+            }
+            _ => Ok(()),
+        })
+    }
+
+    /// Creates the directory and any missing parents
+    /// An existing directory is not a conflict, but anything else is
+    fn create_directory(&self, target: &Path) -> Result<(), ExtractError> {
+        match target.symlink_metadata() {
+            Ok(meta) if meta.is_dir() => Ok(()),
+            Ok(_) => Err(ExtractError::Conflict(target.to_path_buf())),
+            Err(_) => Ok(std::fs::create_dir_all(target)?),
+        }
+    }
+
+    /// Makes room for a new file or link at target
+    fn clear_target(&self, target: &Path) -> Result<(), ExtractError> {
+        if let Ok(existing) = target.symlink_metadata() {
+            // An entry duplicated inside the archive is always a conflict
+            let duplicate = self.written.contains(target);
+            if duplicate || self.overwrite == Overwrite::Never || existing.is_dir() {
+                return Err(ExtractError::Conflict(target.to_path_buf()));
+            }
+            // Remove rather than truncate, so an existing symlink isn't followed
+            std::fs::remove_file(target)?;
+        }
+        Ok(())
+    }
+
+    fn write_file<R: Read>(
+        &self,
+        entry: &ZipEntry<'_>,
+        target: &Path,
+        reader: &mut R,
+    ) -> Result<(), ExtractError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(target)?;
+        if let Err(e) = io::copy(reader, &mut file) {
+            // Don't leave a partial file behind, eg on a crc failure
+            let _ = std::fs::remove_file(target);
+            return Err(e.into());
+        }
+        if self.restore_times {
+            let times = entry_times(entry);
+            let mut file_times = FileTimes::new();
+            if let Some(modified) = times.modified {
+                file_times = file_times.set_modified(modified);
+            }
+            if let Some(accessed) = times.accessed {
+                file_times = file_times.set_accessed(accessed);
+            }
+            file.set_times(file_times)?;
+        }
+        if self.restore_permissions {
+            if let Some(mode) = unix_mode(entry) {
+                set_mode(target, mode)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn write_symlink<R: Read>(&self, target: &Path, reader: &mut R) -> Result<(), ExtractError> {
+        let mut link = String::new();
+        reader.take(SYMLINK_MAX_LENGTH).read_to_string(&mut link)?;
+        std::os::unix::fs::symlink(link, target)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn write_symlink<R: Read>(&self, target: &Path, reader: &mut R) -> Result<(), ExtractError> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(target)?;
+        io::copy(&mut reader.take(SYMLINK_MAX_LENGTH), &mut file)?;
+        Ok(())
+    }
+
+    /// Writes the entry to its location, returning where it was written
+    /// Directory entries are created, files have the contents of reader
+    /// written to them. Missing parent directories are created either way
+    pub fn extract<R: Read>(
+        &mut self,
+        entry: &ZipEntry<'_>,
+        mut reader: R,
+    ) -> Result<PathBuf, ExtractError> {
+        let target = self.target(entry)?;
+        self.check_no_symlinks(&target)?;
+
+        if entry.is_directory() {
+            self.create_directory(&target)?;
+            let times = match self.restore_times {
+                true => entry_times(entry),
+                false => EntryTimes::default(),
+            };
+            let mode = unix_mode(entry).filter(|_| self.restore_permissions);
+            self.directories.push((target.clone(), times, mode));
+            return Ok(target);
+        }
+        if let Some(parent) = target.parent() {
+            self.create_directory(parent)?;
+        }
+
+        self.clear_target(&target)?;
+        if self.restore_symlinks && is_symlink(entry) {
+            self.write_symlink(&target, &mut reader)?;
+        } else {
+            self.write_file(entry, &target, &mut reader)?;
+        }
+        self.written.insert(target.clone());
+        Ok(target)
+    }
+
+    /// Applies the times and permissions of directory entries.
+    /// This is done last, as writing files inside a directory
+    /// changes its modification time
+    pub fn finish(self) -> Result<(), ExtractError> {
+        for (directory, times, mode) in self.directories.iter().rev() {
+            if times.modified.is_some() || times.accessed.is_some() {
+                let accessed = times.accessed.or(times.modified);
+                set_directory_times(directory, accessed, times.modified)?;
+            }
+            if let Some(mode) = mode {
+                set_mode(directory, *mode)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::Permissions::from_mode(mode & PERMISSION_MASK);
+    std::fs::set_permissions(path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+fn set_directory_times(
+    path: &Path,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+) -> io::Result<()> {
+    let mut file_times = FileTimes::new();
+    if let Some(modified) = modified {
+        file_times = file_times.set_modified(modified);
+    }
+    if let Some(accessed) = accessed {
+        file_times = file_times.set_accessed(accessed);
+    }
+    open_directory(path)?.set_times(file_times)
+}
+
+#[cfg(windows)]
+fn open_directory(path: &Path) -> io::Result<std::fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    // Directories can only be opened as handles with backup semantics,
+    // and setting times needs write access to their attributes
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    std::fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(not(windows))]
+fn open_directory(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::File::open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZipArchive;
+
+    #[test]
+    fn sanitize_relative() {
+        let expected: PathBuf = ["a", "b", "c.txt"].iter().collect();
+        assert_eq!(Ok(expected.clone()), sanitize_path("a/b/c.txt"));
+        assert_eq!(Ok(expected.clone()), sanitize_path("./a//b/./c.txt"));
+        assert_eq!(Ok(expected), sanitize_path("a\\b\\c.txt"));
+    }
+
+    #[test]
+    fn sanitize_rejects_escapes() {
+        use UnsafePathReason::*;
+        assert_eq!(Err(ParentDirectory), sanitize_path("../../etc/cron.d/x"));
+        assert_eq!(Err(ParentDirectory), sanitize_path("a/../../x"));
+        assert_eq!(Err(ParentDirectory), sanitize_path("a\\..\\..\\x"));
+        assert_eq!(Err(Absolute), sanitize_path("/abs/path"));
+        assert_eq!(Err(Absolute), sanitize_path("\\\\server\\share"));
+        assert_eq!(Err(DriveLetter), sanitize_path("C:\\Windows\\x"));
+        assert_eq!(Err(DriveLetter), sanitize_path("c:relative"));
+        assert_eq!(Err(Nul), sanitize_path("a\0b"));
+        assert_eq!(Err(Empty), sanitize_path("./"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn sanitize_rejects_windows_names() {
+        use UnsafePathReason::*;
+        assert_eq!(Err(Colon), sanitize_path("a/C:evil"));
+        assert_eq!(Err(Colon), sanitize_path("a/file.txt:stream"));
+        assert_eq!(Err(ReservedName), sanitize_path("a/CON"));
+        assert_eq!(Err(ReservedName), sanitize_path("nul.txt"));
+        assert_eq!(Err(ReservedName), sanitize_path("a/com1"));
+        assert!(sanitize_path("a/console.txt").is_ok());
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn sanitize_allows_colons_elsewhere() {
+        let expected = PathBuf::from("a").join("C:b");
+        assert_eq!(Ok(expected), sanitize_path("a/C:b"));
+        assert!(sanitize_path("CON").is_ok());
+    }
+
+    #[test]
+    fn extract_reports_conflict() {
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let entry = archive.by_index(0).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hello.txt"), b"existing").unwrap();
+
+        let mut extractor = Extractor::new(dir.path());
+        let result = extractor.extract(&entry, archive.open(&entry));
+
+        assert!(matches!(result, Err(ExtractError::Conflict(_))));
+        assert_eq!(
+            b"existing",
+            &std::fs::read(dir.path().join("hello.txt")).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn extract_overwrite() {
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let entry = archive.by_index(0).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hello.txt"), b"existing").unwrap();
+
+        let mut extractor = Extractor::new(dir.path()).overwrite(Overwrite::Always);
+        extractor.extract(&entry, archive.open(&entry)).unwrap();
+
+        assert_eq!(
+            b"world",
+            &std::fs::read(dir.path().join("hello.txt")).unwrap()[..]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_refuses_symlinked_directory() {
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut entry = archive.by_index(0).unwrap();
+        entry.file_name = core::convert::TryFrom::try_from(&b"link/hello.txt"[..]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        let mut extractor = Extractor::new(dir.path());
+        let result = extractor.extract(&entry, archive.open(&entry));
+
+        assert!(matches!(result, Err(ExtractError::SymlinkInPath(_))));
+        assert!(!outside.path().join("hello.txt").exists());
+    }
+
+    #[test]
+    fn extract_creates_directories() {
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut directory = archive.by_index(0).unwrap();
+        directory.file_name = core::convert::TryFrom::try_from(&b"a/"[..]).unwrap();
+        let mut file = archive.by_index(0).unwrap();
+        file.file_name = core::convert::TryFrom::try_from(&b"a/b/c/hello.txt"[..]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut extractor = Extractor::new(dir.path());
+        extractor
+            .extract(&directory, archive.open(&directory))
+            .unwrap();
+        extractor.extract(&file, archive.open(&file)).unwrap();
+
+        assert!(dir.path().join("a").is_dir());
+        let written = std::fs::read(dir.path().join("a/b/c/hello.txt")).unwrap();
+        assert_eq!(b"world", &written[..]);
+    }
+
+    #[test]
+    fn extract_restores_extended_timestamp() {
+        use zipr_data::borrowed::extra_field::{extended_timestamp::ExtendedTimestamp, ExtraField};
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut entry = archive.by_index(0).unwrap();
+        entry.extra_field = ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+            flags: 0b11,
+            modify: Some(1_000_000_000),
+            access: Some(1_000_000_100),
+            create: None,
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut extractor = Extractor::new(dir.path());
+        let target = extractor.extract(&entry, archive.open(&entry)).unwrap();
+
+        let modified = std::fs::metadata(target).unwrap().modified().unwrap();
+        let expected = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        assert_eq!(expected, modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_restores_permissions_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+        use zipr_data::HostCompatibility;
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut file = archive.by_index(0).unwrap();
+        file.version_made_by.host = HostCompatibility::UNIX;
+        file.external_file_attributes = 0o100750 << 16;
+        let mut link = archive.by_index(1).unwrap();
+        link.version_made_by.host = HostCompatibility::UNIX;
+        link.external_file_attributes = 0o120777 << 16;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut extractor = Extractor::new(dir.path());
+        let file_target = extractor.extract(&file, archive.open(&file)).unwrap();
+        let link_target = extractor.extract(&link, archive.open(&link)).unwrap();
+
+        let mode = std::fs::metadata(file_target).unwrap().permissions().mode();
+        assert_eq!(0o750, mode & 0o777);
+        let meta = std::fs::symlink_metadata(&link_target).unwrap();
+        assert!(meta.file_type().is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_symlink_as_file_when_disabled() {
+        use zipr_data::HostCompatibility;
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut link = archive.by_index(0).unwrap();
+        link.version_made_by.host = HostCompatibility::UNIX;
+        link.external_file_attributes = 0o120777 << 16;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut extractor = Extractor::new(dir.path()).restore_symlinks(false);
+        let target = extractor.extract(&link, archive.open(&link)).unwrap();
+
+        let meta = std::fs::symlink_metadata(&target).unwrap();
+        assert!(meta.file_type().is_file());
+        assert_eq!(b"world", &std::fs::read(target).unwrap()[..]);
+    }
+}
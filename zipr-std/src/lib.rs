@@ -1,9 +1,36 @@
-use std::path::PathBuf;
+mod archive;
+mod error;
+mod extract;
+mod metadata;
+mod mmap;
+mod reader;
+mod seek;
+mod split;
+mod stream;
+mod times;
+mod verify;
+mod walk;
+
+use std::{convert::TryFrom, path::PathBuf};
 
 use zipr_data::{
     borrowed::{AsSymbols, ZipPath},
-    DosDate, DosTime,
+    DosDate, DosDateTime, DosDateTimeError, DosTime,
 };
+
+pub use archive::*;
+pub use error::*;
+pub use extract::*;
+pub use metadata::*;
+pub use mmap::*;
+pub use reader::*;
+pub use seek::*;
+pub use split::*;
+pub use stream::*;
+pub use times::{ModifiedAt, ToDateTime, ToDosDateTime, ToSystemTime, ToWinTimestamp};
+pub use verify::*;
+pub use walk::*;
+
 pub trait ToPath {
     fn to_path(&self) -> PathBuf;
 }
@@ -16,27 +43,29 @@ impl<'a> ToPath for ZipPath<'a> {
 }
 
 pub trait ToNaiveDate {
-    fn to_date(&self) -> chrono::NaiveDate;
+    fn to_date(&self) -> Result<chrono::NaiveDate, DosDateTimeError>;
 }
 
 impl ToNaiveDate for DosDate {
-    fn to_date(&self) -> chrono::NaiveDate {
-        let year = self.year();
-        let month = self.month();
-        let day = self.day();
-        chrono::NaiveDate::from_ymd(year.into(), month.into(), day.into())
+    fn to_date(&self) -> Result<chrono::NaiveDate, DosDateTimeError> {
+        let date = DosDateTime {
+            date: *self,
+            time: DosTime::from_u16_unchecked(0),
+        };
+        Ok(chrono::NaiveDateTime::try_from(date)?.date())
     }
 }
 
 pub trait ToNaiveTime {
-    fn to_time(&self) -> chrono::NaiveTime;
+    fn to_time(&self) -> Result<chrono::NaiveTime, DosDateTimeError>;
 }
 
 impl ToNaiveTime for DosTime {
-    fn to_time(&self) -> chrono::NaiveTime {
-        let hour = self.hour();
-        let min = self.min();
-        let sec = self.sec();
-        chrono::NaiveTime::from_hms(hour.into(), min.into(), sec.into())
+    fn to_time(&self) -> Result<chrono::NaiveTime, DosDateTimeError> {
+        let time = DosDateTime {
+            date: DosDateTime::MIN.date,
+            time: *self,
+        };
+        Ok(chrono::NaiveDateTime::try_from(time)?.time())
     }
 }
@@ -0,0 +1,104 @@
+use std::{convert::TryFrom, time::SystemTime};
+
+use zipr_data::{
+    borrowed::{AsSymbols, OEM437Symbols, ZipEntry},
+    CompressionMethod, DosDateTime, DosDateTimeError,
+};
+
+use crate::times::entry_times;
+
+/// Convenience accessors for the properties of an entry
+pub trait EntryMetadata {
+    /// The name of the entry, decoded from OEM437
+    fn name(&self) -> String;
+    /// The uncompressed size in bytes
+    fn size(&self) -> u32;
+    fn compressed_size(&self) -> u32;
+    fn crc32(&self) -> u32;
+    fn compression_method(&self) -> CompressionMethod;
+    /// The dos modification date and time, which may not be a valid date
+    fn last_modified(&self) -> Result<chrono::NaiveDateTime, DosDateTimeError>;
+    /// The most precise modification time available.
+    /// NTFS, then the extended timestamp, then the dos fields
+    fn modified(&self) -> Option<SystemTime>;
+    /// The entry comment, decoded from OEM437
+    fn comment(&self) -> String;
+}
+
+impl EntryMetadata for ZipEntry<'_> {
+    fn name(&self) -> String {
+        self.file_name.to_utf8().collect()
+    }
+
+    fn size(&self) -> u32 {
+        self.compressed_data.uncompressed_size()
+    }
+
+    fn compressed_size(&self) -> u32 {
+        self.compressed_data.bytes().len() as u32
+    }
+
+    fn crc32(&self) -> u32 {
+        self.compressed_data.crc32()
+    }
+
+    fn compression_method(&self) -> CompressionMethod {
+        self.compressed_data.compression_method()
+    }
+
+    fn last_modified(&self) -> Result<chrono::NaiveDateTime, DosDateTimeError> {
+        let dos = DosDateTime {
+            date: self.file_modification_date,
+            time: self.file_modification_time,
+        };
+        chrono::NaiveDateTime::try_from(dos)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        entry_times(self).modified
+    }
+
+    fn comment(&self) -> String {
+        OEM437Symbols::from(self.comment).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use zipr_data::{
+        borrowed::extra_field::{extended_timestamp::ExtendedTimestamp, ntfs::NTFS, ExtraField},
+        WinTimestamp,
+    };
+
+    use super::*;
+    use crate::ZipArchive;
+
+    #[test]
+    fn modified_prefers_precise_times() {
+        let bytes = include_bytes!("../../assets/hello_world_store.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let mut entry = archive.by_index(0).unwrap();
+        let dos = entry.modified().unwrap();
+
+        entry.extra_field = ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+            flags: 0b1,
+            modify: Some(1_000_000_000),
+            access: None,
+            create: None,
+        });
+        let extended = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(Some(extended), entry.modified());
+
+        let mtime = WinTimestamp::from_unix(1_000_000_000, 500).unwrap();
+        let zero = WinTimestamp::from_ticks(0);
+        entry.extra_field = ExtraField::NTFS(NTFS {
+            mtime,
+            ctime: zero,
+            atime: zero,
+        });
+        assert_eq!(Some(extended + Duration::from_nanos(500)), entry.modified());
+        assert_ne!(Some(dos), entry.modified());
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fs::File, io, ops::Deref, path::Path};
+
+use memmap2::Mmap;
+use zipr_nom::iter::{zip_entry_iter, ZipEntryIterator};
+
+/// A zip archive backed by a memory map
+///
+/// The borrowed zipr-data types point straight into the map,
+/// so nothing is copied. Only the pages the parsers touch are
+/// read from disk, eg the central directory and local headers
+/// when listing
+pub struct MmapArchive {
+    map: Mmap,
+}
+
+impl MmapArchive {
+    /// Memory maps the file at path
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        MmapArchive::from_file(&file)
+    }
+
+    /// Memory maps an already opened file
+    /// Note: the file must not be truncated while the map is alive
+    pub fn from_file(file: &File) -> io::Result<Self> {
+        //SAFETY: The map is read only. Modifying the underlying file while it is
+        //mapped is undefined, the same caveat as every other mmap consumer.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(MmapArchive { map })
+    }
+
+    /// The raw bytes of the archive
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Lazily iterates the zip entries in the archive
+    pub fn entries(&self) -> ZipEntryIterator<'_> {
+        zip_entry_iter(self.bytes())
+    }
+}
+
+/// Allows the archive to be passed anywhere a slice is expected
+/// eg straight into the zipr-nom parsers
+impl Deref for MmapArchive {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.bytes()
+    }
+}
+
+impl AsRef<[u8]> for MmapArchive {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_files_store_entries() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/two_files_store.zip");
+        let archive = MmapArchive::open(path).unwrap();
+        let entries = archive.entries().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(b"moredata.txt", entries[1].file_name.as_ref().as_ref());
+    }
+}
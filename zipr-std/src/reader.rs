@@ -0,0 +1,94 @@
+use std::io::{self, Read};
+
+use zipr_compression::{DecompressError, DecompressStream, Decompressor};
+use zipr_data::{borrowed::file::CompressedData, CompressionMethod};
+
+/// Streams the uncompressed bytes of an entry
+///
+/// The crc32 and size are verified when the end is reached,
+/// a mismatch is reported as an InvalidData io error
+pub struct EntryReader<'a> {
+    stream: DecompressStream<'a>,
+}
+
+impl<'a> EntryReader<'a> {
+    pub fn new(data: CompressedData<'a>) -> Self {
+        let stream = DecompressStream::new(data);
+        EntryReader { stream }
+    }
+}
+
+impl Read for EntryReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf).map_err(invalid_data)
+    }
+}
+
+/// How much compressed data DecompressReader reads at once
+const INPUT_SIZE: usize = 32 * 1024;
+
+/// Streams the uncompressed bytes of an entry whose compressed data
+/// is read as it is needed, eg from a pipe or SeekArchive::local_data
+///
+/// The source should give exactly the compressed data. Like EntryReader,
+/// the crc32 and size are verified when the end is reached
+pub struct DecompressReader<R> {
+    source: R,
+    decompressor: Decompressor,
+    input: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// The source has nothing more to give
+    exhausted: bool,
+}
+
+impl<R: Read> DecompressReader<R> {
+    pub fn new(
+        source: R,
+        compression_method: CompressionMethod,
+        compressed_size: u64,
+        uncompressed_size: u32,
+        crc32: u32,
+    ) -> Self {
+        DecompressReader {
+            source,
+            decompressor: Decompressor::new(
+                compression_method,
+                compressed_size,
+                uncompressed_size,
+                crc32,
+            ),
+            input: vec![0; INPUT_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            exhausted: false,
+        }
+    }
+}
+
+impl<R: Read> Read for DecompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let input = &self.input[self.start..self.end];
+            let (consumed, written) = self
+                .decompressor
+                .decompress(input, buf, self.exhausted)
+                .map_err(invalid_data)?;
+            self.start += consumed;
+            if written > 0 || buf.is_empty() || self.decompressor.is_done() {
+                return Ok(written);
+            }
+            // Nothing could be written without more input
+            self.input.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            let read = self.source.read(&mut self.input[self.end..])?;
+            self.exhausted = read == 0;
+            self.end += read;
+        }
+    }
+}
+
+fn invalid_data(e: DecompressError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))
+}
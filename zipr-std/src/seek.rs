@@ -0,0 +1,425 @@
+use std::{
+    cell::{RefCell, RefMut},
+    cmp::min,
+    convert::TryFrom,
+    io::{self, Read, Seek, SeekFrom, Take},
+};
+
+use nom::Finish;
+use zipr_data::{
+    borrowed::file::{CentralDirectoryEntry, EndOfCentralDirectory, LocalFileEntry},
+    constants::{
+        CENTRAL_DIRECTORY_HEADER_SIGNATURE, END_OF_CENTRAL_DIRECTORY_MIN_SIZE,
+        GENERAL_PURPOSE_DATA_DESCRIPTOR, LOCAL_FILE_MIN_LENGTH,
+    },
+};
+use zipr_nom::{
+    data::{parse_directory_header, parse_local_file_for},
+    error::{ParseFailure, Record, ZipError, LOCAL_FILE_HEADER},
+    find_end_of_central_directory,
+};
+
+use crate::ArchiveError;
+
+/// The largest the end of central directory record can be
+/// The fixed part plus the maximum comment length
+const END_OF_CENTRAL_DIRECTORY_MAX_SIZE: u64 = END_OF_CENTRAL_DIRECTORY_MIN_SIZE as u64 + 0xFFFF;
+
+/// A zip archive read from anything that is Read + Seek
+///
+/// Only the tail of the source and the central directory are
+/// held in memory. Local files are read on demand, and their data can
+/// be streamed with local_data, so this is suitable for archives that
+/// are much larger than memory. Sizes are checked against the length
+/// of the source before anything is allocated
+pub struct SeekArchive<R> {
+    reader: RefCell<R>,
+    length: u64,
+    prefix: u64,
+    /// Only this disk of a split archive can be read
+    disk: u16,
+    end_of_directory: Vec<u8>,
+    directory_start: u64,
+    directory: Vec<u8>,
+}
+
+impl<R: Read + Seek> SeekArchive<R> {
+    /// Locates the central directory and loads it
+    pub fn new(mut reader: R) -> Result<Self, ArchiveError> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        let tail_length = min(length, END_OF_CENTRAL_DIRECTORY_MAX_SIZE);
+        let tail_start = length - tail_length;
+        let tail = read_at(&mut reader, tail_start, tail_length as usize)?;
+
+        let (directory_offset, directory_size, disk, end_of_directory) = {
+            let (_, eocd) = find_end_of_central_directory::<ParseFailure>(&tail)
+                .finish()
+                .map_err(|e| {
+                    let error = ZipError::new(&tail, Record::EndOfCentralDirectory, e);
+                    ArchiveError::NoEndOfDirectory(error.offset_by(tail_start))
+                })?;
+            // Keep only the record, the comment needs to outlive the tail
+            let record_length = END_OF_CENTRAL_DIRECTORY_MIN_SIZE + eocd.comment.len();
+            let record = tail[tail.len() - record_length..].to_vec();
+            if eocd.directory_start_disk != eocd.number_of_this_disk {
+                let disk = eocd.directory_start_disk;
+                return Err(ArchiveError::MissingDisk { disk });
+            }
+            (
+                eocd.offset_start_directory as u64,
+                eocd.size_of_directory as usize,
+                eocd.number_of_this_disk,
+                record,
+            )
+        };
+
+        // See prefix_length, the directory should be right before the record
+        let record_start = length - end_of_directory.len() as u64;
+        if directory_offset + directory_size as u64 > record_start {
+            return Err(ArchiveError::DirectoryOutOfRange {
+                offset: directory_offset,
+                size: directory_size as u64,
+            });
+        }
+        let mut prefix = record_start
+            .saturating_sub(directory_size as u64)
+            .saturating_sub(directory_offset);
+        let mut directory = read_at(&mut reader, directory_offset + prefix, directory_size)?;
+        if directory_size != 0 && !directory.starts_with(&CENTRAL_DIRECTORY_HEADER_SIGNATURE) {
+            prefix = 0;
+            directory = read_at(&mut reader, directory_offset, directory_size)?;
+        }
+        let directory_start = directory_offset + prefix;
+
+        Ok(SeekArchive {
+            reader: RefCell::new(reader),
+            length,
+            prefix,
+            disk,
+            end_of_directory,
+            directory_start,
+            directory,
+        })
+    }
+
+    /// The length of the bytes before the archive, eg a self extracting stub.
+    /// See prefix_length
+    pub fn prefix_length(&self) -> u64 {
+        self.prefix
+    }
+
+    /// Reads the bytes before the archive
+    pub fn read_prefix(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut reader = self.reader()?;
+        Ok(read_at(&mut *reader, 0, self.prefix as usize)?)
+    }
+
+    /// The end of central directory record
+    pub fn end_of_central_directory(&self) -> EndOfCentralDirectory<'_> {
+        // This was parsed successfully in new, so will parse again
+        let (_, eocd) = find_end_of_central_directory::<ParseFailure>(&self.end_of_directory)
+            .finish()
+            .expect("end of central directory was validated on creation");
+        eocd
+    }
+
+    /// Lazily iterates over the already loaded central directory
+    pub fn directory_entries(&self) -> DirectoryEntries<'_> {
+        DirectoryEntries {
+            unprocessed: &self.directory,
+            offset: self.directory_start,
+            index: 0,
+            errored: false,
+        }
+    }
+
+    /// Reads the fixed part of the local header, giving where it is,
+    /// the header, and the lengths of the name and extra field, and the data
+    fn local_extent(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+    ) -> Result<(u64, Vec<u8>, u64, u64), ArchiveError> {
+        let disk = directory.disk_number_start;
+        if disk != self.disk {
+            return Err(ArchiveError::MissingDisk { disk });
+        }
+        let offset = self.prefix + directory.relative_offset as u64;
+        let out_of_range = ArchiveError::LocalFileOutOfRange { offset };
+        let header_length = LOCAL_FILE_MIN_LENGTH as u64;
+        if offset + header_length > self.length {
+            return Err(out_of_range);
+        }
+        let mut reader = self.reader()?;
+        let header = read_at(&mut *reader, offset, header_length as usize)?;
+
+        let general_purpose = le_u16_at(&header, 6);
+        let mut compressed_size = le_u32_at(&header, 18) as u64;
+        // The size may be in a data descriptor after the data instead
+        if general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0 && compressed_size == 0 {
+            compressed_size = directory.compressed_size as u64;
+        }
+        let names = le_u16_at(&header, 26) as u64 + le_u16_at(&header, 28) as u64;
+        if offset + header_length + names + compressed_size > self.length {
+            return Err(out_of_range);
+        }
+        Ok((offset, header, names, compressed_size))
+    }
+
+    /// Reads the local file header and its data for the directory entry
+    /// The bytes are stored in the supplied buffer, which the entry borrows from.
+    /// See local_data to stream the data instead
+    pub fn read_local_file<'b>(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+        buffer: &'b mut Vec<u8>,
+    ) -> Result<LocalFileEntry<'b>, ArchiveError> {
+        let (offset, header, names, compressed_size) = self.local_extent(directory)?;
+        let remaining = usize::try_from(names + compressed_size).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                "local file is too large to buffer",
+            )
+        })?;
+        *buffer = header;
+        let header_length = buffer.len();
+        buffer.resize(header_length + remaining, 0);
+        self.reader()?.read_exact(&mut buffer[header_length..])?;
+
+        let (_, entry) = parse_local_file_for::<ParseFailure>(directory)(buffer)
+            .finish()
+            .map_err(|e| {
+                let record = Record::Unknown(LOCAL_FILE_HEADER);
+                let error = ZipError::new(buffer, record, e).offset_by(offset);
+                ArchiveError::InvalidLocalFile(error)
+            })?;
+        Ok(entry)
+    }
+
+    /// The compressed data of the entry, read lazily from the source.
+    /// Nothing is buffered, so entries larger than memory can be read.
+    /// The source is borrowed until the reader is dropped, other reads fail until then
+    pub fn local_data(
+        &self,
+        directory: &CentralDirectoryEntry<'_>,
+    ) -> Result<Take<Source<'_, R>>, ArchiveError> {
+        let (offset, header, names, compressed_size) = self.local_extent(directory)?;
+        let mut reader = self.reader()?;
+        reader.seek(SeekFrom::Start(offset + header.len() as u64 + names))?;
+        Ok(Source(reader).take(compressed_size))
+    }
+
+    fn reader(&self) -> io::Result<RefMut<'_, R>> {
+        self.reader
+            .try_borrow_mut()
+            .map_err(|_| io::Error::other("the source is being read by local_data"))
+    }
+
+    /// Gives back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+}
+
+/// The source of a SeekArchive, borrowed while local data is read
+pub struct Source<'a, R>(RefMut<'a, R>);
+
+impl<R: Read> Read for Source<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+fn le_u16_at(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([bytes[position], bytes[position + 1]])
+}
+
+fn le_u32_at(bytes: &[u8], position: usize) -> u32 {
+    let mut le = [0u8; 4];
+    le.copy_from_slice(&bytes[position..position + 4]);
+    u32::from_le_bytes(le)
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, length: usize) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buffer = vec![0; length];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Iterator over the central directory held by a SeekArchive
+pub struct DirectoryEntries<'a> {
+    unprocessed: &'a [u8],
+    offset: u64,
+    index: usize,
+    errored: bool,
+}
+
+impl<'a> Iterator for DirectoryEntries<'a> {
+    type Item = Result<CentralDirectoryEntry<'a>, ArchiveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.errored || self.unprocessed.is_empty() {
+            return None;
+        }
+        match parse_directory_header::<ParseFailure>(self.unprocessed).finish() {
+            Err(e) => {
+                self.errored = true;
+                let record = Record::CentralDirectoryHeader(self.index);
+                let error = ZipError::new(self.unprocessed, record, e).offset_by(self.offset);
+                Some(Err(ArchiveError::InvalidDirectoryEntry(error)))
+            }
+            Ok((rem, entry)) => {
+                self.offset += (self.unprocessed.len() - rem.len()) as u64;
+                self.index += 1;
+                self.unprocessed = rem;
+                Some(Ok(entry))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn two_files_store_directory() {
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let entries = archive
+            .directory_entries()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(2, entries.len());
+        assert_eq!(44, entries[1].relative_offset);
+    }
+
+    #[test]
+    fn two_files_store_local_file() {
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let entry = archive.directory_entries().nth(1).unwrap().unwrap();
+        let mut buffer = Vec::new();
+        let local = archive.read_local_file(&entry, &mut buffer).unwrap();
+
+        assert_eq!(entry.file_name, local.file_name);
+        assert_eq!(entry.crc32, local.compressed_data.crc32());
+    }
+
+    #[test]
+    fn local_data_streamed() {
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let entry = archive.directory_entries().nth(1).unwrap().unwrap();
+        let mut data = Vec::new();
+        let mut reader = archive.local_data(&entry).unwrap();
+        reader.read_to_end(&mut data).unwrap();
+
+        assert_eq!(entry.compressed_size as usize, data.len());
+        let mut buffer = Vec::new();
+        assert!(archive.read_local_file(&entry, &mut buffer).is_err());
+        drop(reader);
+        let local = archive.read_local_file(&entry, &mut buffer).unwrap();
+        assert_eq!(local.compressed_data.bytes(), &data[..]);
+    }
+
+    #[test]
+    fn oversized_local_file_rejected() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        // The compressed size in the second local header
+        bytes[44 + 18..44 + 22].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let archive = SeekArchive::new(Cursor::new(bytes)).unwrap();
+        let entry = archive.directory_entries().nth(1).unwrap().unwrap();
+        let mut buffer = Vec::new();
+
+        assert!(matches!(
+            archive.read_local_file(&entry, &mut buffer),
+            Err(ArchiveError::LocalFileOutOfRange { offset: 44 })
+        ));
+    }
+
+    #[test]
+    fn oversized_directory_rejected() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        let eocd = bytes.len() - END_OF_CENTRAL_DIRECTORY_MIN_SIZE;
+        bytes[eocd + 12..eocd + 16].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+
+        assert!(matches!(
+            SeekArchive::new(Cursor::new(bytes)),
+            Err(ArchiveError::DirectoryOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn data_descriptor_local_file() {
+        let bytes = include_bytes!("../../assets/data_descriptor.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let entry = archive.directory_entries().next().unwrap().unwrap();
+        let mut buffer = Vec::new();
+        let local = archive.read_local_file(&entry, &mut buffer).unwrap();
+
+        assert_eq!(b"hello", local.compressed_data.bytes());
+        assert_eq!(entry.crc32, local.compressed_data.crc32());
+    }
+
+    #[test]
+    fn last_split_segment_partial() {
+        let bytes = include_bytes!("../../assets/split.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let entries = archive
+            .directory_entries()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let mut buffer = Vec::new();
+
+        assert!(matches!(
+            archive.read_local_file(&entries[0], &mut buffer),
+            Err(ArchiveError::MissingDisk { disk: 0 })
+        ));
+        let local = archive.read_local_file(&entries[2], &mut buffer).unwrap();
+        assert_eq!(b"last\n", local.compressed_data.bytes());
+    }
+
+    #[test]
+    fn hello_world_store_with_comment() {
+        let bytes = include_bytes!("../../assets/hello_world_store_with_comment.zip");
+        let archive = SeekArchive::new(Cursor::new(&bytes[..])).unwrap();
+        let eocd = archive.end_of_central_directory();
+
+        assert_eq!(b"tricky", eocd.comment.as_ref());
+    }
+
+    #[test]
+    fn self_extracting_prefix() {
+        let mut bytes = b"stub".to_vec();
+        bytes.extend_from_slice(include_bytes!("../../assets/two_files_store.zip"));
+        let archive = SeekArchive::new(Cursor::new(bytes)).unwrap();
+        let entry = archive.directory_entries().nth(1).unwrap().unwrap();
+        let mut buffer = Vec::new();
+        let local = archive.read_local_file(&entry, &mut buffer).unwrap();
+
+        assert_eq!(b"stub", &archive.read_prefix().unwrap()[..]);
+        assert_eq!(entry.file_name, local.file_name);
+    }
+
+    #[test]
+    fn invalid_directory_entry_located() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        // The signature of the second central directory header
+        bytes[0xb4] = 0;
+        let archive = SeekArchive::new(Cursor::new(bytes)).unwrap();
+        let mut entries = archive.directory_entries();
+
+        assert!(entries.next().unwrap().is_ok());
+        match entries.next() {
+            Some(Err(ArchiveError::InvalidDirectoryEntry(e))) => {
+                assert_eq!(0xb4, e.offset);
+                assert_eq!(Record::CentralDirectoryHeader(1), e.record);
+            }
+            _ => panic!("expected an invalid directory entry"),
+        }
+    }
+}
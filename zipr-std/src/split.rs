@@ -0,0 +1,33 @@
+use std::path::{Path, PathBuf};
+
+/// The file holding a disk before the last one of a split archive,
+/// eg archive.z01 for the first disk of archive.zip
+pub fn segment_path<P: AsRef<Path>>(last: P, disk: u16) -> PathBuf {
+    let number = disk as u32 + 1;
+    last.as_ref().with_extension(format!("z{:02}", number))
+}
+
+/// The files holding the disks before the last one of a split archive,
+/// eg archive.z01 and archive.z02 for archive.zip on the third disk
+pub fn segment_paths<P: AsRef<Path>>(last: P, disk: u16) -> Vec<PathBuf> {
+    (0..disk).map(|x| segment_path(&last, x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_before_third_disk() {
+        let paths = segment_paths("dir/archive.zip", 2);
+
+        assert_eq!(
+            vec![
+                PathBuf::from("dir/archive.z01"),
+                PathBuf::from("dir/archive.z02")
+            ],
+            paths
+        );
+        assert!(segment_paths("archive.zip", 0).is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    cmp::{max, min},
+    io::{self, Cursor, Read},
+    mem,
+    ops::Range,
+};
+
+use nom::{IResult, Needed, Offset};
+use zipr_data::{
+    borrowed::file::{CompressedData, LocalFileEntry},
+    owned::{CompressedDataBuf, ZipEntryBuf},
+};
+use zipr_domain::local_zip_entry;
+use zipr_nom::{
+    error::{ParseFailure, Record, ZipError},
+    stream::{parse_streaming_entry, parse_streaming_header},
+};
+
+use crate::{ArchiveError, DecompressReader};
+
+/// How much to read when the amount needed isn't known,
+/// and the most read at once when it is
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Reads the entries of an archive forwards from any Read, eg a pipe
+///
+/// The central directory isn't needed, entries come from their local
+/// headers, so attributes like permissions aren't available. Entries
+/// using a data descriptor are found by scanning for it, so are held in
+/// memory. As an iterator every entry is held in memory, next_streamed
+/// reads the data of the others as it is used instead. The crc32 is
+/// checked when the data is decompressed
+pub struct ZipStream<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    /// The bytes before the buffer, to locate failures
+    consumed: u64,
+    /// Data of the last streamed entry that hasn't been read yet
+    unread: u64,
+    index: usize,
+    done: bool,
+}
+
+/// An entry from ZipStream::next_streamed
+pub struct StreamedEntry<'a, R> {
+    /// The local header. Its compressed data is empty, as it is read by reader
+    pub entry: ZipEntryBuf,
+    /// The uncompressed contents
+    pub reader: DecompressReader<StreamedData<'a, R>>,
+}
+
+/// The compressed data of a StreamedEntry
+pub struct StreamedData<'a, R> {
+    source: Source<'a, R>,
+}
+
+enum Source<'a, R> {
+    /// Found by scanning for a data descriptor
+    Buffered(Cursor<Vec<u8>>),
+    /// Read from the stream, up to the size in the local header
+    Streamed(&'a mut ZipStream<R>),
+}
+
+impl<R: Read> Read for StreamedData<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.source {
+            Source::Buffered(data) => data.read(buf),
+            Source::Streamed(stream) => stream.read_unread(buf),
+        }
+    }
+}
+
+impl<R: Read> ZipStream<R> {
+    pub fn new(reader: R) -> Self {
+        ZipStream {
+            reader,
+            buffer: Vec::new(),
+            consumed: 0,
+            unread: 0,
+            index: 0,
+            done: false,
+        }
+    }
+
+    /// Gives back the underlying reader.
+    /// Anything buffered, eg the central directory, is lost
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The next entry, with a reader for its uncompressed data. Data of a
+    /// known size is read from the stream as it is used, rather than held
+    /// in memory. Whatever isn't read is skipped before the next entry
+    pub fn next_streamed(&mut self) -> Option<Result<StreamedEntry<'_, R>, ArchiveError>> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_header();
+        match next {
+            Ok(Some(_)) => self.index += 1,
+            _ => self.done = true,
+        }
+        let (mut entry, streamed) = match next.transpose()? {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let data = &entry.compressed_data;
+        let (method, uncompressed_size, crc32) = (
+            data.compression_method(),
+            data.uncompressed_size(),
+            data.crc32(),
+        );
+        let empty = CompressedDataBuf::create_unchecked(uncompressed_size, method, crc32, vec![]);
+        let bytes = mem::replace(&mut entry.compressed_data, empty).into_bytes();
+        let (source, compressed_size) = match streamed {
+            Some(size) => {
+                self.unread = size;
+                (Source::Streamed(self), size)
+            }
+            None => {
+                let size = bytes.len() as u64;
+                (Source::Buffered(Cursor::new(bytes)), size)
+            }
+        };
+        let data = StreamedData { source };
+        let reader = DecompressReader::new(data, method, compressed_size, uncompressed_size, crc32);
+        Some(Ok(StreamedEntry { entry, reader }))
+    }
+
+    /// Reads up to more bytes, false if the end was already reached
+    fn fill(&mut self, more: usize) -> io::Result<bool> {
+        let start = self.buffer.len();
+        self.buffer.resize(start + more, 0);
+        let mut filled = start;
+        let result = loop {
+            if filled == self.buffer.len() {
+                break Ok(());
+            }
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(0) => break Ok(()),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buffer.truncate(filled);
+        result.map(|_| filled > start)
+    }
+
+    /// Reads until the parser has enough input,
+    /// giving how much of the buffer it used and its result
+    fn parse<T, P>(&mut self, parser: P) -> Result<(usize, T), ArchiveError>
+    where
+        P: for<'b> Fn(&'b [u8]) -> IResult<&'b [u8], T, ParseFailure<'b>>,
+    {
+        let record = Record::LocalFileHeader(self.index);
+        loop {
+            let needed = match parser(&self.buffer) {
+                Ok((rest, result)) => return Ok((self.buffer.len() - rest.len(), result)),
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                    let error = ZipError::new(&self.buffer, record, e).offset_by(self.consumed);
+                    return Err(ArchiveError::InvalidLocalFile(error));
+                }
+                Err(nom::Err::Incomplete(needed)) => needed,
+            };
+            // A hostile size can't make this allocate more than a chunk at a
+            // time. Grows with the buffer when scanning for a data descriptor,
+            // so that doesn't repeat too often
+            let more = match needed {
+                Needed::Size(size) => min(size.get(), CHUNK_SIZE),
+                Needed::Unknown => max(CHUNK_SIZE, self.buffer.len()),
+            };
+            if !self.fill(more)? {
+                let end = self.consumed + self.buffer.len() as u64;
+                return Err(ArchiveError::InvalidLocalFile(ZipError::out_of_range(
+                    end, record,
+                )));
+            }
+        }
+    }
+
+    /// Parses the next local header. When the size of the data is only in
+    /// a data descriptor the whole entry is parsed, otherwise the size of
+    /// the data left in the stream is given
+    fn next_header(&mut self) -> Result<Option<(ZipEntryBuf, Option<u64>)>, ArchiveError> {
+        self.skip_unread()?;
+        let (used, header) = self.parse(|input| {
+            let (rest, header) = parse_streaming_header(input)?;
+            let header = header.map(|(entry, size)| (local_zip_entry(&entry).to_owned(), size));
+            Ok((rest, header))
+        })?;
+        match header {
+            None => Ok(None),
+            Some((entry, Some(size))) => {
+                self.buffer.drain(..used);
+                self.consumed += used as u64;
+                Ok(Some((entry, Some(size.into()))))
+            }
+            Some((_, None)) => Ok(self.next_entry()?.map(|entry| (entry, None))),
+        }
+    }
+
+    /// Parses the next entry along with its data
+    fn next_entry(&mut self) -> Result<Option<ZipEntryBuf>, ArchiveError> {
+        self.skip_unread()?;
+        let (used, entry) = self.parse(|input| {
+            let (rest, entry) = parse_streaming_entry(input)?;
+            Ok((rest, entry.map(|x| without_data(input, &x))))
+        })?;
+        let (mut entry, range) = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        // Copies whichever of the data and the bytes read after it is smaller
+        let bytes = if range.len() < self.buffer.len() - used {
+            let bytes = self.buffer[range].to_vec();
+            self.buffer.drain(..used);
+            bytes
+        } else {
+            let rest = self.buffer.split_off(used);
+            let mut bytes = mem::replace(&mut self.buffer, rest);
+            bytes.truncate(range.end);
+            bytes.drain(..range.start);
+            bytes
+        };
+        self.consumed += used as u64;
+        let data = &entry.compressed_data;
+        entry.compressed_data = CompressedDataBuf::create_unchecked(
+            data.uncompressed_size(),
+            data.compression_method(),
+            data.crc32(),
+            bytes,
+        );
+        Ok(Some(entry))
+    }
+
+    /// Reads the data of the last streamed entry, starting with what is buffered
+    fn read_unread(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let limit = min(buf.len() as u64, self.unread) as usize;
+        let read = if self.buffer.is_empty() {
+            self.reader.read(&mut buf[..limit])?
+        } else {
+            let length = min(limit, self.buffer.len());
+            buf[..length].copy_from_slice(&self.buffer[..length]);
+            self.buffer.drain(..length);
+            length
+        };
+        self.unread -= read as u64;
+        self.consumed += read as u64;
+        Ok(read)
+    }
+
+    /// Skips whatever wasn't read of the last streamed entry's data
+    fn skip_unread(&mut self) -> io::Result<()> {
+        let buffered = min(self.unread, self.buffer.len() as u64);
+        self.buffer.drain(..buffered as usize);
+        let skipped = io::copy(
+            &mut (&mut self.reader).take(self.unread - buffered),
+            &mut io::sink(),
+        )?;
+        self.consumed += buffered + skipped;
+        self.unread = 0;
+        Ok(())
+    }
+}
+
+/// The entry without its data, which is copied separately,
+/// and where the data is within input
+fn without_data(input: &[u8], local: &LocalFileEntry<'_>) -> (ZipEntryBuf, Range<usize>) {
+    let data = local.compressed_data;
+    let start = input.offset(data.bytes());
+    let mut entry = local_zip_entry(local);
+    entry.compressed_data = CompressedData::create_unchecked(
+        data.uncompressed_size(),
+        data.compression_method(),
+        data.crc32(),
+        &[],
+    );
+    (entry.to_owned(), start..start + data.bytes().len())
+}
+
+impl<R: Read> Iterator for ZipStream<R> {
+    type Item = Result<ZipEntryBuf, ArchiveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_entry();
+        match next {
+            Ok(Some(_)) => self.index += 1,
+            _ => self.done = true,
+        }
+        next.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use zipr_data::borrowed::AsSymbols;
+
+    use super::*;
+    use crate::EntryReader;
+
+    /// Gives out a few bytes at a time, like a slow pipe
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let length = buf.len().min(self.0.len()).min(7);
+            buf[..length].copy_from_slice(&self.0[..length]);
+            self.0 = &self.0[length..];
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn data_descriptor_zip() {
+        let bytes = include_bytes!("../../assets/data_descriptor.zip");
+        let entries = ZipStream::new(Trickle(bytes))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(2, entries.len());
+        let entry = entries[1].as_borrowed();
+        let name = entry.file_name.to_utf8().collect::<String>();
+        assert_eq!("moredata.txt", name);
+        let mut contents = Vec::new();
+        EntryReader::new(entry.compressed_data)
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(b"more data, more data, more data\n".repeat(4), contents);
+    }
+
+    #[test]
+    fn streamed_entries() {
+        let bytes = include_bytes!("../../assets/data_descriptor.zip");
+        let mut stream = ZipStream::new(Trickle(bytes));
+        let mut contents = Vec::new();
+        while let Some(entry) = stream.next_streamed() {
+            let mut entry = entry.unwrap();
+            assert!(entry.entry.compressed_data.bytes().is_empty());
+            entry.reader.read_to_end(&mut contents).unwrap();
+        }
+        let expected = [
+            &b"hello"[..],
+            &b"more data, more data, more data\n".repeat(4),
+        ]
+        .concat();
+        assert_eq!(expected, contents);
+
+        let bytes = include_bytes!("../../assets/two_files_store.zip");
+        let mut stream = ZipStream::new(Trickle(bytes));
+        // The first entry's data is skipped without being read
+        stream.next_streamed().unwrap().unwrap();
+        let mut entry = stream.next_streamed().unwrap().unwrap();
+        let name = entry
+            .entry
+            .file_name
+            .as_borrowed()
+            .to_utf8()
+            .collect::<String>();
+        assert_eq!("moredata.txt", name);
+        let mut contents = Vec::new();
+        entry.reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(b"nom", contents.as_slice());
+        assert!(stream.next_streamed().is_none());
+    }
+
+    #[test]
+    fn hostile_size() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip")[..0x2c].to_vec();
+        bytes[18..22].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+
+        // Read in chunks, so this fails at the end rather than allocating
+        let mut entries = ZipStream::new(Cursor::new(&bytes));
+        assert!(matches!(
+            entries.next(),
+            Some(Err(ArchiveError::InvalidLocalFile(_)))
+        ));
+
+        let mut stream = ZipStream::new(Cursor::new(&bytes));
+        let mut entry = stream.next_streamed().unwrap().unwrap();
+        let mut contents = Vec::new();
+        let result = entry.reader.read_to_end(&mut contents);
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = include_bytes!("../../assets/data_descriptor.zip");
+        let mut entries = ZipStream::new(Cursor::new(&bytes[..0x40]));
+
+        assert!(entries.next().unwrap().is_ok());
+        match entries.next() {
+            Some(Err(ArchiveError::InvalidLocalFile(e))) => {
+                assert_eq!(Record::LocalFileHeader(1), e.record);
+                assert_eq!(0x40, e.offset);
+            }
+            _ => panic!("expected the second entry to be cut short"),
+        }
+        assert!(entries.next().is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike, Utc};
+use zipr_data::{
+    borrowed::extra_field::ExtraField, borrowed::ZipEntry, DosDateTime, DosDateTimeError,
+    WinTimestamp, WinTimestampCreateError,
+};
+use zipr_domain::ZipEntryBuilder;
+use zipr_nom::data::extra_subfields;
+
+/// The times to apply to an extracted file
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) struct EntryTimes {
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+}
+
+fn from_unix(seconds: i32) -> Option<SystemTime> {
+    let magnitude = Duration::from_secs(seconds.unsigned_abs() as u64);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(magnitude)
+    } else {
+        UNIX_EPOCH.checked_sub(magnitude)
+    }
+}
+
+/// Conversion from a system time to the local dos date and time
+pub trait ToDosDateTime {
+    /// Fails for times outside of 1980-2107. Odd seconds are rounded down
+    fn to_dos_date_time(&self) -> Result<DosDateTime, DosDateTimeError>;
+    /// Times outside of 1980-2107 become the nearest representable value
+    fn to_dos_date_time_clamped(&self) -> DosDateTime;
+}
+
+impl ToDosDateTime for SystemTime {
+    fn to_dos_date_time(&self) -> Result<DosDateTime, DosDateTimeError> {
+        let local: DateTime<Local> = (*self).into();
+        DosDateTime::try_from(local.naive_local())
+    }
+
+    fn to_dos_date_time_clamped(&self) -> DosDateTime {
+        let local: DateTime<Local> = (*self).into();
+        let (date, time) = (local.date_naive(), local.time());
+        DosDateTime::clamped(
+            date.year(),
+            date.month() as u8,
+            date.day() as u8,
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+        )
+        .unwrap_or(DosDateTime::MIN)
+    }
+}
+
+/// Conversion from a zip timestamp to a system time
+pub trait ToSystemTime {
+    type Error;
+    fn to_system_time(&self) -> Result<SystemTime, Self::Error>;
+}
+
+/// Dos times have no timezone, so are treated as local time.
+/// A time repeated by a daylight savings change takes the earlier instant
+impl ToSystemTime for DosDateTime {
+    type Error = DosDateTimeError;
+
+    fn to_system_time(&self) -> Result<SystemTime, DosDateTimeError> {
+        let naive = NaiveDateTime::try_from(*self)?;
+        let local = Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or(DosDateTimeError::NonexistentLocalTime)?;
+        Ok(local.into())
+    }
+}
+
+impl ToSystemTime for WinTimestamp {
+    type Error = WinTimestampCreateError;
+
+    fn to_system_time(&self) -> Result<SystemTime, WinTimestampCreateError> {
+        let (seconds, nanos) = self.to_unix();
+        let time = match u64::try_from(seconds) {
+            Ok(after) => UNIX_EPOCH.checked_add(Duration::new(after, nanos)),
+            Err(_) => UNIX_EPOCH
+                .checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+                .and_then(|x| x.checked_add(Duration::from_nanos(nanos.into()))),
+        };
+        time.ok_or(WinTimestampCreateError())
+    }
+}
+
+/// Conversion from a windows timestamp to a chrono utc date time
+pub trait ToDateTime {
+    fn to_date_time(&self) -> Result<DateTime<Utc>, WinTimestampCreateError>;
+}
+
+impl ToDateTime for WinTimestamp {
+    fn to_date_time(&self) -> Result<DateTime<Utc>, WinTimestampCreateError> {
+        let (seconds, nanos) = self.to_unix();
+        DateTime::from_timestamp(seconds, nanos).ok_or(WinTimestampCreateError())
+    }
+}
+
+/// Conversion to the 100ns windows timestamp used by the NTFS extra field
+pub trait ToWinTimestamp {
+    fn to_win_timestamp(&self) -> Result<WinTimestamp, WinTimestampCreateError>;
+}
+
+impl ToWinTimestamp for SystemTime {
+    fn to_win_timestamp(&self) -> Result<WinTimestamp, WinTimestampCreateError> {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(after) => {
+                let seconds =
+                    i64::try_from(after.as_secs()).map_err(|_| WinTimestampCreateError())?;
+                WinTimestamp::from_unix(seconds, after.subsec_nanos())
+            }
+            Err(before) => {
+                // Round towards the past, so the nanoseconds are positive
+                let before = before.duration();
+                let seconds =
+                    i64::try_from(before.as_secs()).map_err(|_| WinTimestampCreateError())?;
+                match before.subsec_nanos() {
+                    0 => WinTimestamp::from_unix(-seconds, 0),
+                    nanos => WinTimestamp::from_unix(-seconds - 1, 1_000_000_000 - nanos),
+                }
+            }
+        }
+    }
+}
+
+impl ToWinTimestamp for DateTime<Utc> {
+    fn to_win_timestamp(&self) -> Result<WinTimestamp, WinTimestampCreateError> {
+        WinTimestamp::from_unix(self.timestamp(), self.timestamp_subsec_nanos())
+    }
+}
+
+/// Sets a builder's modification time from the system clock
+pub trait ModifiedAt: Sized {
+    /// Times outside of 1980-2107 are clamped
+    fn modified_at(self, time: SystemTime) -> Self;
+
+    fn modified_now(self) -> Self {
+        self.modified_at(SystemTime::now())
+    }
+}
+
+impl ModifiedAt for ZipEntryBuilder<'_> {
+    fn modified_at(self, time: SystemTime) -> Self {
+        self.modified(time.to_dos_date_time_clamped())
+    }
+}
+
+/// Whole seconds since the unix epoch, if it fits in the extended timestamp
+pub(crate) fn to_unix(time: SystemTime) -> Option<i32> {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => i32::try_from(after.as_secs()).ok(),
+        Err(before) => i32::try_from(before.duration().as_secs()).ok().map(|x| -x),
+    }
+}
+
+/// Finds the most precise known subfield when the extra field holds several
+fn best_subfield(bytes: &[u8]) -> ExtraField<'_> {
+    let ntfs = extra_subfields(bytes).find(|x| matches!(x, ExtraField::NTFS(_)));
+    let timestamp =
+        || extra_subfields(bytes).find(|x| matches!(x, ExtraField::ExtendedTimestamp(_)));
+    ntfs.or_else(timestamp)
+        .unwrap_or(ExtraField::Unknown(bytes))
+}
+
+/// Picks the most precise times available for the entry.
+/// NTFS (100ns), then the extended timestamp (1s), then the dos fields (2s)
+pub(crate) fn entry_times(entry: &ZipEntry<'_>) -> EntryTimes {
+    let dos = || {
+        let dos = DosDateTime {
+            date: entry.file_modification_date,
+            time: entry.file_modification_time,
+        };
+        dos.to_system_time().ok()
+    };
+    let extra_field = match entry.extra_field {
+        ExtraField::Unknown(bytes) => best_subfield(bytes),
+        known => known,
+    };
+    match extra_field {
+        ExtraField::NTFS(ntfs) => EntryTimes {
+            modified: ntfs.mtime.to_system_time().ok().or_else(dos),
+            accessed: ntfs.atime.to_system_time().ok(),
+        },
+        ExtraField::ExtendedTimestamp(x) => EntryTimes {
+            modified: x.modify.and_then(from_unix).or_else(dos),
+            accessed: x.access.and_then(from_unix),
+        },
+        ExtraField::Unknown(_) => EntryTimes {
+            modified: dos(),
+            accessed: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dos_round_trip() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_001);
+        let dos = time.to_dos_date_time().unwrap();
+        let expected = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(Ok(expected), dos.to_system_time());
+    }
+
+    #[test]
+    fn dos_out_of_range() {
+        assert!(matches!(
+            UNIX_EPOCH.to_dos_date_time(),
+            Err(DosDateTimeError::YearOutOfRange(_))
+        ));
+        assert_eq!(DosDateTime::MIN, UNIX_EPOCH.to_dos_date_time_clamped());
+    }
+
+    #[test]
+    fn dos_invalid_stored_date() {
+        let feb_30 = DosDateTime {
+            date: zipr_data::DosDate::from_u16_unchecked(41 << 9 | 2 << 5 | 30),
+            time: DosDateTime::MIN.time,
+        };
+        assert!(feb_30.to_system_time().is_err());
+    }
+
+    #[test]
+    fn unix_seconds() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(Some(1_000_000_000), to_unix(time));
+        assert_eq!(Some(time), from_unix(1_000_000_000));
+    }
+
+    #[test]
+    fn win_timestamp_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_700);
+        let windows = time.to_win_timestamp().unwrap();
+        assert_eq!(Ok(time), windows.to_system_time());
+
+        let before = UNIX_EPOCH - Duration::new(10, 100);
+        let windows = before.to_win_timestamp().unwrap();
+        assert_eq!((-11, 999_999_900), windows.to_unix());
+        assert_eq!(Ok(before), windows.to_system_time());
+    }
+
+    #[test]
+    fn win_timestamp_chrono() {
+        let time = DateTime::from_timestamp(1_000_000_000, 500).unwrap();
+        let windows = time.to_win_timestamp().unwrap();
+        assert_eq!(Ok(time), windows.to_date_time());
+    }
+
+    #[test]
+    fn builder_modified_at() {
+        use zipr_data::borrowed::{file::CompressedData, ZipPath};
+        let name = ZipPath::try_from(&b"a"[..]).unwrap();
+        let data =
+            CompressedData::create_unchecked(0, zipr_data::CompressionMethod::Stored, 0, &[]);
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let entry = ZipEntryBuilder::new(name, data)
+            .modified_at(time)
+            .build()
+            .unwrap();
+        assert_eq!(
+            time.to_dos_date_time_clamped().date,
+            entry.file_modification_date
+        );
+    }
+
+    #[test]
+    fn timestamp_among_other_subfields() {
+        use zipr_data::borrowed::{file::CompressedData, ZipPath};
+        // Info-ZIP's extended timestamp, then its unix uid/gid
+        let extra = [
+            0x55, 0x54, 0x05, 0x00, 0x01, 0x00, 0xca, 0x9a, 0x3b, 0x75, 0x78, 0x0b, 0x00, 0x01,
+            0x04, 0xe8, 0x03, 0x00, 0x00, 0x04, 0xe8, 0x03, 0x00, 0x00,
+        ];
+        let name = ZipPath::try_from(&b"a"[..]).unwrap();
+        let data =
+            CompressedData::create_unchecked(0, zipr_data::CompressionMethod::Stored, 0, &[]);
+        let entry = ZipEntryBuilder::new(name, data)
+            .modified(DosDateTime::MIN)
+            .extra_field(ExtraField::Unknown(&extra))
+            .build()
+            .unwrap();
+        let expected = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        assert_eq!(Some(expected), entry_times(&entry).modified);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Display;
+
+use zipr_compression::{DecompressError, DecompressStream};
+use zipr_data::{
+    borrowed::{file::CompressedData, AsSymbols},
+    constants::GENERAL_PURPOSE_DATA_DESCRIPTOR,
+};
+use zipr_domain::{discrepancies, Discrepancy};
+use zipr_nom::error::Record;
+
+use crate::{ArchiveError, ZipArchive};
+
+const BUFFER_SIZE: usize = 32 * 1024;
+
+/// Which header an expected value came from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Header {
+    Central,
+    Local,
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Header::Central => f.write_str("central directory"),
+            Header::Local => f.write_str("local header"),
+        }
+    }
+}
+
+/// A reason an entry failed its test
+#[derive(Debug)]
+pub enum TestFailure {
+    /// The local header couldn't be read
+    Local(ArchiveError),
+    /// The local header differs from the central directory
+    Inconsistent(Discrepancy),
+    /// The compressed data runs past the end of the archive
+    DataOutOfRange { offset: u64, size: u32 },
+    /// The compressed data is corrupt
+    Decompress(DecompressError),
+    Crc32 {
+        header: Header,
+        expected: u32,
+        actual: u32,
+    },
+    Size {
+        header: Header,
+        expected: u32,
+        actual: u64,
+    },
+}
+
+impl Display for TestFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestFailure::Local(e) => write!(f, "{}", e),
+            TestFailure::Inconsistent(discrepancy) => write!(f, "{}", discrepancy),
+            TestFailure::DataOutOfRange { offset, size } => write!(
+                f,
+                "data at 0x{:x} (0x{:x} bytes) is outside the file",
+                offset, size
+            ),
+            TestFailure::Decompress(e) => write!(f, "corrupt data ({:?})", e),
+            TestFailure::Crc32 {
+                header,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "bad crc32 0x{:08x}, {} has 0x{:08x}",
+                actual, header, expected
+            ),
+            TestFailure::Size {
+                header,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "bad size {} bytes, {} has {} bytes",
+                actual, header, expected
+            ),
+        }
+    }
+}
+
+/// The outcome of testing one entry
+#[derive(Debug)]
+pub struct EntryTest {
+    pub index: usize,
+    pub name: String,
+    pub failures: Vec<TestFailure>,
+}
+
+impl EntryTest {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl<T: AsRef<[u8]>> ZipArchive<T> {
+    /// Decompresses the entry, checking the crc32 and size against both headers.
+    /// The headers are also compared, using the archive's consistency policy.
+    /// Only fails if the index doesn't exist, all other problems are reported
+    pub fn test_entry(&self, index: usize) -> Result<EntryTest, ArchiveError> {
+        let central = self.central_directory_entry(index)?;
+        let name = central.file_name.to_utf8().collect::<String>();
+        let mut failures = Vec::new();
+        let local = match self.local_file(&central, Record::LocalFileHeader(index)) {
+            Ok(local) => local,
+            Err(e) => {
+                failures.push(TestFailure::Local(e));
+                return Ok(EntryTest {
+                    index,
+                    name,
+                    failures,
+                });
+            }
+        };
+        let policy = self.policy();
+        failures.extend(
+            discrepancies(&central, &local)
+                .filter(|x| policy.rejects(x))
+                .map(TestFailure::Inconsistent),
+        );
+
+        // The local sizes may be zero when a data descriptor is used,
+        // so the data is read using the central directory
+        let size = central.compressed_size;
+        let data = match self.directory_sized_data(&central, &local) {
+            Ok(data) => data,
+            Err(offset) => {
+                failures.push(TestFailure::DataOutOfRange { offset, size });
+                return Ok(EntryTest {
+                    index,
+                    name,
+                    failures,
+                });
+            }
+        };
+        let data = CompressedData::create_unchecked(
+            central.uncompressed_size,
+            central.compression_method,
+            central.crc32,
+            data,
+        );
+
+        let mut stream = DecompressStream::new(data);
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let result = loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => break Ok(()),
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        match result {
+            Err(DecompressError::InvalidSize(expected, actual)) => {
+                failures.push(TestFailure::Size {
+                    header: Header::Central,
+                    expected,
+                    actual,
+                });
+            }
+            // Compared below, along with the local header
+            Ok(()) | Err(DecompressError::InvalidCrc(..)) => {}
+            Err(e) => failures.push(TestFailure::Decompress(e)),
+        }
+
+        // Only known once the whole stream was read
+        if let Some(crc32) = stream.crc32() {
+            let written = stream.written();
+            if crc32 != central.crc32 {
+                failures.push(TestFailure::Crc32 {
+                    header: Header::Central,
+                    expected: central.crc32,
+                    actual: crc32,
+                });
+            }
+            let deferred = local.general_purpose & GENERAL_PURPOSE_DATA_DESCRIPTOR != 0;
+            let local_data = local.compressed_data;
+            if !(deferred && local_data.crc32() == 0) && local_data.crc32() != crc32 {
+                failures.push(TestFailure::Crc32 {
+                    header: Header::Local,
+                    expected: local_data.crc32(),
+                    actual: crc32,
+                });
+            }
+            let expected = local_data.uncompressed_size();
+            if !(deferred && expected == 0) && expected as u64 != written {
+                failures.push(TestFailure::Size {
+                    header: Header::Local,
+                    expected,
+                    actual: written,
+                });
+            }
+        }
+
+        Ok(EntryTest {
+            index,
+            name,
+            failures,
+        })
+    }
+
+    /// Tests every entry in central directory order
+    pub fn test(&self) -> impl Iterator<Item = EntryTest> + '_ {
+        (0..self.len()).filter_map(move |index| self.test_entry(index).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::Offset;
+
+    use super::*;
+
+    #[test]
+    fn hello_world_deflate_passes() {
+        let bytes = include_bytes!("../../assets/hello_world_deflate.zip");
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let results = archive.test().collect::<Vec<_>>();
+
+        assert_eq!(1, results.len());
+        assert!(results[0].passed(), "{:?}", results[0].failures);
+    }
+
+    #[test]
+    fn corrupt_data_fails_both_headers() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        let archive = ZipArchive::new(bytes.clone()).unwrap();
+        let start = archive
+            .bytes()
+            .offset(archive.by_index(0).unwrap().compressed_data.bytes());
+        bytes[start] ^= 0xff;
+
+        let archive = ZipArchive::new(bytes).unwrap();
+        let result = archive.test_entry(0).unwrap();
+
+        assert!(!result.passed());
+        assert!(matches!(
+            result.failures[..],
+            [
+                TestFailure::Crc32 {
+                    header: Header::Central,
+                    ..
+                },
+                TestFailure::Crc32 {
+                    header: Header::Local,
+                    ..
+                }
+            ]
+        ));
+        assert!(archive.test_entry(1).unwrap().passed());
+    }
+
+    #[test]
+    fn inconsistent_headers_reported() {
+        let mut bytes = include_bytes!("../../assets/two_files_store.zip").to_vec();
+        bytes[30] = b'X';
+        let archive = ZipArchive::new(&bytes[..]).unwrap();
+        let result = archive.test_entry(0).unwrap();
+
+        assert!(matches!(
+            result.failures[..],
+            [TestFailure::Inconsistent(Discrepancy::FileName)]
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    convert::TryFrom,
+    fmt::Display,
+    fs::Metadata,
+    io,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+use glob::{Pattern, PatternError};
+use zipr_data::{
+    borrowed::{
+        extra_field::{extended_timestamp::ExtendedTimestamp, ExtraField},
+        file::CompressedData,
+        OEM437Str, OEM437Symbols, ZipEntry, ZipPath,
+    },
+    constants::UNIX_SYMLINK,
+    DosDateTime,
+};
+use zipr_domain::{ZipEntryBuildError, ZipEntryBuilder};
+
+use crate::times::{to_unix, ModifiedAt};
+
+/// What to do when the walk finds a symlink
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Symlinks {
+    /// Add whatever the link points to, as if it were there
+    #[default]
+    Follow,
+    /// Add the link itself, with the target as its data
+    Store,
+    /// Leave links out of the archive
+    Skip,
+}
+
+impl FromStr for Symlinks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "follow" => Ok(Symlinks::Follow),
+            "store" => Ok(Symlinks::Store),
+            "skip" => Ok(Symlinks::Skip),
+            _ => Err(format!("Unknown symlink mode {}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WalkError {
+    /// The glob couldn't be parsed
+    Pattern(PatternError),
+    /// The path can't be named relative to the base
+    OutsideBase(PathBuf),
+    /// The name has characters that can't be stored in the archive
+    Name(String),
+    /// Following the symlink leads back to one of its parents
+    SymlinkLoop(PathBuf),
+    /// The metadata doesn't make a valid entry
+    Entry(ZipEntryBuildError),
+    IO(io::Error),
+}
+
+impl Display for WalkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkError::Pattern(e) => write!(f, "Invalid pattern: {}", e),
+            WalkError::OutsideBase(path) => {
+                write!(f, "{} is outside the base folder", path.display())
+            }
+            WalkError::Name(name) => write!(f, "{} can't be stored as a zip name", name),
+            WalkError::SymlinkLoop(path) => write!(f, "Symlink loop at {}", path.display()),
+            WalkError::Entry(e) => write!(f, "{}", e),
+            WalkError::IO(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for WalkError {}
+
+impl From<io::Error> for WalkError {
+    fn from(e: io::Error) -> Self {
+        WalkError::IO(e)
+    }
+}
+
+impl From<PatternError> for WalkError {
+    fn from(e: PatternError) -> Self {
+        WalkError::Pattern(e)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WalkKind {
+    File,
+    Directory,
+    /// A stored symlink, with the path it points to
+    Symlink(PathBuf),
+}
+
+/// A file, folder or link found by the walker
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WalkEntry {
+    /// Where it is on disk
+    pub path: PathBuf,
+    /// The relative name, forward slashes and a trailing slash for folders
+    pub name: String,
+    pub kind: WalkKind,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    /// The unix mode, including the file type bits
+    pub mode: Option<u32>,
+    pub read_only: bool,
+}
+
+impl WalkEntry {
+    fn from_metadata(path: PathBuf, name: String, kind: WalkKind, meta: &Metadata) -> Self {
+        let mode = unix_mode(meta).or(match kind {
+            WalkKind::Symlink(_) => Some(UNIX_SYMLINK | 0o777),
+            _ => None,
+        });
+        WalkEntry {
+            path,
+            name,
+            kind,
+            modified: meta.modified().ok(),
+            accessed: meta.accessed().ok(),
+            mode,
+            read_only: meta.permissions().readonly(),
+        }
+    }
+
+    /// The uncompressed data for the entry.
+    /// The contents of files, the target of links, and nothing for folders
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        match &self.kind {
+            WalkKind::File => std::fs::read(&self.path),
+            WalkKind::Directory => Ok(Vec::new()),
+            WalkKind::Symlink(target) => {
+                Ok(target.to_string_lossy().replace('\\', "/").into_bytes())
+            }
+        }
+    }
+
+    /// The extended timestamp holding the modified and accessed times
+    pub fn extended_timestamp(&self) -> Option<ExtendedTimestamp> {
+        let modify = self.modified.and_then(to_unix);
+        let access = self.accessed.and_then(to_unix);
+        let flags = modify.map_or(0, |_| 0b01) | access.map_or(0, |_| 0b10);
+        if flags == 0 {
+            None
+        } else {
+            Some(ExtendedTimestamp {
+                flags,
+                modify,
+                access,
+                create: None,
+            })
+        }
+    }
+
+    /// Builds the zip entry for the already compressed data
+    pub fn to_zip_entry<'a>(
+        &'a self,
+        compressed_data: CompressedData<'a>,
+    ) -> Result<ZipEntry<'a>, WalkError> {
+        let symbols = OEM437Symbols::try_from(self.name.as_str())
+            .map_err(|_| WalkError::Name(self.name.clone()))?;
+        let str: OEM437Str = *symbols.as_ref();
+        let file_name = ZipPath::try_from(str).map_err(|_| WalkError::Name(self.name.clone()))?;
+
+        let builder = ZipEntryBuilder::new(file_name, compressed_data).read_only(self.read_only);
+        // Without a time from the file system, use the earliest dos time
+        let mut builder = match self.modified {
+            Some(modified) => builder.modified_at(modified),
+            None => builder.modified(DosDateTime::MIN),
+        };
+        if let Some(mode) = self.mode {
+            builder = builder.mode(mode);
+        }
+        if let Some(timestamp) = self.extended_timestamp() {
+            builder = builder.extra_field(ExtraField::ExtendedTimestamp(timestamp));
+        }
+        builder.build().map_err(WalkError::Entry)
+    }
+}
+
+#[cfg(unix)]
+fn unix_mode(meta: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.mode())
+}
+
+#[cfg(not(unix))]
+fn unix_mode(_meta: &Metadata) -> Option<u32> {
+    None
+}
+
+/// Recursively finds the files to add to an archive
+///
+/// Names are relative to the base, which defaults to the parent of the root,
+/// so walking `docs` gives `docs/`, `docs/a.txt` and so on.
+/// Folders come before their contents, which are sorted by name.
+///
+/// Include globs only apply to files and links, folders are always walked.
+/// Exclude globs apply to everything, and an excluded folder isn't walked.
+/// Globs are matched against the name without the trailing slash
+pub struct Walker {
+    root: PathBuf,
+    base: Option<PathBuf>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    symlinks: Symlinks,
+}
+
+impl Walker {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Walker {
+            root: root.into(),
+            base: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            symlinks: Symlinks::default(),
+        }
+    }
+
+    /// Sets the folder entry names are relative to
+    pub fn base<P: Into<PathBuf>>(mut self, base: P) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+
+    /// Only add files matching one of the include globs
+    pub fn include(mut self, glob: &str) -> Result<Self, WalkError> {
+        self.include.push(Pattern::new(glob)?);
+        Ok(self)
+    }
+
+    /// Leave out anything matching the glob
+    pub fn exclude(mut self, glob: &str) -> Result<Self, WalkError> {
+        self.exclude.push(Pattern::new(glob)?);
+        Ok(self)
+    }
+
+    /// Sets how symlinks are handled
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Walks the root, returning everything to add in order
+    pub fn walk(&self) -> Result<Vec<WalkEntry>, WalkError> {
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => self
+                .root
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        };
+        let mut entries = Vec::new();
+        let mut parents = Vec::new();
+        self.visit(&base, &self.root, &mut parents, &mut entries)?;
+        Ok(entries)
+    }
+
+    /// The forward slash name of path relative to the base
+    fn name(&self, base: &Path, path: &Path) -> Result<String, WalkError> {
+        let relative = path
+            .strip_prefix(base)
+            .map_err(|_| WalkError::OutsideBase(path.to_path_buf()))?;
+        let parts = relative
+            .components()
+            .filter(|x| *x != Component::CurDir)
+            .map(|x| match x {
+                Component::Normal(part) => Ok(part.to_string_lossy()),
+                _ => Err(WalkError::OutsideBase(path.to_path_buf())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(parts.join("/"))
+    }
+
+    fn excluded(&self, name: &str) -> bool {
+        self.exclude.iter().any(|x| x.matches(name))
+    }
+
+    fn included(&self, name: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|x| x.matches(name))
+    }
+
+    fn visit(
+        &self,
+        base: &Path,
+        path: &Path,
+        parents: &mut Vec<PathBuf>,
+        entries: &mut Vec<WalkEntry>,
+    ) -> Result<(), WalkError> {
+        let name = self.name(base, path)?;
+        if !name.is_empty() && self.excluded(&name) {
+            return Ok(());
+        }
+
+        let link = path.symlink_metadata()?;
+        let meta = if link.file_type().is_symlink() {
+            match self.symlinks {
+                Symlinks::Skip => return Ok(()),
+                Symlinks::Store => {
+                    if self.included(&name) {
+                        let target = std::fs::read_link(path)?;
+                        let kind = WalkKind::Symlink(target);
+                        let entry = WalkEntry::from_metadata(path.into(), name, kind, &link);
+                        entries.push(entry);
+                    }
+                    return Ok(());
+                }
+                Symlinks::Follow => path.metadata()?,
+            }
+        } else {
+            link
+        };
+
+        if !meta.is_dir() {
+            if self.included(&name) {
+                let entry = WalkEntry::from_metadata(path.into(), name, WalkKind::File, &meta);
+                entries.push(entry);
+            }
+            return Ok(());
+        }
+
+        let canonical = path.canonicalize()?;
+        if parents.contains(&canonical) {
+            return Err(WalkError::SymlinkLoop(path.to_path_buf()));
+        }
+        if !name.is_empty() {
+            let name = format!("{}/", name);
+            let entry = WalkEntry::from_metadata(path.into(), name, WalkKind::Directory, &meta);
+            entries.push(entry);
+        }
+
+        let mut children = std::fs::read_dir(path)?
+            .map(|x| x.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        children.sort();
+        parents.push(canonical);
+        for child in children.iter() {
+            self.visit(base, child, parents, entries)?;
+        }
+        parents.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(entries: &[WalkEntry]) -> Vec<&str> {
+        entries.iter().map(|x| x.name.as_str()).collect()
+    }
+
+    fn sample() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.txt"), b"a").unwrap();
+        std::fs::write(root.join("b.log"), b"b").unwrap();
+        std::fs::write(root.join("sub").join("c.txt"), b"c").unwrap();
+        dir
+    }
+
+    #[test]
+    fn walks_in_order() {
+        let dir = sample();
+        let entries = Walker::new(dir.path().join("root")).walk().unwrap();
+        assert_eq!(
+            vec![
+                "root/",
+                "root/a.txt",
+                "root/b.log",
+                "root/sub/",
+                "root/sub/c.txt"
+            ],
+            names(&entries)
+        );
+        assert_eq!(WalkKind::Directory, entries[0].kind);
+        assert_eq!(b"a", &entries[1].read().unwrap()[..]);
+    }
+
+    #[test]
+    fn relative_to_base() {
+        let dir = sample();
+        let root = dir.path().join("root");
+        let entries = Walker::new(root.join("sub")).base(&root).walk().unwrap();
+        assert_eq!(vec!["sub/", "sub/c.txt"], names(&entries));
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let dir = sample();
+        let entries = Walker::new(dir.path().join("root"))
+            .include("*.txt")
+            .unwrap()
+            .exclude("root/sub")
+            .unwrap()
+            .walk()
+            .unwrap();
+        assert_eq!(vec!["root/", "root/a.txt"], names(&entries));
+    }
+
+    #[test]
+    fn fills_zip_entry() {
+        let dir = sample();
+        let entries = Walker::new(dir.path().join("root")).walk().unwrap();
+        let data =
+            CompressedData::create_unchecked(1, zipr_data::CompressionMethod::Stored, 0, b"a");
+        let entry = entries[1].to_zip_entry(data).unwrap();
+        assert!(matches!(
+            entry.extra_field,
+            ExtraField::ExtendedTimestamp(_)
+        ));
+        assert_ne!(0, entry.file_modification_date.as_bytes());
+        assert!(matches!(
+            entries[0].to_zip_entry(data),
+            Err(WalkError::Entry(ZipEntryBuildError::DirectoryWithData))
+        ));
+
+        let empty =
+            CompressedData::create_unchecked(0, zipr_data::CompressionMethod::Stored, 0, &[]);
+        let directory = entries[0].to_zip_entry(empty).unwrap();
+        use zipr_data::constants::EXTERNAL_ATTRIBUTE_DOS_DIRECTORY;
+        assert_eq!(
+            EXTERNAL_ATTRIBUTE_DOS_DIRECTORY,
+            directory.external_file_attributes & EXTERNAL_ATTRIBUTE_DOS_DIRECTORY
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_modes() {
+        let dir = sample();
+        let root = dir.path().join("root");
+        std::os::unix::fs::symlink("a.txt", root.join("link")).unwrap();
+
+        let skip = Walker::new(&root).symlinks(Symlinks::Skip).walk().unwrap();
+        assert!(!names(&skip).contains(&"root/link"));
+
+        let store = Walker::new(&root).symlinks(Symlinks::Store).walk().unwrap();
+        let link = store.iter().find(|x| x.name == "root/link").unwrap();
+        assert_eq!(WalkKind::Symlink("a.txt".into()), link.kind);
+        assert_eq!(Some(UNIX_SYMLINK), link.mode.map(|x| x & 0o170000));
+        assert_eq!(b"a.txt", &link.read().unwrap()[..]);
+
+        let follow = Walker::new(&root).walk().unwrap();
+        let link = follow.iter().find(|x| x.name == "root/link").unwrap();
+        assert_eq!(WalkKind::File, link.kind);
+        assert_eq!(b"a", &link.read().unwrap()[..]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loop() {
+        let dir = sample();
+        let root = dir.path().join("root");
+        std::os::unix::fs::symlink(&root, root.join("sub").join("up")).unwrap();
+        let result = Walker::new(&root).walk();
+        assert!(matches!(result, Err(WalkError::SymlinkLoop(_))));
+    }
+}